@@ -0,0 +1,361 @@
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::path::Path;
+
+use github_client::Label;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+
+use crate::cli::ReviewerSpec;
+
+/// The branch model applied after the repository is created: branches to
+/// create and protect, and deployment environments. Loaded from `--config`;
+/// service-* templates get [`GitflowConfig::service_default`] without one.
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct GitflowConfig {
+    #[serde(default)]
+    pub branches: Vec<BranchConfig>,
+    #[serde(default)]
+    pub environments: Vec<EnvironmentConfig>,
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct BranchConfig {
+    pub name: String,
+    /// Branch to create it from (default: the repository's default branch).
+    #[serde(default)]
+    pub from: Option<String>,
+    /// Protect the branch like the default branch; ignored with
+    /// `--protect-default-branch false`.
+    #[serde(default = "default_protect")]
+    pub protect: bool,
+    /// Status checks required on this branch (default: `--required-checks`).
+    #[serde(default)]
+    pub required_checks: Option<Vec<String>>,
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct EnvironmentConfig {
+    pub name: String,
+    /// Branch name patterns allowed to deploy, e.g. `release/*`.
+    #[serde(default)]
+    pub branches: Vec<String>,
+    /// Minutes to wait before a deployment proceeds.
+    #[serde(default)]
+    pub wait_timer: Option<u32>,
+    /// Required reviewers as `user:<login>` or `team:<org>/<slug>`.
+    #[serde(default)]
+    pub reviewers: Vec<ReviewerSpec>,
+}
+
+/// Labels to create or update on the new repository, loaded from
+/// `--labels-file`: a list of `name`, `color` and optional `description`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LabelsFile(pub Vec<Label>);
+
+impl LabelsFile {
+    /// Reads a labels file, as YAML or JSON like [`GitflowConfig::load`].
+    pub fn load(path: &str) -> Result<Self, String> {
+        let labels: Vec<Label> = read_file(path)?;
+        for label in &labels {
+            let color = label.color.trim_start_matches('#');
+            if label.name.trim().is_empty() {
+                return Err(format!("invalid '{}': label name must not be empty", path));
+            }
+            if color.len() != 6 || !color.chars().all(|c| c.is_ascii_hexdigit()) {
+                return Err(format!(
+                    "invalid '{}': label '{}' has color '{}', expected six hex digits",
+                    path, label.name, label.color
+                ));
+            }
+        }
+        Ok(Self(labels))
+    }
+}
+
+/// Repositories to provision in one run, loaded from `--manifest`: a list of
+/// entries in YAML or JSON like [`GitflowConfig::load`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Manifest(pub Vec<ManifestEntry>);
+
+/// One repository of a [`Manifest`]. Fields left out fall back to the
+/// matching create flag (`--repo-desc`, `--repo-type`, `--template-name`).
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct ManifestEntry {
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    /// `public` or `private`.
+    #[serde(default)]
+    pub visibility: Option<String>,
+    /// Template repository as `owner/repo`.
+    #[serde(default)]
+    pub template: Option<String>,
+    /// Topics added to the `--topic` ones.
+    #[serde(default)]
+    pub topics: Vec<String>,
+    /// Actions repository variables added to the `--var` ones.
+    #[serde(default)]
+    pub vars: BTreeMap<String, String>,
+}
+
+impl Manifest {
+    /// Reads a manifest and checks the whole of it before anything is created.
+    pub fn load(path: &str) -> Result<Self, String> {
+        let manifest = Self(read_file(path)?);
+        manifest
+            .validate()
+            .map_err(|e| format!("invalid '{}': {}", path, e))?;
+        Ok(manifest)
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if self.0.is_empty() {
+            return Err("the manifest lists no repositories".to_string());
+        }
+        // GitHub repository names are case-insensitive
+        let mut seen: HashSet<String> = HashSet::new();
+        for entry in &self.0 {
+            if entry.name.trim().is_empty() {
+                return Err("repository name must not be empty".to_string());
+            }
+            if !seen.insert(entry.name.to_lowercase()) {
+                return Err(format!(
+                    "repository '{}' is listed more than once",
+                    entry.name
+                ));
+            }
+            if let Some(visibility) = &entry.visibility {
+                if visibility != "public" && visibility != "private" {
+                    return Err(format!(
+                        "repository '{}' has visibility '{}', expected public or private",
+                        entry.name, visibility
+                    ));
+                }
+            }
+            if let Some(template) = &entry.template {
+                if !matches!(template.split_once('/'), Some((o, r)) if !o.is_empty() && !r.is_empty())
+                {
+                    return Err(format!(
+                        "repository '{}' has template '{}', expected owner/repo",
+                        entry.name, template
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Parses `path` as JSON when it ends in `.json`, as YAML otherwise.
+fn read_file<T: DeserializeOwned>(path: &str) -> Result<T, String> {
+    let text = fs::read_to_string(path).map_err(|e| format!("cannot read '{}': {}", path, e))?;
+    let is_json = Path::new(path)
+        .extension()
+        .map(|ext| ext.eq_ignore_ascii_case("json"))
+        .unwrap_or(false);
+    if is_json {
+        serde_json::from_str(&text).map_err(|e| format!("invalid '{}': {}", path, e))
+    } else {
+        serde_yaml::from_str(&text).map_err(|e| format!("invalid '{}': {}", path, e))
+    }
+}
+
+fn default_protect() -> bool {
+    true
+}
+
+impl GitflowConfig {
+    /// Reads a config file; `.json` files are parsed as JSON, anything else
+    /// as YAML.
+    pub fn load(path: &str) -> Result<Self, String> {
+        let config: Self = read_file(path)?;
+        config
+            .validate()
+            .map_err(|e| format!("invalid '{}': {}", path, e))?;
+        Ok(config)
+    }
+
+    /// What service-* templates have always got: a protected `dev` branch, a
+    /// `dev` environment for dev, feature and hotfix branches, and a `release`
+    /// environment for release branches and main.
+    pub fn service_default() -> Self {
+        Self {
+            branches: vec![BranchConfig {
+                name: "dev".to_string(),
+                from: None,
+                protect: true,
+                required_checks: None,
+            }],
+            environments: vec![
+                EnvironmentConfig {
+                    name: "dev".to_string(),
+                    branches: vec!["dev".into(), "feature/*".into(), "hotfix/*".into()],
+                    wait_timer: None,
+                    reviewers: Vec::new(),
+                },
+                EnvironmentConfig {
+                    name: "release".to_string(),
+                    branches: vec!["release/*".into(), "main".into()],
+                    wait_timer: None,
+                    reviewers: Vec::new(),
+                },
+            ],
+        }
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        let names = self
+            .branches
+            .iter()
+            .map(|b| ("branch", &b.name))
+            .chain(self.environments.iter().map(|e| ("environment", &e.name)));
+        for (kind, name) in names {
+            if name.trim().is_empty() {
+                return Err(format!("{} name must not be empty", kind));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &str = r#"
+branches:
+  - name: develop
+    required_checks: [build, lint]
+  - name: staging
+    from: develop
+    protect: false
+environments:
+  - name: staging
+    branches: [staging, "feature/*"]
+  - name: production
+    branches: [main]
+    wait_timer: 15
+    reviewers: ["user:alice", "team:org/sre"]
+"#;
+
+    #[test]
+    fn parses_sample_config() {
+        let config: GitflowConfig = serde_yaml::from_str(SAMPLE).unwrap();
+        config.validate().unwrap();
+        assert_eq!(
+            config.branches,
+            vec![
+                BranchConfig {
+                    name: "develop".into(),
+                    from: None,
+                    protect: true,
+                    required_checks: Some(vec!["build".into(), "lint".into()]),
+                },
+                BranchConfig {
+                    name: "staging".into(),
+                    from: Some("develop".into()),
+                    protect: false,
+                    required_checks: None,
+                },
+            ]
+        );
+        assert_eq!(config.environments[0].branches, ["staging", "feature/*"]);
+        assert_eq!(config.environments[1].wait_timer, Some(15));
+        assert_eq!(
+            config.environments[1].reviewers,
+            vec![
+                ReviewerSpec::User("alice".into()),
+                ReviewerSpec::Team {
+                    org: "org".into(),
+                    slug: "sre".into()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn loads_json_and_rejects_unknown_fields() {
+        let dir = std::env::temp_dir();
+        let json = dir.join(format!("gitflow-{}.json", std::process::id()));
+        fs::write(
+            &json,
+            r#"{ "environments": [{ "name": "qa", "branches": ["qa/*"] }] }"#,
+        )
+        .unwrap();
+        let config = GitflowConfig::load(json.to_str().unwrap()).unwrap();
+        assert!(config.branches.is_empty());
+        assert_eq!(config.environments[0].name, "qa");
+
+        let yaml = dir.join(format!("gitflow-{}.yaml", std::process::id()));
+        fs::write(&yaml, "branches:\n  - name: dev\n    protected: true\n").unwrap();
+        let err = GitflowConfig::load(yaml.to_str().unwrap()).unwrap_err();
+        assert!(err.contains("unknown field `protected`"), "{}", err);
+        let _ = fs::remove_file(json);
+        let _ = fs::remove_file(yaml);
+    }
+
+    #[test]
+    fn loads_labels_and_rejects_bad_colors() {
+        let dir = std::env::temp_dir();
+        let path = dir.join(format!("labels-{}.yaml", std::process::id()));
+        fs::write(
+            &path,
+            "- name: bug\n  color: d73a4a\n  description: Something is broken\n- name: docs\n  color: \"#0075ca\"\n",
+        )
+        .unwrap();
+        let LabelsFile(labels) = LabelsFile::load(path.to_str().unwrap()).unwrap();
+        assert_eq!(labels.len(), 2);
+        assert_eq!(
+            labels[0].description.as_deref(),
+            Some("Something is broken")
+        );
+        assert_eq!(labels[1].description, None);
+
+        fs::write(&path, "- name: bug\n  color: red\n").unwrap();
+        let err = LabelsFile::load(path.to_str().unwrap()).unwrap_err();
+        assert!(err.contains("expected six hex digits"), "{}", err);
+        let _ = fs::remove_file(path);
+    }
+
+    #[test]
+    fn validates_the_whole_manifest() {
+        let manifest: Vec<ManifestEntry> = serde_yaml::from_str(
+            "- name: billing\n  visibility: private\n  topics: [payments]\n  vars: { TEAM: pay }\n- name: ledger\n  template: org/service-rust\n",
+        )
+        .unwrap();
+        let manifest = Manifest(manifest);
+        manifest.validate().unwrap();
+        assert_eq!(manifest.0[0].vars["TEAM"], "pay");
+        assert_eq!(manifest.0[1].visibility, None);
+
+        let mut duplicate = manifest.clone();
+        duplicate.0[1].name = "Billing".into();
+        let err = duplicate.validate().unwrap_err();
+        assert!(
+            err.contains("'Billing' is listed more than once"),
+            "{}",
+            err
+        );
+
+        let mut internal = manifest.clone();
+        internal.0[1].visibility = Some("internal".into());
+        let err = internal.validate().unwrap_err();
+        assert!(err.contains("visibility 'internal'"), "{}", err);
+
+        assert!(Manifest(Vec::new()).validate().is_err());
+    }
+
+    #[test]
+    fn rejects_bad_reviewers() {
+        let err = serde_yaml::from_str::<GitflowConfig>(
+            "environments:\n  - name: prod\n    reviewers: [alice]\n",
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("expected user:<login>"), "{}", err);
+    }
+}
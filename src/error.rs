@@ -0,0 +1,319 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::client::GithubClient;
+
+pub type Result<T, E = GithubClientError> = std::result::Result<T, E>;
+
+/// One entry of the `errors` array GitHub returns with a 422.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct ApiErrorDetail {
+    pub resource: Option<String>,
+    pub field: Option<String>,
+    pub code: Option<String>,
+    pub message: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+pub(crate) struct ApiError {
+    pub(crate) message: String,
+    pub(crate) errors: Option<Vec<ApiErrorDetail>>,
+}
+
+/// Errors returned by this crate.
+#[derive(Debug, Error)]
+pub enum GithubClientError {
+    #[error(
+        "Unauthorized: the token is missing, invalid or expired. Check GITHUB_TOKEN / GH_TOKEN."
+    )]
+    Unauthorized,
+
+    #[error("Forbidden: {message}")]
+    Forbidden { message: String },
+
+    /// GitHub refused a write because the repository is archived and so
+    /// read-only.
+    #[error("Repository is archived: {message}")]
+    Archived { message: String },
+
+    #[error("Not found: {resource}")]
+    NotFound { resource: String },
+
+    #[error("Validation failed: {message}{}", format_details(errors))]
+    ValidationFailed {
+        message: String,
+        errors: Vec<ApiErrorDetail>,
+    },
+
+    /// `reset_at` is the Unix time (seconds) at which requests may resume, when known.
+    #[error("Rate limited by GitHub{}", format_reset(*reset_at))]
+    RateLimited { reset_at: Option<u64> },
+
+    #[error("{}", transport_message(.0))]
+    Transport(#[source] reqwest::Error),
+
+    #[error("Unexpected response from GitHub (status {status}): {body}")]
+    Unexpected { status: u16, body: String },
+
+    /// A response GitHub accepted but whose content we cannot use.
+    #[error("{0}")]
+    InvalidResponse(String),
+
+    /// Arguments rejected before any change was made.
+    #[error("{0}")]
+    InvalidInput(String),
+
+    /// A classic token lacks OAuth scopes the requested operations need.
+    #[error("Token is missing {}", format_scopes(missing))]
+    MissingScopes { missing: Vec<String> },
+
+    /// A polling operation (e.g. waiting for a new branch) gave up.
+    #[error("Timed out: {operation}")]
+    Timeout { operation: String },
+
+    #[error("Rolled back: deleted newly created repository '{full_name}'")]
+    RolledBack {
+        full_name: String,
+        #[source]
+        source: Box<GithubClientError>,
+    },
+
+    #[error("Rollback failed, repository '{full_name}' was left in place: {delete_error}")]
+    RollbackFailed {
+        full_name: String,
+        delete_error: Box<GithubClientError>,
+        #[source]
+        source: Box<GithubClientError>,
+    },
+
+    #[error(transparent)]
+    Other(#[from] anyhow::Error),
+}
+
+impl From<reqwest::Error> for GithubClientError {
+    fn from(err: reqwest::Error) -> Self {
+        GithubClientError::Transport(err)
+    }
+}
+
+impl GithubClientError {
+    /// Whether GitHub rejected a create because the resource already exists.
+    pub fn is_already_exists(&self) -> bool {
+        match self {
+            GithubClientError::ValidationFailed { message, errors } => {
+                is_already_exists(errors) || message.to_lowercase().contains("already exists")
+            }
+            _ => false,
+        }
+    }
+
+    /// Whether GitHub refused to open a pull request because the head branch
+    /// has no commits that the base lacks.
+    pub fn is_nothing_to_compare(&self) -> bool {
+        match self {
+            GithubClientError::ValidationFailed { message, errors } => {
+                let no_commits = |m: &str| m.to_lowercase().contains("no commits between");
+                no_commits(message)
+                    || errors
+                        .iter()
+                        .any(|e| e.message.as_deref().is_some_and(no_commits))
+            }
+            _ => false,
+        }
+    }
+
+    /// Whether the request failed because the repository is archived.
+    pub fn is_archived(&self) -> bool {
+        matches!(self, GithubClientError::Archived { .. })
+    }
+
+    pub fn is_not_found(&self) -> bool {
+        matches!(self, GithubClientError::NotFound { .. })
+    }
+
+    pub fn is_timeout(&self) -> bool {
+        match self {
+            GithubClientError::Timeout { .. } => true,
+            GithubClientError::Transport(e) => e.is_timeout(),
+            _ => false,
+        }
+    }
+
+    /// Whether the same request may succeed if sent again later: rate limits,
+    /// network failures, timeouts and 5xx responses.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            GithubClientError::RateLimited { .. } | GithubClientError::Timeout { .. } => true,
+            GithubClientError::Transport(e) => !e.is_decode() && !e.is_builder(),
+            GithubClientError::Unexpected { status, .. } => *status >= 500,
+            _ => false,
+        }
+    }
+}
+
+/// Whether a 422 error list says the resource already exists.
+pub(crate) fn is_already_exists(errors: &[ApiErrorDetail]) -> bool {
+    errors.iter().any(|e| {
+        e.code
+            .as_deref()
+            .map(|c| c.eq_ignore_ascii_case("already_exists"))
+            .unwrap_or(false)
+            || e.message
+                .as_deref()
+                .map(|m| m.to_lowercase().contains("already exists"))
+                .unwrap_or(false)
+    })
+}
+
+/// Turns a non-success response into an error. `action` describes what was
+/// attempted, e.g. "create ruleset 'main' on 'me/repo'".
+/// The response body is passed through [`GithubClient::redact`] first.
+pub(crate) async fn error_from_response(
+    client: &GithubClient,
+    resp: reqwest::Response,
+    action: &str,
+) -> GithubClientError {
+    let status = resp.status().as_u16();
+    let path = resp.url().path().to_string();
+    let headers = resp.headers().clone();
+    let context = response_context(&headers);
+    let body = client.redact(&resp.text().await.unwrap_or_default());
+    let api_error: Option<ApiError> = serde_json::from_str(&body).ok();
+    let api_message = api_error
+        .as_ref()
+        .map(|e| e.message.clone())
+        .unwrap_or_else(|| body.trim().to_string());
+
+    let header_u64 = |name: &str| {
+        headers
+            .get(name)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.trim().parse::<u64>().ok())
+    };
+    let exhausted = headers
+        .get("x-ratelimit-remaining")
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.trim() == "0")
+        .unwrap_or(false);
+    let retry_after = header_u64("retry-after");
+    if status == 429 || (status == 403 && (exhausted || retry_after.is_some())) {
+        let reset_at = retry_after
+            .map(|secs| unix_now() + secs)
+            .or_else(|| header_u64("x-ratelimit-reset"));
+        return GithubClientError::RateLimited { reset_at };
+    }
+
+    match status {
+        401 => GithubClientError::Unauthorized,
+        // "Repository was archived so is read-only."
+        403 if api_message.to_lowercase().contains("archived") => GithubClientError::Archived {
+            message: format!("cannot {}; unarchive it first{}", action, context),
+        },
+        403 => GithubClientError::Forbidden {
+            message: format!("cannot {}: {}{}", action, api_message, context),
+        },
+        404 => GithubClientError::NotFound {
+            resource: format!("{} (while trying to {}){}", path, action, context),
+        },
+        422 => GithubClientError::ValidationFailed {
+            message: format!("cannot {}: {}{}", action, api_message, context),
+            errors: api_error.and_then(|e| e.errors).unwrap_or_default(),
+        },
+        _ => GithubClientError::Unexpected {
+            status,
+            body: format!("cannot {}: {}{}", action, body.trim(), context),
+        },
+    }
+}
+
+/// What GitHub said about a failed request, for failures that are logged
+/// rather than returned: the `message` of its JSON error body with any
+/// per-field `errors`, or the raw (redacted) body when it is not JSON,
+/// followed by [`response_context`].
+pub(crate) async fn decode_github_error(client: &GithubClient, resp: reqwest::Response) -> String {
+    let context = response_context(resp.headers());
+    let body = client.redact(&resp.text().await.unwrap_or_default());
+    match serde_json::from_str::<ApiError>(&body) {
+        Ok(api_error) => format!(
+            "{}{}{}",
+            api_error.message,
+            format_details(&api_error.errors.unwrap_or_default()),
+            context
+        ),
+        Err(_) => format!("{}{}", body.trim(), context),
+    }
+}
+
+/// The request id and rate-limit state of a response, formatted to be
+/// appended to an error message, e.g.
+/// ` [request id 0C8E:1A2B; rate limit 4321 remaining, resets in 1800s]`.
+/// Empty when GitHub sent none of these headers. GitHub support asks for the
+/// request id when investigating a failure.
+pub(crate) fn response_context(headers: &reqwest::header::HeaderMap) -> String {
+    let header = |name: &str| {
+        headers
+            .get(name)
+            .and_then(|v| v.to_str().ok())
+            .map(str::trim)
+            .filter(|v| !v.is_empty())
+    };
+    let mut parts = Vec::new();
+    if let Some(id) = header("x-github-request-id") {
+        parts.push(format!("request id {}", id));
+    }
+    if let Some(remaining) = header("x-ratelimit-remaining") {
+        let reset = header("x-ratelimit-reset")
+            .and_then(|v| v.parse::<u64>().ok())
+            .map(|at| format!(", resets in {}s", at.saturating_sub(unix_now())))
+            .unwrap_or_default();
+        parts.push(format!("rate limit {} remaining{}", remaining, reset));
+    }
+    if parts.is_empty() {
+        String::new()
+    } else {
+        format!(" [{}]", parts.join("; "))
+    }
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+fn format_details(errors: &[ApiErrorDetail]) -> String {
+    if errors.is_empty() {
+        String::new()
+    } else {
+        format!(" details={:?}", errors)
+    }
+}
+
+fn format_scopes(missing: &[String]) -> String {
+    let quoted: Vec<String> = missing.iter().map(|s| format!("'{}'", s)).collect();
+    format!(
+        "scope{} {}",
+        if missing.len() == 1 { "" } else { "s" },
+        quoted.join(", ")
+    )
+}
+
+fn format_reset(reset_at: Option<u64>) -> String {
+    match reset_at {
+        Some(at) => format!("; retry after {} seconds", at.saturating_sub(unix_now())),
+        None => String::new(),
+    }
+}
+
+fn transport_message(err: &reqwest::Error) -> &'static str {
+    if err.is_timeout() {
+        "Request to GitHub timed out"
+    } else if err.is_decode() {
+        "Could not decode the response from GitHub"
+    } else {
+        "Network error talking to GitHub"
+    }
+}
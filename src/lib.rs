@@ -1,54 +1,174 @@
-use anyhow::{anyhow, Result};
-use reqwest::header::{HeaderMap, HeaderName, HeaderValue, ACCEPT, AUTHORIZATION, USER_AGENT};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::str::FromStr;
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use crypto_box::aead::OsRng;
+use crypto_box::PublicKey;
+use futures::stream::{self, StreamExt};
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+use percent_encoding::{utf8_percent_encode, AsciiSet, CONTROLS};
+use reqwest::Method;
 use serde::{Deserialize, Serialize};
 use tokio::time::{sleep, Duration};
 use tracing::{debug, info, warn};
 
-#[derive(Deserialize)]
+mod client;
+mod error;
+mod retry;
+mod summary;
+
+use error::{decode_github_error, error_from_response, is_already_exists, response_context};
+pub use error::{ApiErrorDetail, GithubClientError, Result};
+pub use summary::{BatchEntry, BatchReport, ProvisionSummary, ReconcileStep};
+
+pub use client::{
+    is_timeout, normalize_api_base, redact, ClientConfig, ExecutionMode, GithubClient,
+    GithubClientBuilder, Plan, PlannedCall, DEFAULT_API_VERSION, DEFAULT_BRANCH_WAIT,
+    DEFAULT_CONNECT_TIMEOUT, DEFAULT_CREATED_WAIT, DEFAULT_ETAG_CACHE_BYTES, DEFAULT_MAX_RETRIES,
+    DEFAULT_READY_WAIT, DEFAULT_RETRY_BASE_DELAY, DEFAULT_TIMEOUT, DEFAULT_USER_AGENT,
+};
+pub use retry::retry;
+
+#[derive(Deserialize, Serialize, Debug)]
 pub struct RepoResponse {
     pub full_name: String,
     pub html_url: String,
     pub default_branch: String,
 }
 
-#[derive(Deserialize, Debug)]
-#[allow(dead_code)]
-struct ApiErrorDetail {
-    resource: Option<String>,
-    field: Option<String>,
-    code: Option<String>,
-    message: Option<String>,
-}
-
-#[derive(Deserialize, Debug)]
-#[allow(dead_code)]
-struct ApiError {
-    message: String,
-    errors: Option<Vec<ApiErrorDetail>>,
-    documentation_url: Option<String>,
-}
-
 #[derive(Serialize)]
 struct GenerateRequest<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    owner: Option<&'a str>,
     name: &'a str,
     description: &'a str,
     private: bool,
     include_all_branches: bool,
 }
 
+/// Result of [`generate_or_adopt`]: whether the repository was just created
+/// from the template or already existed and was adopted.
+#[derive(Debug)]
+pub enum GeneratedRepo {
+    Created(RepoResponse),
+    Adopted(RepoResponse),
+}
+
+impl GeneratedRepo {
+    pub fn repo(&self) -> &RepoResponse {
+        match self {
+            GeneratedRepo::Created(repo) | GeneratedRepo::Adopted(repo) => repo,
+        }
+    }
+
+    pub fn into_repo(self) -> RepoResponse {
+        match self {
+            GeneratedRepo::Created(repo) | GeneratedRepo::Adopted(repo) => repo,
+        }
+    }
+
+    /// `true` when this run created the repository.
+    pub fn was_created(&self) -> bool {
+        matches!(self, GeneratedRepo::Created(_))
+    }
+}
+
+/// Creates `repo_name` from the template. With `owner` set (a user or an
+/// organization) the repository is created there; otherwise it lands under
+/// the authenticated user.
+#[allow(clippy::too_many_arguments)]
 pub async fn generate_from_template(
     api_base: &str,
     token: &str,
     template_name: &str,
+    owner: Option<&str>,
+    repo_name: &str,
+    repo_desc: &str,
+    is_private: bool,
+    include_all_branches: bool,
+) -> Result<RepoResponse> {
+    generate_from_template_with_client(
+        &GithubClient::new(api_base, token)?,
+        template_name,
+        owner,
+        repo_name,
+        repo_desc,
+        is_private,
+        include_all_branches,
+    )
+    .await
+}
+
+/// [`generate_from_template`] on an existing client.
+pub async fn generate_from_template_with_client(
+    client: &GithubClient,
+    template_name: &str,
+    owner: Option<&str>,
     repo_name: &str,
     repo_desc: &str,
     is_private: bool,
     include_all_branches: bool,
 ) -> Result<RepoResponse> {
+    generate_or_adopt_with_client(
+        client,
+        template_name,
+        owner,
+        repo_name,
+        repo_desc,
+        is_private,
+        include_all_branches,
+        false,
+    )
+    .await
+    .map(GeneratedRepo::into_repo)
+}
+
+/// Like [`generate_from_template`], but with `adopt_existing` a 422
+/// "already exists" response returns the existing repository as
+/// [`GeneratedRepo::Adopted`] instead of failing.
+#[allow(clippy::too_many_arguments)]
+pub async fn generate_or_adopt(
+    api_base: &str,
+    token: &str,
+    template_name: &str,
+    owner: Option<&str>,
+    repo_name: &str,
+    repo_desc: &str,
+    is_private: bool,
+    include_all_branches: bool,
+    adopt_existing: bool,
+) -> Result<GeneratedRepo> {
+    generate_or_adopt_with_client(
+        &GithubClient::new(api_base, token)?,
+        template_name,
+        owner,
+        repo_name,
+        repo_desc,
+        is_private,
+        include_all_branches,
+        adopt_existing,
+    )
+    .await
+}
+
+/// [`generate_or_adopt`] on an existing client.
+#[allow(clippy::too_many_arguments)]
+pub async fn generate_or_adopt_with_client(
+    client: &GithubClient,
+    template_name: &str,
+    owner: Option<&str>,
+    repo_name: &str,
+    repo_desc: &str,
+    is_private: bool,
+    include_all_branches: bool,
+    adopt_existing: bool,
+) -> Result<GeneratedRepo> {
     let (template_owner, template_repo) = split_template_name(template_name)?;
     let url = format!(
         "{}/repos/{}/{}/generate",
-        api_base.trim_end_matches('/'),
+        client.api_base(),
         template_owner,
         template_repo
     );
@@ -58,127 +178,492 @@ pub async fn generate_from_template(
         repo_name, template_owner, template_repo
     );
 
-    let mut headers = HeaderMap::new();
-    headers.insert(
-        AUTHORIZATION,
-        HeaderValue::from_str(&format!("Bearer {}", token))?,
-    );
-    headers.insert(
-        ACCEPT,
-        HeaderValue::from_static("application/vnd.github+json"),
-    );
-    headers.insert(
-        USER_AGENT,
-        HeaderValue::from_static("github-client-rust/0.1"),
-    );
-    headers.insert(
-        HeaderName::from_static("x-github-api-version"),
-        HeaderValue::from_static("2022-11-28"),
-    );
-
-    let client = reqwest::Client::builder()
-        .default_headers(headers)
-        .build()?;
-
     let body = GenerateRequest {
+        owner,
         name: repo_name,
         description: repo_desc,
         private: is_private,
         include_all_branches,
     };
 
+    if client.is_dry_run() {
+        // The new repo gets the template's default branch
+        let owner = resolve_owner_with_client(client, owner).await?;
+        let default_branch = get_repo_default_branch_with_client(client, template_name).await?;
+        client.record(
+            Method::POST,
+            &url,
+            format!(
+                "create {} repository '{}/{}' from template '{}/{}' (include_all_branches={})",
+                if is_private { "private" } else { "public" },
+                owner,
+                repo_name,
+                template_owner,
+                template_repo,
+                include_all_branches
+            ),
+        );
+        return Ok(GeneratedRepo::Created(RepoResponse {
+            full_name: format!("{}/{}", owner, repo_name),
+            html_url: String::new(),
+            default_branch,
+        }));
+    }
+
     debug!(
         "POST to GitHub API: include_all_branches={}, private={}",
         include_all_branches, is_private
     );
-    let resp = client.post(url).json(&body).send().await?;
+    let resp = client.send(client.post(url).json(&body)).await?;
     let status = resp.status();
     if status.is_success() || status.as_u16() == 201 {
         let repo: RepoResponse = resp.json().await?;
         info!("Successfully created repository '{}'", repo.full_name);
+        return Ok(GeneratedRepo::Created(repo));
+    }
+
+    // Kept for the friendlier messages below, which replace GitHub's
+    let context = response_context(resp.headers());
+    let err = error_from_response(
+        client,
+        resp,
+        &format!(
+            "generate '{}' from template '{}/{}'",
+            repo_name, template_owner, template_repo
+        ),
+    )
+    .await;
+    if err.is_already_exists() && adopt_existing {
+        let owner = resolve_owner_with_client(client, owner).await?;
+        let existing = get_repo_with_client(client, &format!("{}/{}", owner, repo_name)).await?;
+        info!(
+            "Repository '{}' already exists; adopting it",
+            existing.full_name
+        );
+        return Ok(GeneratedRepo::Adopted(existing));
+    }
+    warn!("GitHub API error {}: {}", status, err);
+
+    // Replace GitHub's terse messages with hints on how to fix the usual causes
+    let err = match err {
+        GithubClientError::Forbidden { .. } => GithubClientError::Forbidden {
+            message: format!("token lacks required permissions. Ensure fine-grained PAT has Administration: Read & write on your account and Contents: Read on the template (or use classic PAT with repo/public_repo).{}", context),
+        },
+        GithubClientError::NotFound { .. } => GithubClientError::NotFound {
+            resource: format!(
+                "template '{}/{}' is not accessible or does not exist. Verify 'owner/repo' and that the repository is marked as a Template.{}",
+                template_owner, template_repo, context
+            ),
+        },
+        GithubClientError::ValidationFailed { errors, .. } => {
+            let message = if is_already_exists(&errors) {
+                "a repository with this name already exists. Choose a different repo_name."
+            } else {
+                "check repo_name and inputs."
+            };
+            GithubClientError::ValidationFailed {
+                message: format!("{}{}", message, context),
+                errors,
+            }
+        }
+        other => other,
+    };
+    Err(err)
+}
+
+/// Like [`generate_from_template`], but copies only the template branches
+/// named in `only_branches` besides the default branch, rather than all or
+/// none of them.
+///
+/// GitHub's generate endpoint can only copy every branch or none, and a
+/// generated repository does not share history with its template, so the
+/// template's commits cannot simply be pointed at. Instead the repository is
+/// generated with the default branch only, and each requested branch is
+/// recreated as one commit on top of the new default branch whose tree is the
+/// template branch's tree; only files that differ from the default branch are
+/// uploaded. Every requested branch is checked before anything is created, so
+/// a name missing from the template fails without leaving a repository behind.
+#[allow(clippy::too_many_arguments)]
+pub async fn generate_with_selected_branches(
+    api_base: &str,
+    token: &str,
+    template_name: &str,
+    owner: Option<&str>,
+    repo_name: &str,
+    repo_desc: &str,
+    is_private: bool,
+    only_branches: &[&str],
+) -> Result<RepoResponse> {
+    generate_with_selected_branches_with_client(
+        &GithubClient::new(api_base, token)?,
+        template_name,
+        owner,
+        repo_name,
+        repo_desc,
+        is_private,
+        only_branches,
+    )
+    .await
+}
+
+/// [`generate_with_selected_branches`] on an existing client.
+pub async fn generate_with_selected_branches_with_client(
+    client: &GithubClient,
+    template_name: &str,
+    owner: Option<&str>,
+    repo_name: &str,
+    repo_desc: &str,
+    is_private: bool,
+    only_branches: &[&str],
+) -> Result<RepoResponse> {
+    let (template_owner, template_repo) = split_template_name(template_name)?;
+    let src_url = format!(
+        "{}/repos/{}/{}",
+        client.api_base(),
+        template_owner,
+        template_repo
+    );
+
+    let template_default = get_repo_default_branch_with_client(client, template_name).await?;
+    let mut branches: Vec<&str> = Vec::new();
+    for branch in only_branches {
+        if *branch == template_default || branches.contains(branch) {
+            continue;
+        }
+        if branch_head(client, &src_url, branch).await?.is_none() {
+            return Err(GithubClientError::NotFound {
+                resource: format!(
+                    "branch '{}' in template '{}'; check the branches to copy",
+                    branch, template_name
+                ),
+            });
+        }
+        branches.push(branch);
+    }
+
+    let repo = generate_from_template_with_client(
+        client,
+        template_name,
+        owner,
+        repo_name,
+        repo_desc,
+        is_private,
+        false,
+    )
+    .await?;
+    if branches.is_empty() {
         return Ok(repo);
     }
 
-    // Try to decode structured error if possible
-    let content_type_is_json = resp
-        .headers()
-        .get(reqwest::header::CONTENT_TYPE)
-        .and_then(|v| v.to_str().ok())
-        .map(|v| v.contains("application/json"))
-        .unwrap_or(false);
+    let (owner, repo_short) = split_template_name(&repo.full_name)?;
+    let dst_url = format!("{}/repos/{}/{}", client.api_base(), owner, repo_short);
+    if client.is_dry_run() {
+        for branch in &branches {
+            client.record(
+                Method::POST,
+                &format!("{}/git/refs", dst_url),
+                format!(
+                    "create branch '{}' with the content of '{}' in template '{}'",
+                    branch, branch, template_name
+                ),
+            );
+        }
+        return Ok(repo);
+    }
 
-    if content_type_is_json {
-        let api_err: ApiError = resp.json().await.unwrap_or(ApiError {
-            message: "Unknown error".to_string(),
-            errors: None,
-            documentation_url: None,
+    wait_for_repo_ready_with_client(
+        client,
+        &repo.full_name,
+        &repo.default_branch,
+        client.ready_wait(),
+    )
+    .await?;
+    let Some((head_sha, head_tree_sha)) =
+        branch_head(client, &dst_url, &repo.default_branch).await?
+    else {
+        return Err(GithubClientError::NotFound {
+            resource: format!(
+                "default branch '{}' in '{}'",
+                repo.default_branch, repo.full_name
+            ),
         });
-        warn!("GitHub API error {}: {}", status, api_err.message);
-
-        let friendly = match status.as_u16() {
-            403 => "Forbidden: token lacks required permissions. Ensure fine-grained PAT has Administration: Read & write on your account and Contents: Read on the template (or use classic PAT with repo/public_repo).".to_string(),
-            404 => "Not found: template is not accessible or does not exist. Verify 'owner/repo' and that the repository is marked as a Template.".to_string(),
-            422 => {
-                if let Some(errors) = &api_err.errors {
-                    if errors.iter().any(|e| {
-                        e.code
-                            .as_deref()
-                            .map(|c| c.eq_ignore_ascii_case("already_exists"))
-                            .unwrap_or(false)
-                            || e.message
-                                .as_deref()
-                                .map(|m| m.to_lowercase().contains("already exists"))
-                                .unwrap_or(false)
-                    }) {
-                        "Validation failed: a repository with this name already exists. Choose a different repo_name.".to_string()
-                    } else {
-                        "Validation failed: check repo_name and inputs.".to_string()
-                    }
-                } else {
-                    "Validation failed: check repo_name and inputs.".to_string()
-                }
+    };
+    // Blob SHAs are content hashes, so files the default branch already has
+    // need not be uploaded again
+    let mut present: HashSet<String> = fetch_tree(client, &dst_url, &head_tree_sha)
+        .await?
+        .tree
+        .into_iter()
+        .map(|e| e.sha)
+        .collect();
+
+    for branch in branches {
+        let source_tree = fetch_tree(client, &src_url, branch).await?;
+        for entry in source_tree.tree.iter().filter(|e| e.r#type == "blob") {
+            if present.contains(&entry.sha) {
+                continue;
             }
-            _ => format!("GitHub API error {}: {}", status, api_err.message),
-        };
+            let content = fetch_blob(client, &src_url, &entry.path, &entry.sha).await?;
+            create_blob(client, &dst_url, &entry.path, &content).await?;
+            present.insert(entry.sha.clone());
+        }
+        let entries: Vec<NewTreeEntry> = source_tree
+            .tree
+            .iter()
+            .filter(|e| e.r#type != "tree")
+            .map(|e| NewTreeEntry {
+                path: &e.path,
+                mode: e.mode(),
+                r#type: &e.r#type,
+                sha: &e.sha,
+            })
+            .collect();
+        let commit = create_commit(
+            client,
+            &dst_url,
+            &head_sha,
+            None,
+            &entries,
+            &format!("Copy branch '{}' from {}", branch, template_name),
+            None,
+        )
+        .await?;
 
-        let details = api_err
-            .errors
-            .as_ref()
-            .map(|v| format!(" details={:?}", v))
-            .unwrap_or_default();
-        return Err(anyhow!("{}{}", friendly, details));
+        let resp = client
+            .send(
+                client
+                    .post(format!("{}/git/refs", dst_url))
+                    .json(&serde_json::json!({
+                        "ref": format!("refs/heads/{}", branch),
+                        "sha": commit
+                    })),
+            )
+            .await?;
+        if !resp.status().is_success() {
+            return Err(
+                error_from_response(client, resp, &format!("create branch '{}'", branch)).await,
+            );
+        }
+        info!("Copied branch '{}' from '{}'", branch, template_name);
     }
+    Ok(repo)
+}
 
-    // Fallback to plain text
-    let text = resp
-        .text()
-        .await
-        .unwrap_or_else(|_| "<no body>".to_string());
-    warn!("GitHub API error {}: {}", status, text.trim());
-    Err(anyhow!(format!(
-        "GitHub API error (status {}): {}",
-        status,
-        text.trim()
-    )))
+/// A repository to generate from a template with [`provision_repo`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RepoSpec {
+    pub name: String,
+    pub description: String,
+    pub private: bool,
+    /// Template repository as `owner/repo`.
+    pub template: String,
+    /// User or organization to create it under (default: the authenticated user).
+    pub owner: Option<String>,
+    /// Copy every branch of the template instead of only its default branch.
+    pub include_all_branches: bool,
+    /// Fail before anything is created if the name is taken.
+    pub fail_if_exists: bool,
+    /// Reuse an existing repository of that name; wins over `fail_if_exists`.
+    pub adopt_existing: bool,
+}
+
+/// Generates the repository `spec` describes and waits until its template
+/// content has landed, so it is ready to configure.
+///
+/// The template is checked with [`verify_template`] first. Its metadata is
+/// revalidated with an ETag, so provisioning many repositories from one
+/// template through [`provision_repo_with_client`] and the same client reads
+/// it once.
+pub async fn provision_repo(api_base: &str, token: &str, spec: &RepoSpec) -> Result<GeneratedRepo> {
+    provision_repo_with_client(&GithubClient::new(api_base, token)?, spec).await
+}
+
+/// [`provision_repo`] on an existing client.
+pub async fn provision_repo_with_client(
+    client: &GithubClient,
+    spec: &RepoSpec,
+) -> Result<GeneratedRepo> {
+    verify_template_with_client(client, &spec.template).await?;
+
+    // The new repo lands under `owner` or the authenticated user, not the
+    // template owner, so check there
+    if spec.fail_if_exists && !spec.adopt_existing {
+        let owner = resolve_owner_with_client(client, spec.owner.as_deref()).await?;
+        let target = format!("{}/{}", owner, spec.name);
+        if repo_exists_with_client(client, &target).await? {
+            return Err(GithubClientError::InvalidInput(format!(
+                "Repository '{}' already exists. Choose a different repo_name.",
+                target
+            )));
+        }
+    }
+
+    let generated = generate_or_adopt_with_client(
+        client,
+        &spec.template,
+        spec.owner.as_deref(),
+        &spec.name,
+        &spec.description,
+        spec.private,
+        spec.include_all_branches,
+        spec.adopt_existing,
+    )
+    .await?;
+
+    // In a dry run nothing was created, so there is nothing to wait for
+    if !client.is_dry_run() {
+        let repo = generated.repo();
+        // The new repo can 404 briefly after creation; wait until it is readable
+        wait_for_created_repo_with_client(client, &repo.full_name, client.created_wait()).await?;
+        info!("Repository created: {}", repo.full_name);
+        // Template content is copied asynchronously; seeding or branching
+        // before it lands fails with 404 or "Git Repository is empty"
+        wait_for_repo_ready_with_client(
+            client,
+            &repo.full_name,
+            &repo.default_branch,
+            client.ready_wait(),
+        )
+        .await?;
+    }
+    Ok(generated)
+}
+
+/// The account a generated repository lands under: `owner` if given,
+/// otherwise the authenticated user.
+pub async fn resolve_owner(api_base: &str, token: &str, owner: Option<&str>) -> Result<String> {
+    resolve_owner_with_client(&GithubClient::new(api_base, token)?, owner).await
+}
+
+/// [`resolve_owner`] on an existing client.
+pub async fn resolve_owner_with_client(
+    client: &GithubClient,
+    owner: Option<&str>,
+) -> Result<String> {
+    match owner {
+        Some(owner) => Ok(owner.to_string()),
+        None => get_authenticated_user_with_client(client).await,
+    }
 }
 
 fn split_template_name(template: &str) -> Result<(&str, &str)> {
-    let mut parts = template.splitn(2, '/');
-    let owner = parts
-        .next()
-        .ok_or_else(|| anyhow!("Invalid template name; expected 'owner/repo'"))?;
-    let repo = parts
-        .next()
-        .ok_or_else(|| anyhow!("Invalid template name; expected 'owner/repo'"))?;
-    if owner.is_empty() || repo.is_empty() {
-        return Err(anyhow!(
+    let invalid = || {
+        GithubClientError::InvalidInput(format!(
             "Invalid template name; expected 'owner/repo', got '{}'",
             template
-        ));
+        ))
+    };
+    let mut parts = template.splitn(2, '/');
+    let owner = parts.next().ok_or_else(invalid)?;
+    let repo = parts.next().ok_or_else(invalid)?;
+    if owner.is_empty() || repo.is_empty() {
+        return Err(invalid());
     }
     Ok((owner, repo))
 }
 
+/// Characters escaped when a name becomes part of a URL path or query.
+const URL_SEGMENT: &AsciiSet = &CONTROLS
+    .add(b' ')
+    .add(b'"')
+    .add(b'#')
+    .add(b'%')
+    .add(b'&')
+    .add(b'+')
+    .add(b'/')
+    .add(b'<')
+    .add(b'>')
+    .add(b'?')
+    .add(b'[')
+    .add(b'\\')
+    .add(b']')
+    .add(b'^')
+    .add(b'`')
+    .add(b'{')
+    .add(b'|')
+    .add(b'}');
+
+/// Percent-encodes `name` as a single URL path segment, slashes included.
+fn encode_segment(name: &str) -> String {
+    utf8_percent_encode(name, URL_SEGMENT).to_string()
+}
+
+/// Percent-encodes each `/`-separated part of `path`, keeping the slashes;
+/// for branch names and file paths, which GitHub takes unescaped.
+fn encode_path(path: &str) -> String {
+    path.split('/')
+        .map(encode_segment)
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Checks `name` against git's rules for branch names, so a bad name is
+/// rejected with a reason instead of a malformed request and a 404.
+pub fn validate_ref_name(name: &str) -> Result<()> {
+    let reason = if name.is_empty() {
+        Some("it must not be empty".to_string())
+    } else if let Some(short) = name.strip_prefix("refs/heads/") {
+        Some(format!(
+            "give the branch name without 'refs/heads/', e.g. '{}'",
+            short
+        ))
+    } else if name.starts_with("refs/") {
+        Some("give a branch name, not a full ref".to_string())
+    } else if name.starts_with('/') || name.ends_with('/') || name.contains("//") {
+        Some("it must not start or end with '/' or contain '//'".to_string())
+    } else if name.contains("..") {
+        Some("it must not contain '..'".to_string())
+    } else if name
+        .split('/')
+        .any(|part| part.starts_with('.') || part.ends_with(".lock"))
+    {
+        Some("no part of it may start with '.' or end with '.lock'".to_string())
+    } else if name.ends_with('.') || name == "@" || name.contains("@{") {
+        Some("it must not end with '.', be '@' or contain '@{'".to_string())
+    } else if name
+        .chars()
+        .any(|c| c.is_control() || c.is_whitespace() || "~^:?*[\\".contains(c))
+    {
+        Some("it must not contain spaces or any of ~ ^ : ? * [ \\".to_string())
+    } else {
+        None
+    };
+    match reason {
+        Some(reason) => Err(GithubClientError::InvalidInput(format!(
+            "invalid branch name '{}': {}",
+            name, reason
+        ))),
+        None => Ok(()),
+    }
+}
+
+/// Checks an environment name before it is sent. GitHub allows most
+/// characters, including `/`, which is escaped in the URL; names may not be
+/// blank, longer than 255 characters, `.` or `..`, or contain control
+/// characters.
+pub fn validate_env_name(name: &str) -> Result<()> {
+    let reason = if name.trim().is_empty() {
+        Some("it must not be empty")
+    } else if name.trim() != name {
+        Some("it must not start or end with whitespace")
+    } else if name.chars().count() > 255 {
+        Some("it must be at most 255 characters")
+    } else if name == "." || name == ".." {
+        Some("it must not be '.' or '..'")
+    } else if name.chars().any(char::is_control) {
+        Some("it must not contain control characters")
+    } else {
+        None
+    };
+    match reason {
+        Some(reason) => Err(GithubClientError::InvalidInput(format!(
+            "invalid environment name '{}': {}",
+            name, reason
+        ))),
+        None => Ok(()),
+    }
+}
+
 #[derive(Serialize)]
 struct RequiredStatusChecks<'a> {
     strict: bool,
@@ -208,96 +693,115 @@ struct BranchProtectionRequest<'a> {
     allow_fork_syncing: bool,
 }
 
+/// Legacy branch protection settings applied by [`protect_branch_with_options`].
+///
+/// The default is the policy [`protect_branch`] applies: one approving review
+/// with stale review dismissal and last-push approval, strict (up-to-date)
+/// status checks, enforced for admins, no force-pushes or deletions.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BranchProtectionOptions {
+    /// Status-check contexts that must pass before merging.
+    pub required_contexts: Vec<String>,
+    /// Require branches to be up to date with the base before merging.
+    pub strict_checks: bool,
+    pub required_approvals: u8,
+    pub enforce_admins: bool,
+    pub allow_force_pushes: bool,
+}
+
+impl Default for BranchProtectionOptions {
+    fn default() -> Self {
+        Self {
+            required_contexts: Vec::new(),
+            strict_checks: true,
+            required_approvals: 1,
+            enforce_admins: true,
+            allow_force_pushes: false,
+        }
+    }
+}
+
+impl BranchProtectionOptions {
+    /// The default policy plus the given required status-check contexts.
+    pub fn with_checks(required_contexts: &[&str]) -> Self {
+        Self {
+            required_contexts: required_contexts.iter().map(|c| c.to_string()).collect(),
+            ..Self::default()
+        }
+    }
+
+    /// Short description for logs and dry-run plans.
+    fn describe(&self) -> String {
+        let mut parts = vec![format!(
+            "require {} approving review{}",
+            self.required_approvals,
+            if self.required_approvals == 1 {
+                ""
+            } else {
+                "s"
+            }
+        )];
+        if !self.required_contexts.is_empty() {
+            parts.push(format!("status checks {:?}", self.required_contexts));
+        }
+        if !self.enforce_admins {
+            parts.push("not enforced for admins".to_string());
+        }
+        parts.push(if self.allow_force_pushes {
+            "allow force-pushes; block deletions".to_string()
+        } else {
+            "block force-pushes and deletions".to_string()
+        });
+        parts.join("; ")
+    }
+
+    fn request(&self) -> BranchProtectionRequest<'_> {
+        BranchProtectionRequest {
+            required_status_checks: RequiredStatusChecks {
+                strict: self.strict_checks,
+                contexts: &self.required_contexts,
+            },
+            enforce_admins: self.enforce_admins,
+            required_pull_request_reviews: RequiredPullRequestReviews {
+                required_approving_review_count: self.required_approvals,
+                dismiss_stale_reviews: true,
+                require_code_owner_reviews: false,
+                require_last_push_approval: true,
+            },
+            restrictions: None,
+            allow_force_pushes: self.allow_force_pushes,
+            allow_deletions: false,
+            required_linear_history: true,
+            block_creations: false,
+            required_conversation_resolution: true,
+            lock_branch: false,
+            allow_fork_syncing: false,
+        }
+    }
+}
+
 pub async fn protect_branch(
     api_base: &str,
     token: &str,
     full_name: &str,
     branch: &str,
 ) -> Result<()> {
-    let (owner, repo) = split_template_name(full_name)?;
-    let url = format!(
-        "{}/repos/{}/{}/branches/{}/protection",
-        api_base.trim_end_matches('/'),
-        owner,
-        repo,
-        branch
-    );
-
-    info!(
-        "Applying branch protection to '{}/{}' (branch '{}')",
-        owner, repo, branch
-    );
-
-    // Wait for the branch to exist (new repos can be slightly delayed)
-    ensure_branch_exists(api_base, token, full_name, branch, Duration::from_secs(30)).await?;
-
-    let mut headers = HeaderMap::new();
-    headers.insert(
-        AUTHORIZATION,
-        HeaderValue::from_str(&format!("Bearer {}", token))?,
-    );
-    headers.insert(
-        ACCEPT,
-        HeaderValue::from_static("application/vnd.github+json"),
-    );
-    headers.insert(
-        USER_AGENT,
-        HeaderValue::from_static("github-client-rust/0.1"),
-    );
-    headers.insert(
-        HeaderName::from_static("x-github-api-version"),
-        HeaderValue::from_static("2022-11-28"),
-    );
-
-    let client = reqwest::Client::builder()
-        .default_headers(headers)
-        .build()?;
-
-    let contexts: Vec<String> = Vec::new();
-    let body = BranchProtectionRequest {
-        required_status_checks: RequiredStatusChecks {
-            strict: true,
-            contexts: &contexts,
-        },
-        enforce_admins: true,
-        required_pull_request_reviews: RequiredPullRequestReviews {
-            required_approving_review_count: 1,
-            dismiss_stale_reviews: true,
-            require_code_owner_reviews: false,
-            require_last_push_approval: true,
-        },
-        restrictions: None,
-        allow_force_pushes: false,
-        allow_deletions: false,
-        required_linear_history: true,
-        block_creations: false,
-        required_conversation_resolution: true,
-        lock_branch: false,
-        allow_fork_syncing: false,
-    };
-
-    debug!("PUT branch protection payload prepared");
-    let resp = client.put(url).json(&body).send().await?;
-    let status = resp.status();
-    if status.is_success() {
-        info!("Branch protection applied");
-        return Ok(());
-    }
+    protect_branch_with_client(&GithubClient::new(api_base, token)?, full_name, branch).await
+}
 
-    let text = resp
-        .text()
-        .await
-        .unwrap_or_else(|_| "<no body>".to_string());
-    warn!(
-        "Failed to apply branch protection {}: {}",
-        status,
-        text.trim()
-    );
-    Err(anyhow!(format!(
-        "Failed to apply branch protection (status {}): {}",
-        status,
-        text.trim()
-    )))
+/// [`protect_branch`] on an existing client.
+pub async fn protect_branch_with_client(
+    client: &GithubClient,
+    full_name: &str,
+    branch: &str,
+) -> Result<()> {
+    protect_branch_with_options_with_client(
+        client,
+        full_name,
+        branch,
+        &BranchProtectionOptions::default(),
+    )
+    .await
 }
 
 pub async fn protect_branch_with_checks(
@@ -307,134 +811,675 @@ pub async fn protect_branch_with_checks(
     branch: &str,
     required_contexts: &[&str],
 ) -> Result<()> {
-    let (owner, repo) = split_template_name(full_name)?;
-    let url = format!(
-        "{}/repos/{}/{}/branches/{}/protection",
-        api_base.trim_end_matches('/'),
-        owner,
-        repo,
-        branch
-    );
-
-    info!(
-        "Applying branch protection (with checks) to '{}/{}' (branch '{}'): {:?}",
-        owner, repo, branch, required_contexts
-    );
+    protect_branch_with_checks_with_client(
+        &GithubClient::new(api_base, token)?,
+        full_name,
+        branch,
+        required_contexts,
+    )
+    .await
+}
 
-    // Ensure branch exists
-    ensure_branch_exists(api_base, token, full_name, branch, Duration::from_secs(30)).await?;
+/// [`protect_branch_with_checks`] on an existing client.
+pub async fn protect_branch_with_checks_with_client(
+    client: &GithubClient,
+    full_name: &str,
+    branch: &str,
+    required_contexts: &[&str],
+) -> Result<()> {
+    protect_branch_with_options_with_client(
+        client,
+        full_name,
+        branch,
+        &BranchProtectionOptions::with_checks(required_contexts),
+    )
+    .await
+}
 
-    let mut headers = HeaderMap::new();
-    headers.insert(
-        AUTHORIZATION,
-        HeaderValue::from_str(&format!("Bearer {}", token))?,
-    );
-    headers.insert(
-        ACCEPT,
-        HeaderValue::from_static("application/vnd.github+json"),
-    );
-    headers.insert(
-        USER_AGENT,
-        HeaderValue::from_static("github-client-rust/0.1"),
-    );
-    headers.insert(
-        HeaderName::from_static("x-github-api-version"),
-        HeaderValue::from_static("2022-11-28"),
-    );
+/// Applies legacy branch protection to `branch`, waiting first for the
+/// branch to exist (see [`ClientConfig::branch_wait`]).
+pub async fn protect_branch_with_options(
+    api_base: &str,
+    token: &str,
+    full_name: &str,
+    branch: &str,
+    options: &BranchProtectionOptions,
+) -> Result<()> {
+    protect_branch_with_options_with_client(
+        &GithubClient::new(api_base, token)?,
+        full_name,
+        branch,
+        options,
+    )
+    .await
+}
 
-    let client = reqwest::Client::builder()
-        .default_headers(headers)
-        .build()?;
+/// [`protect_branch_with_options`] on an existing client.
+pub async fn protect_branch_with_options_with_client(
+    client: &GithubClient,
+    full_name: &str,
+    branch: &str,
+    options: &BranchProtectionOptions,
+) -> Result<()> {
+    validate_ref_name(branch)?;
+    let (owner, repo) = split_template_name(full_name)?;
 
-    let contexts_vec: Vec<String> = required_contexts.iter().map(|s| s.to_string()).collect();
-    let body = BranchProtectionRequest {
-        required_status_checks: RequiredStatusChecks {
-            strict: true,
-            contexts: &contexts_vec,
-        },
-        enforce_admins: true,
-        required_pull_request_reviews: RequiredPullRequestReviews {
-            required_approving_review_count: 1,
-            dismiss_stale_reviews: true,
-            require_code_owner_reviews: false,
-            require_last_push_approval: true,
-        },
-        restrictions: None,
-        allow_force_pushes: false,
-        allow_deletions: false,
-        required_linear_history: true,
-        block_creations: false,
-        required_conversation_resolution: true,
-        lock_branch: false,
-        allow_fork_syncing: false,
-    };
+    info!(
+        "Applying branch protection to '{}/{}' (branch '{}'): {:?}",
+        owner, repo, branch, options.required_contexts
+    );
 
-    debug!("PUT branch protection (with checks) payload prepared");
-    let resp = client.put(url).json(&body).send().await?;
-    let status = resp.status();
-    if status.is_success() {
-        info!("Branch protection (with checks) applied");
-        return Ok(());
+    if !client.is_dry_run() {
+        // Wait for the branch to exist (new repos can be slightly delayed)
+        ensure_branch_exists(client, full_name, branch, client.branch_wait()).await?;
     }
-
-    let text = resp
-        .text()
-        .await
-        .unwrap_or_else(|_| "<no body>".to_string());
-    warn!(
-        "Failed to apply branch protection (with checks) {}: {}",
-        status,
-        text.trim()
-    );
-    Err(anyhow!(format!(
-        "Failed to apply branch protection (with checks) (status {}): {}",
-        status,
-        text.trim()
-    )))
+    put_branch_protection(client, full_name, branch, options).await
 }
 
-async fn ensure_branch_exists(
-    api_base: &str,
-    token: &str,
+async fn put_branch_protection(
+    client: &GithubClient,
     full_name: &str,
     branch: &str,
-    max_wait: Duration,
+    options: &BranchProtectionOptions,
 ) -> Result<()> {
     let (owner, repo) = split_template_name(full_name)?;
     let url = format!(
-        "{}/repos/{}/{}/branches/{}",
-        api_base.trim_end_matches('/'),
+        "{}/repos/{}/{}/branches/{}/protection",
+        client.api_base(),
         owner,
         repo,
-        branch
-    );
-
-    let mut headers = HeaderMap::new();
-    headers.insert(
-        AUTHORIZATION,
-        HeaderValue::from_str(&format!("Bearer {}", token))?,
-    );
-    headers.insert(
-        ACCEPT,
-        HeaderValue::from_static("application/vnd.github+json"),
-    );
-    headers.insert(
-        USER_AGENT,
-        HeaderValue::from_static("github-client-rust/0.1"),
-    );
-    headers.insert(
-        HeaderName::from_static("x-github-api-version"),
-        HeaderValue::from_static("2022-11-28"),
+        encode_path(branch)
     );
 
-    let client = reqwest::Client::builder()
-        .default_headers(headers)
-        .build()?;
+    if client.is_dry_run() {
+        client.record(Method::PUT, &url, options.describe());
+        return Ok(());
+    }
+
+    debug!("PUT branch protection payload prepared");
+    let resp = client
+        .send(client.put(url).json(&options.request()))
+        .await?;
+    let status = resp.status();
+    if status.is_success() {
+        info!("Branch protection applied to '{}'", branch);
+        return Ok(());
+    }
+
+    let err = error_from_response(
+        client,
+        resp,
+        &format!("apply branch protection to '{}' on '{}'", branch, full_name),
+    )
+    .await;
+    warn!("Failed to apply branch protection {}: {}", status, err);
+    Err(err)
+}
+
+/// Outcome of an idempotent `ensure_*` step, as reported in reconcile mode.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ReconcileStatus {
+    /// Already matched the desired state; nothing was sent.
+    Compliant,
+    /// Created or updated to match (or would be, in dry run).
+    Changed,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct EnabledFlag {
+    enabled: bool,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct CurrentStatusChecks {
+    strict: bool,
+    contexts: Vec<String>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct CurrentReviews {
+    required_approving_review_count: u8,
+    dismiss_stale_reviews: bool,
+    require_code_owner_reviews: bool,
+    require_last_push_approval: bool,
+}
+
+/// Branch protection as returned by `GET .../branches/{branch}/protection`,
+/// limited to the fields [`BranchProtectionOptions`] sets.
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct CurrentProtection {
+    required_status_checks: Option<CurrentStatusChecks>,
+    enforce_admins: EnabledFlag,
+    required_pull_request_reviews: Option<CurrentReviews>,
+    restrictions: Option<serde_json::Value>,
+    required_linear_history: EnabledFlag,
+    allow_force_pushes: EnabledFlag,
+    allow_deletions: EnabledFlag,
+    block_creations: EnabledFlag,
+    required_conversation_resolution: EnabledFlag,
+    lock_branch: EnabledFlag,
+    allow_fork_syncing: EnabledFlag,
+}
+
+impl CurrentProtection {
+    /// Whether applying `options` would leave the protection unchanged.
+    /// Status-check contexts are compared ignoring order.
+    fn matches(&self, options: &BranchProtectionOptions) -> bool {
+        let desired = options.request();
+        let sorted = |contexts: &[String]| {
+            let mut contexts = contexts.to_vec();
+            contexts.sort();
+            contexts
+        };
+        let checks_match = self.required_status_checks.as_ref().is_some_and(|c| {
+            c.strict == desired.required_status_checks.strict
+                && sorted(&c.contexts) == sorted(desired.required_status_checks.contexts)
+        });
+        let reviews = &desired.required_pull_request_reviews;
+        let reviews_match = self
+            .required_pull_request_reviews
+            .as_ref()
+            .is_some_and(|r| {
+                r.required_approving_review_count == reviews.required_approving_review_count
+                    && r.dismiss_stale_reviews == reviews.dismiss_stale_reviews
+                    && r.require_code_owner_reviews == reviews.require_code_owner_reviews
+                    && r.require_last_push_approval == reviews.require_last_push_approval
+            });
+        checks_match
+            && reviews_match
+            && self.restrictions.is_none()
+            && self.enforce_admins.enabled == desired.enforce_admins
+            && self.required_linear_history.enabled == desired.required_linear_history
+            && self.allow_force_pushes.enabled == desired.allow_force_pushes
+            && self.allow_deletions.enabled == desired.allow_deletions
+            && self.block_creations.enabled == desired.block_creations
+            && self.required_conversation_resolution.enabled
+                == desired.required_conversation_resolution
+            && self.lock_branch.enabled == desired.lock_branch
+            && self.allow_fork_syncing.enabled == desired.allow_fork_syncing
+    }
+}
+
+/// Like [`protect_branch_with_options`], but reads the current protection
+/// first and only sends the PUT when it differs from `options`.
+pub async fn ensure_branch_protection(
+    api_base: &str,
+    token: &str,
+    full_name: &str,
+    branch: &str,
+    options: &BranchProtectionOptions,
+) -> Result<ReconcileStatus> {
+    ensure_branch_protection_with_client(
+        &GithubClient::new(api_base, token)?,
+        full_name,
+        branch,
+        options,
+    )
+    .await
+}
+
+/// [`ensure_branch_protection`] on an existing client.
+pub async fn ensure_branch_protection_with_client(
+    client: &GithubClient,
+    full_name: &str,
+    branch: &str,
+    options: &BranchProtectionOptions,
+) -> Result<ReconcileStatus> {
+    validate_ref_name(branch)?;
+    let (owner, repo) = split_template_name(full_name)?;
+    let url = format!(
+        "{}/repos/{}/{}/branches/{}/protection",
+        client.api_base(),
+        owner,
+        repo,
+        encode_path(branch)
+    );
+
+    let resp = client.send(client.get(&url)).await?;
+    let status = resp.status();
+    if status.is_success() {
+        let current: CurrentProtection = resp.json().await?;
+        if current.matches(options) {
+            info!("Branch protection on '{}' is already compliant", branch);
+            if client.is_dry_run() {
+                client.record_skip(format!("branch protection on '{}'", branch));
+            }
+            return Ok(ReconcileStatus::Compliant);
+        }
+        info!("Branch protection on '{}' differs; updating", branch);
+    } else if status.as_u16() == 404 {
+        // Unprotected branch, or in dry run a repository not created yet
+        debug!("Branch '{}' is not protected", branch);
+        if !client.is_dry_run() {
+            ensure_branch_exists(client, full_name, branch, client.branch_wait()).await?;
+        }
+    } else {
+        return Err(error_from_response(
+            client,
+            resp,
+            &format!("read branch protection of '{}' on '{}'", branch, full_name),
+        )
+        .await);
+    }
+    put_branch_protection(client, full_name, branch, options).await?;
+    Ok(ReconcileStatus::Changed)
+}
+
+const BRANCHES_PER_PAGE: usize = 100;
+
+#[derive(Deserialize)]
+struct BranchSummary {
+    name: String,
+}
+
+/// Lists the names of every branch of `full_name`, following the `Link:
+/// rel="next"` pagination header. In dry run a repository that does not
+/// exist yet has no branches.
+pub async fn list_branches(api_base: &str, token: &str, full_name: &str) -> Result<Vec<String>> {
+    list_branches_with_client(&GithubClient::new(api_base, token)?, full_name).await
+}
+
+/// [`list_branches`] on an existing client.
+pub async fn list_branches_with_client(
+    client: &GithubClient,
+    full_name: &str,
+) -> Result<Vec<String>> {
+    let (owner, repo) = split_template_name(full_name)?;
+    let url = format!(
+        "{}/repos/{}/{}/branches?per_page={}",
+        client.api_base(),
+        owner,
+        repo,
+        BRANCHES_PER_PAGE
+    );
+
+    let mut names = Vec::new();
+    let mut next = Some(url);
+    while let Some(url) = next.take() {
+        let resp = client.send(client.get(&url)).await?;
+        if resp.status().as_u16() == 404 && client.is_dry_run() {
+            break;
+        }
+        if !resp.status().is_success() {
+            return Err(error_from_response(
+                client,
+                resp,
+                &format!("list branches of '{}'", full_name),
+            )
+            .await);
+        }
+        next = next_page_url(resp.headers());
+        let batch: Vec<BranchSummary> = resp.json().await?;
+        names.extend(batch.into_iter().map(|b| b.name));
+    }
+    Ok(names)
+}
+
+/// The `rel="next"` URL of a paginated response's `Link` header, if any.
+fn next_page_url(headers: &reqwest::header::HeaderMap) -> Option<String> {
+    let link = headers.get(reqwest::header::LINK)?.to_str().ok()?;
+    link.split(',').find_map(|part| {
+        let (url, params) = part.split_once(';')?;
+        params
+            .split(';')
+            .any(|p| p.trim() == "rel=\"next\"")
+            .then(|| {
+                url.trim()
+                    .trim_start_matches('<')
+                    .trim_end_matches('>')
+                    .to_string()
+            })
+    })
+}
+
+/// Outcome of [`protect_branches_matching`], as branch names.
+#[derive(Serialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct BranchProtectionReport {
+    pub protected: Vec<String>,
+    /// Rejected by GitHub; see the warnings in the log.
+    pub failed: Vec<String>,
+}
+
+/// Applies `options` to every existing branch whose name matches any of
+/// `patterns`.
+///
+/// Patterns are globs where `*` does not cross `/`, so `release/*` matches
+/// `release/1.2` but not `release/1.2/hotfix`; a name without wildcards
+/// matches only that branch. Branches created later are not covered; use a
+/// ruleset for that. A branch GitHub refuses to protect is reported in
+/// `failed` and the remaining branches are still attempted.
+pub async fn protect_branches_matching(
+    api_base: &str,
+    token: &str,
+    full_name: &str,
+    patterns: &[&str],
+    options: &BranchProtectionOptions,
+) -> Result<BranchProtectionReport> {
+    protect_branches_matching_with_client(
+        &GithubClient::new(api_base, token)?,
+        full_name,
+        patterns,
+        options,
+    )
+    .await
+}
+
+/// [`protect_branches_matching`] on an existing client.
+pub async fn protect_branches_matching_with_client(
+    client: &GithubClient,
+    full_name: &str,
+    patterns: &[&str],
+    options: &BranchProtectionOptions,
+) -> Result<BranchProtectionReport> {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        builder.add(
+            GlobBuilder::new(pattern.trim())
+                .literal_separator(true)
+                .build()
+                .map_err(|e| {
+                    GithubClientError::InvalidInput(format!(
+                        "invalid branch pattern '{}': {}",
+                        pattern, e
+                    ))
+                })?,
+        );
+    }
+    let matcher = builder
+        .build()
+        .map_err(|e| GithubClientError::InvalidInput(format!("invalid branch patterns: {}", e)))?;
+
+    let mut report = BranchProtectionReport::default();
+    for branch in list_branches_with_client(client, full_name).await? {
+        if !matcher.is_match(&branch) {
+            continue;
+        }
+        match put_branch_protection(client, full_name, &branch, options).await {
+            Ok(()) => report.protected.push(branch),
+            Err(err @ GithubClientError::Transport(_)) => return Err(err),
+            Err(err) => {
+                warn!("Failed to protect branch '{}': {}", branch, err);
+                report.failed.push(branch);
+            }
+        }
+    }
+    info!(
+        "Branches matching {:?} on '{}': {} protected, {} failed",
+        patterns,
+        full_name,
+        report.protected.len(),
+        report.failed.len()
+    );
+    Ok(report)
+}
+
+/// What a ruleset applies to.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum RulesetTarget {
+    Branch,
+    Tag,
+}
+
+/// Whether a ruleset is enforced or only evaluated (reported on).
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum RulesetEnforcement {
+    Active,
+    Evaluate,
+    Disabled,
+}
+
+/// Ref name patterns a ruleset targets, e.g. `~DEFAULT_BRANCH` or `refs/heads/release/*`.
+#[derive(Serialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct RefNameCondition {
+    pub include: Vec<String>,
+    pub exclude: Vec<String>,
+}
+
+#[derive(Serialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct RulesetConditions {
+    pub ref_name: RefNameCondition,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct PullRequestRuleParameters {
+    pub required_approving_review_count: u32,
+    pub dismiss_stale_reviews_on_push: bool,
+    pub require_code_owner_review: bool,
+    pub require_last_push_approval: bool,
+    pub required_review_thread_resolution: bool,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct StatusCheck {
+    pub context: String,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct RequiredStatusChecksParameters {
+    pub strict_required_status_checks_policy: bool,
+    pub required_status_checks: Vec<StatusCheck>,
+}
+
+/// A single ruleset rule, serialized as `{ "type": ..., "parameters": ... }`.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "type", content = "parameters", rename_all = "snake_case")]
+pub enum RulesetRule {
+    PullRequest(PullRequestRuleParameters),
+    RequiredStatusChecks(RequiredStatusChecksParameters),
+    NonFastForward,
+    Deletion,
+    RequiredLinearHistory,
+}
+
+/// Request body for `POST/PUT /repos/{owner}/{repo}/rulesets`.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct Ruleset {
+    pub name: String,
+    pub target: RulesetTarget,
+    pub enforcement: RulesetEnforcement,
+    pub conditions: RulesetConditions,
+    pub rules: Vec<RulesetRule>,
+}
+
+impl Ruleset {
+    /// Maps the policy applied by [`protect_branch_with_checks`] onto ruleset
+    /// rules for the refs in `include`: one approving review with stale review
+    /// dismissal and last-push approval, resolved conversations, linear
+    /// history, no force-pushes or deletions, and strict status checks when
+    /// `required_contexts` is not empty. No bypass actors are configured, which
+    /// matches enforcing the rules for admins.
+    pub fn branch_protection(name: &str, include: &[&str], required_contexts: &[&str]) -> Self {
+        Self::with_options(
+            name,
+            include,
+            &BranchProtectionOptions::with_checks(required_contexts),
+        )
+    }
+
+    /// Like [`Ruleset::branch_protection`], but takes the approval count,
+    /// strictness and force-push setting from `options`. Rulesets have no
+    /// bypass actors here, so `enforce_admins: false` has no effect.
+    pub fn with_options(name: &str, include: &[&str], options: &BranchProtectionOptions) -> Self {
+        let mut rules = vec![RulesetRule::Deletion];
+        if !options.allow_force_pushes {
+            rules.push(RulesetRule::NonFastForward);
+        }
+        rules.push(RulesetRule::RequiredLinearHistory);
+        rules.push(RulesetRule::PullRequest(PullRequestRuleParameters {
+            required_approving_review_count: options.required_approvals.into(),
+            dismiss_stale_reviews_on_push: true,
+            require_code_owner_review: false,
+            require_last_push_approval: true,
+            required_review_thread_resolution: true,
+        }));
+        if !options.required_contexts.is_empty() {
+            rules.push(RulesetRule::RequiredStatusChecks(
+                RequiredStatusChecksParameters {
+                    strict_required_status_checks_policy: options.strict_checks,
+                    required_status_checks: options
+                        .required_contexts
+                        .iter()
+                        .map(|c| StatusCheck { context: c.clone() })
+                        .collect(),
+                },
+            ));
+        }
+        Ruleset {
+            name: name.to_string(),
+            target: RulesetTarget::Branch,
+            enforcement: RulesetEnforcement::Active,
+            conditions: RulesetConditions {
+                ref_name: RefNameCondition {
+                    include: include.iter().map(|s| s.to_string()).collect(),
+                    exclude: Vec::new(),
+                },
+            },
+            rules,
+        }
+    }
+
+    /// [`Ruleset::branch_protection`] targeting the repository's default branch.
+    pub fn default_branch(required_contexts: &[&str]) -> Self {
+        Self::branch_protection(
+            "default-branch-protection",
+            &["~DEFAULT_BRANCH"],
+            required_contexts,
+        )
+    }
+}
+
+#[derive(Deserialize)]
+struct RulesetSummary {
+    id: u64,
+    name: String,
+}
+
+/// Creates a repository ruleset, or updates the existing one with the same
+/// name when GitHub rejects the create with a 422. Returns the ruleset id.
+pub async fn create_ruleset(
+    api_base: &str,
+    token: &str,
+    full_name: &str,
+    ruleset: &Ruleset,
+) -> Result<u64> {
+    create_ruleset_with_client(&GithubClient::new(api_base, token)?, full_name, ruleset).await
+}
+
+/// [`create_ruleset`] on an existing client.
+pub async fn create_ruleset_with_client(
+    client: &GithubClient,
+    full_name: &str,
+    ruleset: &Ruleset,
+) -> Result<u64> {
+    let (owner, repo) = split_template_name(full_name)?;
+    let rulesets_url = format!("{}/repos/{}/{}/rulesets", client.api_base(), owner, repo);
+
+    info!(
+        "Creating ruleset '{}' on '{}/{}'",
+        ruleset.name, owner, repo
+    );
+    if client.is_dry_run() {
+        client.record(
+            Method::POST,
+            &rulesets_url,
+            format!(
+                "{} ruleset '{}' for {:?} with {} rules",
+                match ruleset.enforcement {
+                    RulesetEnforcement::Active => "active",
+                    RulesetEnforcement::Evaluate => "evaluate",
+                    RulesetEnforcement::Disabled => "disabled",
+                },
+                ruleset.name,
+                ruleset.conditions.ref_name.include,
+                ruleset.rules.len()
+            ),
+        );
+        return Ok(0);
+    }
+    let resp = client
+        .send(client.post(&rulesets_url).json(ruleset))
+        .await?;
+    let status = resp.status();
+    if status.is_success() {
+        let created: RulesetSummary = resp.json().await?;
+        info!("Ruleset '{}' created (id {})", created.name, created.id);
+        return Ok(created.id);
+    }
+    let err = error_from_response(
+        client,
+        resp,
+        &format!("create ruleset '{}' on '{}'", ruleset.name, full_name),
+    )
+    .await;
+    if status.as_u16() != 422 {
+        warn!("Failed to create ruleset {}: {}", status, err);
+        return Err(err);
+    }
+
+    // 422 usually means a ruleset with this name already exists; update it
+    let list_resp = client.send(client.get(&rulesets_url)).await?;
+    if !list_resp.status().is_success() {
+        return Err(error_from_response(
+            client,
+            list_resp,
+            &format!("list rulesets on '{}'", full_name),
+        )
+        .await);
+    }
+    let existing: Vec<RulesetSummary> = list_resp.json().await?;
+    let Some(found) = existing.iter().find(|r| r.name == ruleset.name) else {
+        return Err(err);
+    };
+
+    debug!(
+        "Ruleset '{}' exists (id {}), updating",
+        found.name, found.id
+    );
+    let update = client
+        .send(
+            client
+                .put(format!("{}/{}", rulesets_url, found.id))
+                .json(ruleset),
+        )
+        .await?;
+    if !update.status().is_success() {
+        return Err(error_from_response(
+            client,
+            update,
+            &format!("update ruleset '{}' on '{}'", ruleset.name, full_name),
+        )
+        .await);
+    }
+    info!("Ruleset '{}' updated (id {})", ruleset.name, found.id);
+    Ok(found.id)
+}
+
+async fn ensure_branch_exists(
+    client: &GithubClient,
+    full_name: &str,
+    branch: &str,
+    max_wait: Duration,
+) -> Result<()> {
+    let (owner, repo) = split_template_name(full_name)?;
+    let url = format!(
+        "{}/repos/{}/{}/branches/{}",
+        client.api_base(),
+        owner,
+        repo,
+        encode_path(branch)
+    );
 
     let start = tokio::time::Instant::now();
     let mut delay = Duration::from_millis(400);
     loop {
-        let resp = client.get(&url).send().await?;
+        let resp = client.send(client.get(&url)).await?;
         match resp.status().as_u16() {
             200 => {
                 debug!("Branch '{}' is available", branch);
@@ -442,36 +1487,38 @@ async fn ensure_branch_exists(
             }
             404 => {
                 if start.elapsed() >= max_wait {
-                    return Err(anyhow!(
-                        "Default branch '{}' was not found within {:?}",
-                        branch,
-                        max_wait
-                    ));
+                    return Err(GithubClientError::Timeout {
+                        operation: format!(
+                            "branch '{}' was not found within {:?}",
+                            branch, max_wait
+                        ),
+                    });
                 }
                 debug!("Branch '{}' not found yet, retrying...", branch);
                 sleep(delay).await;
                 delay = std::cmp::min(delay * 2, Duration::from_secs(2));
             }
             401 | 403 => {
-                let text = resp.text().await.unwrap_or_default();
-                return Err(anyhow!(
-                    "Insufficient permission to check branch existence: {}",
-                    text
-                ));
+                return Err(error_from_response(
+                    client,
+                    resp,
+                    &format!("check whether branch '{}' exists", branch),
+                )
+                .await);
             }
             code => {
-                let text = resp.text().await.unwrap_or_default();
+                let err = error_from_response(
+                    client,
+                    resp,
+                    &format!("check whether branch '{}' exists", branch),
+                )
+                .await;
                 warn!(
                     "Unexpected response {} while checking branch: {}",
-                    code, text
+                    code, err
                 );
                 if start.elapsed() >= max_wait {
-                    return Err(anyhow!(
-                        "Failed to confirm branch existence within {:?}: {} {}",
-                        max_wait,
-                        code,
-                        text
-                    ));
+                    return Err(err);
                 }
                 sleep(delay).await;
                 delay = std::cmp::min(delay * 2, Duration::from_secs(2));
@@ -480,346 +1527,4364 @@ async fn ensure_branch_exists(
     }
 }
 
-#[derive(Deserialize)]
-struct GitRef {
-    #[serde(rename = "object")]
-    obj: GitObject,
+/// Deletes a repository. The token needs the `delete_repo` scope (or
+/// Administration: write on a fine-grained PAT). A repository that is already
+/// gone (404) counts as deleted.
+pub async fn delete_repo(api_base: &str, token: &str, full_name: &str) -> Result<()> {
+    delete_repo_with_client(&GithubClient::new(api_base, token)?, full_name).await
 }
 
-#[derive(Deserialize)]
-struct GitObject {
-    sha: String,
+/// [`delete_repo`] on an existing client.
+pub async fn delete_repo_with_client(client: &GithubClient, full_name: &str) -> Result<()> {
+    let (owner, repo) = split_template_name(full_name)?;
+    let url = format!("{}/repos/{}/{}", client.api_base(), owner, repo);
+
+    if client.is_dry_run() {
+        client.record(
+            Method::DELETE,
+            &url,
+            format!("delete repository '{}'", full_name),
+        );
+        return Ok(());
+    }
+
+    warn!("Deleting repository '{}/{}'", owner, repo);
+    let resp = client.send(client.delete(&url)).await?;
+    let status = resp.status();
+    if status.is_success() {
+        info!("Repository '{}/{}' deleted", owner, repo);
+        return Ok(());
+    }
+    // Already gone is as good as deleted
+    if status.as_u16() == 404 {
+        info!(
+            "Repository '{}/{}' does not exist; nothing to delete",
+            owner, repo
+        );
+        return Ok(());
+    }
+    match error_from_response(client, resp, &format!("delete repository '{}'", full_name)).await {
+        GithubClientError::Forbidden { message } => Err(GithubClientError::Forbidden {
+            message: format!(
+                "{}. Deleting repositories needs the delete_repo scope (classic PAT) or Administration: Read and write (fine-grained PAT)",
+                message
+            ),
+        }),
+        err => Err(err),
+    }
 }
 
-pub async fn create_branch_from_base(
+/// Runs the post-creation `steps` for `full_name` and, if they fail and
+/// `enabled` is set, deletes the repository before returning the error.
+///
+/// Only pass a repository that the caller has just created; this deletes it
+/// unconditionally on failure. A failed delete is reported together with the
+/// original error as [`GithubClientError::RollbackFailed`].
+pub async fn rollback_on_failure<T, E, F>(
     api_base: &str,
     token: &str,
     full_name: &str,
-    base_branch: &str,
-    new_branch: &str,
-) -> Result<()> {
+    enabled: bool,
+    steps: F,
+) -> Result<T>
+where
+    E: Into<GithubClientError>,
+    F: std::future::Future<Output = std::result::Result<T, E>>,
+{
+    rollback_on_failure_with_client(
+        &GithubClient::new(api_base, token)?,
+        full_name,
+        enabled,
+        steps,
+    )
+    .await
+}
+
+/// [`rollback_on_failure`] on an existing client.
+pub async fn rollback_on_failure_with_client<T, E, F>(
+    client: &GithubClient,
+    full_name: &str,
+    enabled: bool,
+    steps: F,
+) -> Result<T>
+where
+    E: Into<GithubClientError>,
+    F: std::future::Future<Output = std::result::Result<T, E>>,
+{
+    let err: GithubClientError = match steps.await {
+        Ok(value) => return Ok(value),
+        Err(err) => err.into(),
+    };
+    if !enabled {
+        return Err(err);
+    }
+
+    warn!("Provisioning '{}' failed, rolling back: {}", full_name, err);
+    match delete_repo_with_client(client, full_name).await {
+        Ok(()) => Err(GithubClientError::RolledBack {
+            full_name: full_name.to_string(),
+            source: Box::new(err),
+        }),
+        Err(delete_err) => Err(GithubClientError::RollbackFailed {
+            full_name: full_name.to_string(),
+            delete_error: Box::new(delete_err),
+            source: Box::new(err),
+        }),
+    }
+}
+
+/// Fetches a repository's name, URL and default branch.
+pub async fn get_repo(api_base: &str, token: &str, full_name: &str) -> Result<RepoResponse> {
+    get_repo_with_client(&GithubClient::new(api_base, token)?, full_name).await
+}
+
+/// [`get_repo`] on an existing client.
+pub async fn get_repo_with_client(client: &GithubClient, full_name: &str) -> Result<RepoResponse> {
     let (owner, repo) = split_template_name(full_name)?;
-    let base_ref_url = format!(
-        "{}/repos/{}/{}/git/ref/heads/{}",
-        api_base.trim_end_matches('/'),
-        owner,
-        repo,
-        base_branch
-    );
+    let url = format!("{}/repos/{}/{}", client.api_base(), owner, repo);
 
-    let mut headers = HeaderMap::new();
-    headers.insert(
-        AUTHORIZATION,
-        HeaderValue::from_str(&format!("Bearer {}", token))?,
-    );
-    headers.insert(
-        ACCEPT,
-        HeaderValue::from_static("application/vnd.github+json"),
-    );
-    headers.insert(
-        USER_AGENT,
-        HeaderValue::from_static("github-client-rust/0.1"),
-    );
-    headers.insert(
-        HeaderName::from_static("x-github-api-version"),
-        HeaderValue::from_static("2022-11-28"),
-    );
+    match client.get_json_cached(&url).await? {
+        Ok(repo) => Ok(repo),
+        Err(resp) => {
+            Err(
+                error_from_response(client, resp, &format!("read repository '{}'", full_name))
+                    .await,
+            )
+        }
+    }
+}
 
-    let client = reqwest::Client::builder()
-        .default_headers(headers.clone())
-        .build()?;
+/// Returns whether `full_name` exists and is visible to the token.
+///
+/// 200 maps to `true`, 404 to `false`; anything else is an error.
+pub async fn repo_exists(api_base: &str, token: &str, full_name: &str) -> Result<bool> {
+    repo_exists_with_client(&GithubClient::new(api_base, token)?, full_name).await
+}
 
-    // Get base branch SHA
-    let base_resp = client.get(&base_ref_url).send().await?;
-    if !base_resp.status().is_success() {
-        let text = base_resp.text().await.unwrap_or_default();
-        return Err(anyhow!(format!(
-            "Failed to read base branch '{}': {}",
-            base_branch, text
-        )));
+/// [`repo_exists`] on an existing client.
+pub async fn repo_exists_with_client(client: &GithubClient, full_name: &str) -> Result<bool> {
+    let (owner, repo) = split_template_name(full_name)?;
+    let url = format!("{}/repos/{}/{}", client.api_base(), owner, repo);
+
+    let resp = client.send(client.get(&url)).await?;
+    match resp.status().as_u16() {
+        200 => Ok(true),
+        404 => Ok(false),
+        _ => Err(error_from_response(
+            client,
+            resp,
+            &format!("check whether '{}' exists", full_name),
+        )
+        .await),
     }
-    let base_ref: GitRef = base_resp.json().await?;
-    let sha = base_ref.obj.sha;
+}
 
-    // Create new ref
-    let create_ref_url = format!(
-        "{}/repos/{}/{}/git/refs",
-        api_base.trim_end_matches('/'),
-        owner,
-        repo
-    );
-    let payload = serde_json::json!({
-        "ref": format!("refs/heads/{}", new_branch),
-        "sha": sha
-    });
-    let create_resp = client.post(create_ref_url).json(&payload).send().await?;
-    if create_resp.status().is_success() || create_resp.status().as_u16() == 201 {
-        info!("Created branch '{}'", new_branch);
+#[derive(Deserialize)]
+struct AuthenticatedUser {
+    login: String,
+}
+
+/// Returns the login of the user the token belongs to.
+pub async fn get_authenticated_user(api_base: &str, token: &str) -> Result<String> {
+    get_authenticated_user_with_client(&GithubClient::new(api_base, token)?).await
+}
+
+/// [`get_authenticated_user`] on an existing client.
+pub async fn get_authenticated_user_with_client(client: &GithubClient) -> Result<String> {
+    let url = format!("{}/user", client.api_base());
+
+    let resp = client.send(client.get(&url)).await?;
+    if !resp.status().is_success() {
+        return Err(error_from_response(client, resp, "read the authenticated user").await);
+    }
+    let user: AuthenticatedUser = resp.json().await?;
+    Ok(user.login)
+}
+
+/// Checks up front that a classic token has every scope in `required`, so a
+/// missing scope fails the run before any change instead of as a 403 midway.
+///
+/// Convenience wrapper for `verify_token(..).await?.require_scopes(..)`; call
+/// those directly to also use the rest of [`TokenInfo`]. Fine-grained and
+/// GitHub App tokens report no classic scopes, so for them the check passes.
+/// Broader scopes count for the narrower ones they include, e.g. `repo`
+/// satisfies `public_repo` and `admin:org` satisfies `read:org`.
+pub async fn check_token_scopes(api_base: &str, token: &str, required: &[&str]) -> Result<()> {
+    check_token_scopes_with_client(&GithubClient::new(api_base, token)?, required).await
+}
+
+/// [`check_token_scopes`] on an existing client.
+pub async fn check_token_scopes_with_client(
+    client: &GithubClient,
+    required: &[&str],
+) -> Result<()> {
+    verify_token_with_client(client)
+        .await?
+        .require_scopes(required)
+}
+
+/// The classic OAuth scopes listed in a response's `X-OAuth-Scopes` header,
+/// or `None` when the header is absent (fine-grained and app tokens).
+fn parse_scopes(headers: &reqwest::header::HeaderMap) -> Option<Vec<String>> {
+    let header = headers.get("x-oauth-scopes")?;
+    Some(
+        header
+            .to_str()
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(str::to_string)
+            .collect(),
+    )
+}
+
+fn missing_scopes(granted: &[String], required: &[&str]) -> Result<()> {
+    let missing: Vec<String> = required
+        .iter()
+        .filter(|r| !granted.iter().any(|g| scope_covers(g, r)))
+        .map(|r| r.to_string())
+        .collect();
+    if missing.is_empty() {
         Ok(())
     } else {
-        let text = create_resp.text().await.unwrap_or_default();
-        Err(anyhow!(format!(
-            "Failed to create branch '{}': {}",
-            new_branch, text
-        )))
+        Err(GithubClientError::MissingScopes { missing })
     }
 }
 
-#[derive(Serialize)]
-struct DeploymentBranchPolicy {
-    protected_branches: bool,
-    custom_branch_policies: bool,
+/// Kind of token, guessed from its prefix.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TokenKind {
+    /// Classic personal access token (`ghp_`).
+    Classic,
+    /// Fine-grained personal access token (`github_pat_`).
+    FineGrained,
+    /// GitHub App installation token (`ghs_`).
+    Installation,
+    /// Anything else, e.g. an OAuth app token or a pre-2021 classic token.
+    Other,
 }
 
-#[derive(Serialize)]
-struct EnvironmentRequest {
-    deployment_branch_policy: DeploymentBranchPolicy,
+impl TokenKind {
+    pub fn from_token(token: &str) -> Self {
+        if token.starts_with("github_pat_") {
+            TokenKind::FineGrained
+        } else if token.starts_with("ghs_") {
+            TokenKind::Installation
+        } else if token.starts_with("ghp_") {
+            TokenKind::Classic
+        } else {
+            TokenKind::Other
+        }
+    }
+}
+
+/// Core API rate limit of the token at the time of [`verify_token`].
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimit {
+    pub limit: u64,
+    pub remaining: u64,
+    /// Unix time (seconds) at which `remaining` resets.
+    pub reset: u64,
+}
+
+/// What [`verify_token`] learned about a token.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct TokenInfo {
+    /// The authenticated user; `None` for installation tokens, which cannot
+    /// read `GET /user`.
+    pub login: Option<String>,
+    /// Classic OAuth scopes, or `None` when the token does not report any
+    /// (fine-grained and installation tokens).
+    pub scopes: Option<Vec<String>>,
+    pub kind: TokenKind,
+    pub rate_limit: RateLimit,
+}
+
+impl TokenInfo {
+    /// Fails with [`GithubClientError::MissingScopes`] listing every scope
+    /// in `required` the token lacks. Tokens without classic scopes pass;
+    /// their permissions only show up as 403s on the calls themselves.
+    pub fn require_scopes(&self, required: &[&str]) -> Result<()> {
+        match &self.scopes {
+            Some(granted) => missing_scopes(granted, required),
+            None => Ok(()),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct RateLimitResponse {
+    resources: RateLimitResources,
+}
+
+#[derive(Deserialize)]
+struct RateLimitResources {
+    core: RateLimit,
+}
+
+/// Checks that `token` works before anything is changed: reads the
+/// authenticated user (with its `X-OAuth-Scopes`) and the core rate limit.
+///
+/// An expired or revoked token fails with [`GithubClientError::Unauthorized`].
+/// Installation tokens skip `GET /user`, which they are not allowed to read.
+pub async fn verify_token(api_base: &str, token: &str) -> Result<TokenInfo> {
+    verify_token_with_client(&GithubClient::new(api_base, token)?).await
+}
+
+/// [`verify_token`] on an existing client.
+pub async fn verify_token_with_client(client: &GithubClient) -> Result<TokenInfo> {
+    let kind = TokenKind::from_token(client.token());
+
+    let (login, scopes) = if kind == TokenKind::Installation {
+        (None, None)
+    } else {
+        let resp = client
+            .send(client.get(format!("{}/user", client.api_base())))
+            .await?;
+        if !resp.status().is_success() {
+            return Err(error_from_response(client, resp, "read the authenticated user").await);
+        }
+        let scopes = parse_scopes(resp.headers());
+        let user: AuthenticatedUser = resp.json().await?;
+        (Some(user.login), scopes)
+    };
+
+    let resp = client
+        .send(client.get(format!("{}/rate_limit", client.api_base())))
+        .await?;
+    if !resp.status().is_success() {
+        return Err(error_from_response(client, resp, "read the rate limit").await);
+    }
+    let rate: RateLimitResponse = resp.json().await?;
+
+    let info = TokenInfo {
+        login,
+        scopes,
+        kind,
+        rate_limit: rate.resources.core,
+    };
+    debug!("Token info: {:?}", info);
+    Ok(info)
+}
+
+/// Whether the granted classic scope `granted` includes `required`.
+fn scope_covers(granted: &str, required: &str) -> bool {
+    if granted == required {
+        return true;
+    }
+    match granted {
+        "repo" => matches!(
+            required,
+            "public_repo" | "repo:status" | "repo_deployment" | "repo:invite" | "security_events"
+        ),
+        "admin:org" => matches!(required, "write:org" | "read:org"),
+        "write:org" => required == "read:org",
+        "admin:repo_hook" => matches!(required, "write:repo_hook" | "read:repo_hook"),
+        "write:repo_hook" => required == "read:repo_hook",
+        "user" => matches!(required, "read:user" | "user:email" | "user:follow"),
+        _ => false,
+    }
+}
+
+/// Waits for a repository that was just generated to become readable.
+///
+/// Right after a successful generate call, `GET /repos/{owner}/{repo}` can
+/// briefly return 404 due to replication lag. Since we know the repository was
+/// just created, a 404 is treated as transient until `max_wait` elapses.
+pub async fn wait_for_created_repo(
+    api_base: &str,
+    token: &str,
+    full_name: &str,
+    max_wait: Duration,
+) -> Result<RepoResponse> {
+    wait_for_created_repo_with_client(&GithubClient::new(api_base, token)?, full_name, max_wait)
+        .await
+}
+
+/// [`wait_for_created_repo`] on an existing client.
+pub async fn wait_for_created_repo_with_client(
+    client: &GithubClient,
+    full_name: &str,
+    max_wait: Duration,
+) -> Result<RepoResponse> {
+    let (owner, repo) = split_template_name(full_name)?;
+    let url = format!("{}/repos/{}/{}", client.api_base(), owner, repo);
+
+    let start = tokio::time::Instant::now();
+    let mut delay = Duration::from_millis(400);
+    loop {
+        let resp = client.send(client.get(&url)).await?;
+        match resp.status().as_u16() {
+            200 => {
+                debug!("Repository '{}' is available", full_name);
+                return Ok(resp.json().await?);
+            }
+            404 => {
+                if start.elapsed() >= max_wait {
+                    return Err(GithubClientError::Timeout {
+                        operation: format!(
+                            "repository '{}' was created but is still not visible after {:?}",
+                            full_name, max_wait
+                        ),
+                    });
+                }
+                debug!(
+                    "Repository '{}' not visible yet (just created), retrying...",
+                    full_name
+                );
+                sleep(delay).await;
+                delay = std::cmp::min(delay * 2, Duration::from_secs(2));
+            }
+            _ => {
+                return Err(error_from_response(
+                    client,
+                    resp,
+                    &format!("read newly created repository '{}'", full_name),
+                )
+                .await);
+            }
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct TreeListing {
+    tree: Vec<serde_json::Value>,
+}
+
+/// Waits until a repository generated from a template has its content.
+///
+/// Template repositories are copied asynchronously, so right after creation
+/// the branch ref may not exist yet and reading it fails with 404 or
+/// 409 "Git Repository is empty". Polls until `branch` exists and its tree
+/// has at least one entry, backing off like [`wait_for_created_repo`]. On
+/// timeout the error says which of the two was still missing.
+pub async fn wait_for_repo_ready(
+    api_base: &str,
+    token: &str,
+    full_name: &str,
+    branch: &str,
+    max_wait: Duration,
+) -> Result<()> {
+    wait_for_repo_ready_with_client(
+        &GithubClient::new(api_base, token)?,
+        full_name,
+        branch,
+        max_wait,
+    )
+    .await
+}
+
+/// [`wait_for_repo_ready`] on an existing client.
+pub async fn wait_for_repo_ready_with_client(
+    client: &GithubClient,
+    full_name: &str,
+    branch: &str,
+    max_wait: Duration,
+) -> Result<()> {
+    validate_ref_name(branch)?;
+    let (owner, repo) = split_template_name(full_name)?;
+    let ref_url = format!(
+        "{}/repos/{}/{}/git/ref/heads/{}",
+        client.api_base(),
+        owner,
+        repo,
+        encode_path(branch)
+    );
+    let tree_url = format!(
+        "{}/repos/{}/{}/git/trees/{}",
+        client.api_base(),
+        owner,
+        repo,
+        encode_path(branch)
+    );
+
+    let start = tokio::time::Instant::now();
+    let mut delay = Duration::from_millis(400);
+    let mut ref_found = false;
+    loop {
+        let pending = if !ref_found {
+            let resp = client.send(client.get(&ref_url)).await?;
+            match resp.status().as_u16() {
+                200 => {
+                    debug!("Branch '{}' of '{}' exists", branch, full_name);
+                    ref_found = true;
+                    continue;
+                }
+                404 | 409 => format!("branch '{}' does not exist yet", branch),
+                _ => {
+                    return Err(error_from_response(
+                        client,
+                        resp,
+                        &format!("read branch '{}' of '{}'", branch, full_name),
+                    )
+                    .await)
+                }
+            }
+        } else {
+            let resp = client.send(client.get(&tree_url)).await?;
+            match resp.status().as_u16() {
+                200 => {
+                    let listing: TreeListing = resp.json().await?;
+                    if !listing.tree.is_empty() {
+                        debug!("Repository '{}' has content on '{}'", full_name, branch);
+                        return Ok(());
+                    }
+                    format!("the tree of '{}' is still empty", branch)
+                }
+                404 | 409 => format!("the tree of '{}' is not readable yet", branch),
+                _ => {
+                    return Err(error_from_response(
+                        client,
+                        resp,
+                        &format!("read the tree of '{}' in '{}'", branch, full_name),
+                    )
+                    .await)
+                }
+            }
+        };
+
+        if start.elapsed() >= max_wait {
+            return Err(GithubClientError::Timeout {
+                operation: format!(
+                    "repository '{}' was not ready after {:?}: {}",
+                    full_name, max_wait, pending
+                ),
+            });
+        }
+        debug!(
+            "Repository '{}' not ready ({}), retrying...",
+            full_name, pending
+        );
+        sleep(delay).await;
+        delay = std::cmp::min(delay * 2, Duration::from_secs(2));
+    }
+}
+
+#[derive(Deserialize)]
+struct GitRef {
+    #[serde(rename = "object")]
+    obj: GitObject,
+}
+
+#[derive(Deserialize)]
+struct GitObject {
+    sha: String,
+}
+
+/// Creates `new_branch` at the head of `base_branch` and returns the commit
+/// SHA it points at. A `new_branch` that already exists at that commit counts
+/// as created, so re-runs succeed; one pointing elsewhere is an error. In dry
+/// run nothing is read or written and the SHA is empty.
+pub async fn create_branch_from_base(
+    api_base: &str,
+    token: &str,
+    full_name: &str,
+    base_branch: &str,
+    new_branch: &str,
+) -> Result<String> {
+    create_branch_from_base_with_client(
+        &GithubClient::new(api_base, token)?,
+        full_name,
+        base_branch,
+        new_branch,
+    )
+    .await
+}
+
+/// [`create_branch_from_base`] on an existing client.
+pub async fn create_branch_from_base_with_client(
+    client: &GithubClient,
+    full_name: &str,
+    base_branch: &str,
+    new_branch: &str,
+) -> Result<String> {
+    validate_ref_name(base_branch)?;
+    validate_ref_name(new_branch)?;
+    let (owner, repo) = split_template_name(full_name)?;
+    let base_ref_url = format!(
+        "{}/repos/{}/{}/git/ref/heads/{}",
+        client.api_base(),
+        owner,
+        repo,
+        encode_path(base_branch)
+    );
+
+    if client.is_dry_run() {
+        client.record(
+            Method::POST,
+            &format!("{}/repos/{}/{}/git/refs", client.api_base(), owner, repo),
+            format!("create branch '{}' from '{}'", new_branch, base_branch),
+        );
+        return Ok(String::new());
+    }
+
+    // A repository generated from a template may still be importing its content
+    wait_for_repo_ready_with_client(client, full_name, base_branch, client.ready_wait()).await?;
+
+    // Get base branch SHA
+    let base_resp = client.send(client.get(&base_ref_url)).await?;
+    if !base_resp.status().is_success() {
+        return Err(error_from_response(
+            client,
+            base_resp,
+            &format!("read base branch '{}'", base_branch),
+        )
+        .await);
+    }
+    let base_ref: GitRef = base_resp.json().await?;
+    let sha = base_ref.obj.sha;
+
+    // Create new ref
+    let create_ref_url = format!("{}/repos/{}/{}/git/refs", client.api_base(), owner, repo);
+    let payload = serde_json::json!({
+        "ref": format!("refs/heads/{}", new_branch),
+        "sha": sha
+    });
+    let create_resp = client
+        .send(client.post(create_ref_url).json(&payload))
+        .await?;
+    if create_resp.status().is_success() {
+        let created: GitRef = create_resp.json().await?;
+        info!("Created branch '{}'", new_branch);
+        return Ok(created.obj.sha);
+    }
+    let err = error_from_response(
+        client,
+        create_resp,
+        &format!("create branch '{}'", new_branch),
+    )
+    .await;
+    if !err.is_already_exists() {
+        return Err(err);
+    }
+
+    // Creating a branch that already points at the base commit is a no-op
+    let existing_url = format!(
+        "{}/repos/{}/{}/git/ref/heads/{}",
+        client.api_base(),
+        owner,
+        repo,
+        encode_path(new_branch)
+    );
+    let existing_resp = client.send(client.get(&existing_url)).await?;
+    if !existing_resp.status().is_success() {
+        return Err(err);
+    }
+    let existing: GitRef = existing_resp.json().await?;
+    if existing.obj.sha == sha {
+        info!(
+            "Branch '{}' already exists at '{}'",
+            new_branch, base_branch
+        );
+        return Ok(sha);
+    }
+    match err {
+        GithubClientError::ValidationFailed { errors, .. } => {
+            Err(GithubClientError::ValidationFailed {
+                message: format!(
+                    "cannot create branch '{}': it already exists at {}, not at '{}' ({})",
+                    new_branch, existing.obj.sha, base_branch, sha
+                ),
+                errors,
+            })
+        }
+        other => Err(other),
+    }
+}
+
+/// Creates `new_branch` from `base_branch` unless it already exists.
+pub async fn ensure_branch(
+    api_base: &str,
+    token: &str,
+    full_name: &str,
+    base_branch: &str,
+    new_branch: &str,
+) -> Result<ReconcileStatus> {
+    ensure_branch_with_client(
+        &GithubClient::new(api_base, token)?,
+        full_name,
+        base_branch,
+        new_branch,
+    )
+    .await
+}
+
+/// [`ensure_branch`] on an existing client.
+pub async fn ensure_branch_with_client(
+    client: &GithubClient,
+    full_name: &str,
+    base_branch: &str,
+    new_branch: &str,
+) -> Result<ReconcileStatus> {
+    validate_ref_name(base_branch)?;
+    validate_ref_name(new_branch)?;
+    let (owner, repo) = split_template_name(full_name)?;
+    let url = format!(
+        "{}/repos/{}/{}/branches/{}",
+        client.api_base(),
+        owner,
+        repo,
+        encode_path(new_branch)
+    );
+
+    let resp = client.send(client.get(&url)).await?;
+    if resp.status().is_success() {
+        info!("Branch '{}' already exists", new_branch);
+        if client.is_dry_run() {
+            client.record_skip(format!("branch '{}' (already exists)", new_branch));
+        }
+        return Ok(ReconcileStatus::Compliant);
+    }
+    if resp.status().as_u16() != 404 {
+        return Err(error_from_response(
+            client,
+            resp,
+            &format!("check whether branch '{}' exists", new_branch),
+        )
+        .await);
+    }
+    create_branch_from_base_with_client(client, full_name, base_branch, new_branch).await?;
+    Ok(ReconcileStatus::Changed)
+}
+
+/// A pull request opened with [`create_pull_request`].
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct PullRequest {
+    pub number: u64,
+    pub html_url: String,
+    #[serde(default)]
+    pub draft: bool,
+}
+
+/// What [`create_pull_request`] did.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PullRequestOutcome {
+    Opened(PullRequest),
+    /// GitHub had nothing to compare: `head` has no commits that `base`
+    /// lacks, e.g. a `dev` branch just created from `main`.
+    NothingToCompare,
+    /// Dry run: the request was only recorded.
+    Planned,
+}
+
+/// Opens a pull request from `head` into `base`. Having nothing to compare
+/// is reported as [`PullRequestOutcome::NothingToCompare`] rather than as an
+/// error.
+#[allow(clippy::too_many_arguments)]
+pub async fn create_pull_request(
+    api_base: &str,
+    token: &str,
+    full_name: &str,
+    head: &str,
+    base: &str,
+    title: &str,
+    body: &str,
+    draft: bool,
+) -> Result<PullRequestOutcome> {
+    create_pull_request_with_client(
+        &GithubClient::new(api_base, token)?,
+        full_name,
+        head,
+        base,
+        title,
+        body,
+        draft,
+    )
+    .await
+}
+
+/// [`create_pull_request`] on an existing client.
+pub async fn create_pull_request_with_client(
+    client: &GithubClient,
+    full_name: &str,
+    head: &str,
+    base: &str,
+    title: &str,
+    body: &str,
+    draft: bool,
+) -> Result<PullRequestOutcome> {
+    let (owner, repo) = split_template_name(full_name)?;
+    let url = format!("{}/repos/{}/{}/pulls", client.api_base(), owner, repo);
+
+    if client.is_dry_run() {
+        client.record(
+            Method::POST,
+            &url,
+            format!(
+                "open {}pull request '{}' from '{}' into '{}'",
+                if draft { "draft " } else { "" },
+                title,
+                head,
+                base
+            ),
+        );
+        return Ok(PullRequestOutcome::Planned);
+    }
+
+    let payload = serde_json::json!({
+        "title": title,
+        "head": head,
+        "base": base,
+        "body": body,
+        "draft": draft,
+    });
+    let resp = client.send(client.post(&url).json(&payload)).await?;
+    if resp.status().is_success() {
+        let pr: PullRequest = resp.json().await?;
+        info!(
+            "Opened pull request #{} from '{}' into '{}': {}",
+            pr.number, head, base, pr.html_url
+        );
+        return Ok(PullRequestOutcome::Opened(pr));
+    }
+    let context = response_context(resp.headers());
+    match error_from_response(
+        client,
+        resp,
+        &format!("open pull request from '{}' into '{}'", head, base),
+    )
+    .await
+    {
+        err if err.is_nothing_to_compare() => Ok(PullRequestOutcome::NothingToCompare),
+        GithubClientError::NotFound { .. } => Err(GithubClientError::NotFound {
+            resource: format!(
+                "repository '{}' or branch '{}' / '{}'{}",
+                full_name, head, base, context
+            ),
+        }),
+        err => Err(err),
+    }
+}
+
+#[derive(Serialize)]
+struct DeploymentBranchPolicy {
+    protected_branches: bool,
+    custom_branch_policies: bool,
+}
+
+/// Kind of principal allowed to approve deployments to an environment.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReviewerType {
+    User,
+    Team,
+}
+
+/// Required reviewer for an environment, identified by numeric id.
+///
+/// Use [`get_user_id`] or [`get_team_id`] to resolve logins and team slugs.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Reviewer {
+    #[serde(rename = "type")]
+    pub reviewer_type: ReviewerType,
+    pub id: u64,
+}
+
+/// Settings for [`ensure_environment`]; unset fields are left out of the request.
+#[derive(Debug, Clone, Default)]
+pub struct EnvironmentOptions<'a> {
+    /// Branch name patterns allowed to deploy to the environment.
+    pub allowed_patterns: &'a [&'a str],
+    /// Minutes to wait before a deployment may proceed.
+    pub wait_timer: Option<u32>,
+    pub reviewers: &'a [Reviewer],
+    pub prevent_self_review: Option<bool>,
+    /// Delete branch policies that are not in `allowed_patterns`, so the
+    /// environment allows exactly what is declared.
+    pub prune: bool,
+}
+
+#[derive(Serialize)]
+struct EnvironmentRequest<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    wait_timer: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    prevent_self_review: Option<bool>,
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    reviewers: &'a [Reviewer],
+    deployment_branch_policy: DeploymentBranchPolicy,
+}
+
+/// What [`ensure_environment`] changed. In dry run, `branch_policies_added`
+/// and `branch_policies_removed` list what would change.
+#[derive(Serialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct EnvironmentReport {
+    pub name: String,
+    pub branch_policies_added: Vec<String>,
+    /// Already allowed before this run.
+    pub branch_policies_existing: Vec<String>,
+    /// Patterns GitHub rejected as invalid (422); see the warnings in the log.
+    pub branch_policies_failed: Vec<String>,
+    /// Deleted because they were not declared; only with [`EnvironmentOptions::prune`].
+    pub branch_policies_removed: Vec<String>,
+}
+
+pub async fn ensure_environment_with_branches(
+    api_base: &str,
+    token: &str,
+    full_name: &str,
+    env_name: &str,
+    allowed_patterns: &[&str],
+) -> Result<EnvironmentReport> {
+    ensure_environment_with_branches_with_client(
+        &GithubClient::new(api_base, token)?,
+        full_name,
+        env_name,
+        allowed_patterns,
+    )
+    .await
+}
+
+/// [`ensure_environment_with_branches`] on an existing client.
+pub async fn ensure_environment_with_branches_with_client(
+    client: &GithubClient,
+    full_name: &str,
+    env_name: &str,
+    allowed_patterns: &[&str],
+) -> Result<EnvironmentReport> {
+    let options = EnvironmentOptions {
+        allowed_patterns,
+        ..Default::default()
+    };
+    ensure_environment_with_client(client, full_name, env_name, &options).await
+}
+
+/// Creates or updates an environment with custom deployment branch policies,
+/// plus optional protection rules (required reviewers, wait timer).
+///
+/// Existing policies are listed first and only missing patterns are added, so
+/// re-runs do not fail on duplicates. A pattern GitHub rejects as invalid is
+/// reported in `branch_policies_failed`; any other failure is an error.
+pub async fn ensure_environment(
+    api_base: &str,
+    token: &str,
+    full_name: &str,
+    env_name: &str,
+    options: &EnvironmentOptions<'_>,
+) -> Result<EnvironmentReport> {
+    ensure_environment_with_client(
+        &GithubClient::new(api_base, token)?,
+        full_name,
+        env_name,
+        options,
+    )
+    .await
+}
+
+/// [`ensure_environment`] on an existing client.
+pub async fn ensure_environment_with_client(
+    client: &GithubClient,
+    full_name: &str,
+    env_name: &str,
+    options: &EnvironmentOptions<'_>,
+) -> Result<EnvironmentReport> {
+    validate_env_name(env_name)?;
+    let (owner, repo) = split_template_name(full_name)?;
+    let base = client.api_base();
+    let env_url = format!(
+        "{}/repos/{}/{}/environments/{}",
+        base,
+        owner,
+        repo,
+        encode_segment(env_name)
+    );
+    let policy_url = format!("{}/deployment-branch-policies", env_url);
+
+    // Enable custom branch policies
+    let body = EnvironmentRequest {
+        wait_timer: options.wait_timer,
+        prevent_self_review: options.prevent_self_review,
+        reviewers: options.reviewers,
+        deployment_branch_policy: DeploymentBranchPolicy {
+            protected_branches: false,
+            custom_branch_policies: true,
+        },
+    };
+    let mut report = EnvironmentReport {
+        name: env_name.to_string(),
+        ..Default::default()
+    };
+    if client.is_dry_run() {
+        client.record(
+            Method::PUT,
+            &env_url,
+            format!(
+                "environment '{}' (wait_timer={:?}, {} reviewers)",
+                env_name,
+                options.wait_timer,
+                options.reviewers.len()
+            ),
+        );
+    } else {
+        let resp = client.send(client.put(&env_url).json(&body)).await?;
+        if !resp.status().is_success() {
+            return Err(error_from_response(
+                client,
+                resp,
+                &format!("create/update environment '{}'", env_name),
+            )
+            .await);
+        }
+    }
+
+    // Add allowed branch patterns; GitHub rejects duplicates, so skip existing ones
+    let existing = existing_branch_policies(client, &env_url).await?;
+    for pat in options.allowed_patterns {
+        if existing.contains_key(*pat) {
+            debug!(
+                "Environment '{}' already allows branches matching '{}'",
+                env_name, pat
+            );
+            if client.is_dry_run() {
+                client.record_skip(format!(
+                    "branch policy '{}' on environment '{}' (already exists)",
+                    pat, env_name
+                ));
+            }
+            report.branch_policies_existing.push(pat.to_string());
+            continue;
+        }
+        if client.is_dry_run() {
+            client.record(
+                Method::POST,
+                &policy_url,
+                format!("allow branches matching '{}'", pat),
+            );
+            report.branch_policies_added.push(pat.to_string());
+            continue;
+        }
+        let payload = serde_json::json!({ "name": pat });
+        let add = client.send(client.post(&policy_url).json(&payload)).await?;
+        if add.status().is_success() {
+            info!(
+                "Environment '{}' allows branches matching '{}'",
+                env_name, pat
+            );
+            report.branch_policies_added.push(pat.to_string());
+            continue;
+        }
+        let err = error_from_response(
+            client,
+            add,
+            &format!("add branch policy '{}' to environment '{}'", pat, env_name),
+        )
+        .await;
+        if err.is_already_exists() {
+            // Added concurrently since we listed the policies
+            report.branch_policies_existing.push(pat.to_string());
+        } else if matches!(err, GithubClientError::ValidationFailed { .. }) {
+            warn!("{}", err);
+            report.branch_policies_failed.push(pat.to_string());
+        } else {
+            return Err(err);
+        }
+    }
+
+    if options.prune {
+        let mut extra: Vec<(&String, &u64)> = existing
+            .iter()
+            .filter(|(name, _)| !options.allowed_patterns.contains(&name.as_str()))
+            .collect();
+        extra.sort();
+        for (name, id) in extra {
+            let url = format!("{}/{}", policy_url, id);
+            if client.is_dry_run() {
+                client.record(
+                    Method::DELETE,
+                    &url,
+                    format!("remove undeclared branch policy '{}'", name),
+                );
+            } else {
+                let resp = client.send(client.delete(&url)).await?;
+                if !resp.status().is_success() && resp.status().as_u16() != 404 {
+                    return Err(error_from_response(
+                        client,
+                        resp,
+                        &format!(
+                            "remove branch policy '{}' from environment '{}'",
+                            name, env_name
+                        ),
+                    )
+                    .await);
+                }
+                info!(
+                    "Removed branch policy '{}' from environment '{}'",
+                    name, env_name
+                );
+            }
+            report.branch_policies_removed.push(name.clone());
+        }
+    }
+    Ok(report)
+}
+
+const BRANCH_POLICIES_PER_PAGE: usize = 100;
+
+#[derive(Deserialize)]
+struct BranchPolicyPage {
+    total_count: usize,
+    branch_policies: Vec<BranchPolicy>,
+}
+
+#[derive(Deserialize)]
+struct BranchPolicy {
+    id: u64,
+    name: String,
+}
+
+/// Deployment branch policies already on an environment, by name, with
+/// their ids, across all pages. In dry run a missing environment has none.
+async fn existing_branch_policies(
+    client: &GithubClient,
+    env_url: &str,
+) -> Result<HashMap<String, u64>> {
+    let url = format!("{}/deployment-branch-policies", env_url);
+    let mut policies = HashMap::new();
+    let mut seen = 0;
+    for page in 1.. {
+        let resp = client
+            .send(
+                client
+                    .get(&url)
+                    .query(&[("per_page", BRANCH_POLICIES_PER_PAGE), ("page", page)]),
+            )
+            .await?;
+        if resp.status().as_u16() == 404 && client.is_dry_run() {
+            break;
+        }
+        if !resp.status().is_success() {
+            return Err(error_from_response(client, resp, "list deployment branch policies").await);
+        }
+        let body: BranchPolicyPage = resp.json().await?;
+        let count = body.branch_policies.len();
+        seen += count;
+        policies.extend(body.branch_policies.into_iter().map(|p| (p.name, p.id)));
+        if count < BRANCH_POLICIES_PER_PAGE || seen >= body.total_count {
+            break;
+        }
+    }
+    Ok(policies)
+}
+
+#[derive(Deserialize)]
+struct IdResponse {
+    id: u64,
+}
+
+/// Resolves a username to the numeric id the environments API expects.
+pub async fn get_user_id(api_base: &str, token: &str, username: &str) -> Result<u64> {
+    get_user_id_with_client(&GithubClient::new(api_base, token)?, username).await
+}
+
+/// [`get_user_id`] on an existing client.
+pub async fn get_user_id_with_client(client: &GithubClient, username: &str) -> Result<u64> {
+    let url = format!("{}/users/{}", client.api_base(), encode_segment(username));
+    let resp = client.send(client.get(&url)).await?;
+    if !resp.status().is_success() {
+        return Err(
+            error_from_response(client, resp, &format!("resolve user '{}'", username)).await,
+        );
+    }
+    let user: IdResponse = resp.json().await?;
+    Ok(user.id)
+}
+
+/// Resolves an organization team slug to the numeric id the environments API expects.
+pub async fn get_team_id(api_base: &str, token: &str, org: &str, team_slug: &str) -> Result<u64> {
+    get_team_id_with_client(&GithubClient::new(api_base, token)?, org, team_slug).await
+}
+
+/// [`get_team_id`] on an existing client.
+pub async fn get_team_id_with_client(
+    client: &GithubClient,
+    org: &str,
+    team_slug: &str,
+) -> Result<u64> {
+    let url = format!(
+        "{}/orgs/{}/teams/{}",
+        client.api_base(),
+        encode_segment(org),
+        encode_segment(team_slug)
+    );
+    let resp = client.send(client.get(&url)).await?;
+    if !resp.status().is_success() {
+        return Err(error_from_response(
+            client,
+            resp,
+            &format!("resolve team '{}/{}'", org, team_slug),
+        )
+        .await);
+    }
+    let team: IdResponse = resp.json().await?;
+    Ok(team.id)
+}
+
+#[derive(Deserialize)]
+struct PublicKeyResponse {
+    key_id: String,
+    key: String,
+}
+
+/// Encrypts `plaintext` for GitHub Actions with a libsodium sealed box.
+///
+/// `public_key_b64` is the base64 `key` returned by the `secrets/public-key`
+/// endpoints; the result is the base64 `encrypted_value` GitHub expects.
+pub fn seal_secret(public_key_b64: &str, plaintext: &str) -> Result<String> {
+    let key_bytes = BASE64.decode(public_key_b64).map_err(|e| {
+        GithubClientError::InvalidInput(format!("Invalid public key encoding: {}", e))
+    })?;
+    let public_key = PublicKey::from_slice(&key_bytes).map_err(|_| {
+        GithubClientError::InvalidInput(format!(
+            "Invalid public key length: {} bytes",
+            key_bytes.len()
+        ))
+    })?;
+    let sealed = public_key
+        .seal(&mut OsRng, plaintext.as_bytes())
+        .map_err(|_| {
+            GithubClientError::InvalidInput("Failed to encrypt secret value".to_string())
+        })?;
+    Ok(BASE64.encode(sealed))
+}
+
+/// Sets the Actions variable `name` in environment `env_name`, creating it
+/// or updating the existing value.
+pub async fn set_environment_variable(
+    api_base: &str,
+    token: &str,
+    full_name: &str,
+    env_name: &str,
+    name: &str,
+    value: &str,
+) -> Result<()> {
+    set_environment_variable_with_client(
+        &GithubClient::new(api_base, token)?,
+        full_name,
+        env_name,
+        name,
+        value,
+    )
+    .await
+}
+
+/// [`set_environment_variable`] on an existing client.
+pub async fn set_environment_variable_with_client(
+    client: &GithubClient,
+    full_name: &str,
+    env_name: &str,
+    name: &str,
+    value: &str,
+) -> Result<()> {
+    validate_variable_name(name)?;
+    validate_env_name(env_name)?;
+    let (owner, repo) = split_template_name(full_name)?;
+    let vars_url = format!(
+        "{}/repos/{}/{}/environments/{}/variables",
+        client.api_base(),
+        owner,
+        repo,
+        encode_segment(env_name)
+    );
+
+    upsert_variable(
+        client,
+        &vars_url,
+        name,
+        value,
+        &format!("environment '{}'", env_name),
+    )
+    .await
+}
+
+/// Sets the repository-wide Actions variable `name`, creating it or updating
+/// the existing value.
+pub async fn set_repo_variable(
+    api_base: &str,
+    token: &str,
+    full_name: &str,
+    name: &str,
+    value: &str,
+) -> Result<()> {
+    set_repo_variable_with_client(&GithubClient::new(api_base, token)?, full_name, name, value)
+        .await
+}
+
+/// [`set_repo_variable`] on an existing client.
+pub async fn set_repo_variable_with_client(
+    client: &GithubClient,
+    full_name: &str,
+    name: &str,
+    value: &str,
+) -> Result<()> {
+    validate_variable_name(name)?;
+    let (owner, repo) = split_template_name(full_name)?;
+    let vars_url = format!(
+        "{}/repos/{}/{}/actions/variables",
+        client.api_base(),
+        owner,
+        repo
+    );
+
+    upsert_variable(
+        client,
+        &vars_url,
+        name,
+        value,
+        &format!("repository '{}'", full_name),
+    )
+    .await
+}
+
+/// POSTs variable `name` to `vars_url`; on 409 (it already exists) PATCHes
+/// `{vars_url}/{name}` instead. `scope` names the owner in logs and errors.
+async fn upsert_variable(
+    client: &GithubClient,
+    vars_url: &str,
+    name: &str,
+    value: &str,
+    scope: &str,
+) -> Result<()> {
+    let payload = serde_json::json!({ "name": name, "value": value });
+    if client.is_dry_run() {
+        client.record(
+            Method::POST,
+            vars_url,
+            format!("set variable {}={} in {}", name, value, scope),
+        );
+        return Ok(());
+    }
+    let resp = client.send(client.post(vars_url).json(&payload)).await?;
+    if resp.status().is_success() {
+        info!("Variable '{}' created in {}", name, scope);
+        return Ok(());
+    }
+
+    // 409 means the variable already exists; update it in place
+    if resp.status().as_u16() == 409 {
+        let update_url = format!("{}/{}", vars_url, name);
+        let update = client
+            .send(client.patch(&update_url).json(&payload))
+            .await?;
+        if update.status().is_success() {
+            info!("Variable '{}' updated in {}", name, scope);
+            return Ok(());
+        }
+        return Err(error_from_response(
+            client,
+            update,
+            &format!("update variable '{}' in {}", name, scope),
+        )
+        .await);
+    }
+
+    Err(error_from_response(
+        client,
+        resp,
+        &format!("create variable '{}' in {}", name, scope),
+    )
+    .await)
+}
+
+/// Sets the Actions secret `name` in environment `env_name`, sealing it with
+/// the environment's public key first.
+pub async fn set_environment_secret(
+    api_base: &str,
+    token: &str,
+    full_name: &str,
+    env_name: &str,
+    name: &str,
+    plaintext: &str,
+) -> Result<()> {
+    set_environment_secret_with_client(
+        &GithubClient::new(api_base, token)?,
+        full_name,
+        env_name,
+        name,
+        plaintext,
+    )
+    .await
+}
+
+/// [`set_environment_secret`] on an existing client.
+pub async fn set_environment_secret_with_client(
+    client: &GithubClient,
+    full_name: &str,
+    env_name: &str,
+    name: &str,
+    plaintext: &str,
+) -> Result<()> {
+    validate_secret_name(name)?;
+    validate_env_name(env_name)?;
+    let (owner, repo) = split_template_name(full_name)?;
+    let secrets_url = format!(
+        "{}/repos/{}/{}/environments/{}/secrets",
+        client.api_base(),
+        owner,
+        repo,
+        encode_segment(env_name)
+    );
+
+    put_encrypted_secret(client, &secrets_url, name, plaintext).await?;
+    info!("Environment '{}' secret '{}' set", env_name, name);
+    Ok(())
+}
+
+/// Sets a GitHub Actions repository secret, encrypting it with the repository
+/// public key first.
+pub async fn set_repo_secret(
+    api_base: &str,
+    token: &str,
+    full_name: &str,
+    secret_name: &str,
+    secret_value: &str,
+) -> Result<()> {
+    set_repo_secret_with_client(
+        &GithubClient::new(api_base, token)?,
+        full_name,
+        secret_name,
+        secret_value,
+    )
+    .await
+}
+
+/// [`set_repo_secret`] on an existing client.
+pub async fn set_repo_secret_with_client(
+    client: &GithubClient,
+    full_name: &str,
+    secret_name: &str,
+    secret_value: &str,
+) -> Result<()> {
+    validate_secret_name(secret_name)?;
+    let (owner, repo) = split_template_name(full_name)?;
+    let secrets_url = format!(
+        "{}/repos/{}/{}/actions/secrets",
+        client.api_base(),
+        owner,
+        repo
+    );
+
+    put_encrypted_secret(client, &secrets_url, secret_name, secret_value).await?;
+    info!("Repository secret '{}' set on '{}'", secret_name, full_name);
+    Ok(())
+}
+
+/// Fetches the public key under `secrets_url`, seals `plaintext` with it and
+/// PUTs the result to `{secrets_url}/{name}`.
+async fn put_encrypted_secret(
+    client: &GithubClient,
+    secrets_url: &str,
+    name: &str,
+    plaintext: &str,
+) -> Result<()> {
+    if client.is_dry_run() {
+        // The value is never included in the plan
+        client.record(
+            Method::PUT,
+            &format!("{}/{}", secrets_url, name),
+            format!("set secret {} (encrypted)", name),
+        );
+        return Ok(());
+    }
+
+    // Secrets must be encrypted with the public key of their scope
+    let key_resp = client
+        .send(client.get(format!("{}/public-key", secrets_url)))
+        .await?;
+    if !key_resp.status().is_success() {
+        return Err(error_from_response(
+            client,
+            key_resp,
+            &format!("read public key for secret '{}'", name),
+        )
+        .await);
+    }
+    let public_key: PublicKeyResponse = key_resp.json().await?;
+    let encrypted_value = seal_secret(&public_key.key, plaintext)?;
+
+    let payload = serde_json::json!({
+        "encrypted_value": encrypted_value,
+        "key_id": public_key.key_id
+    });
+    let resp = client
+        .send(
+            client
+                .put(format!("{}/{}", secrets_url, name))
+                .json(&payload),
+        )
+        .await?;
+    if resp.status().is_success() {
+        return Ok(());
+    }
+
+    Err(error_from_response(client, resp, &format!("set secret '{}'", name)).await)
+}
+
+/// Checks a secret name against GitHub's naming rules: only ASCII letters,
+/// digits and underscores, not starting with a digit or the `GITHUB_` prefix.
+fn validate_secret_name(name: &str) -> Result<()> {
+    validate_actions_name("secret", name)
+}
+
+/// Variable names must additionally be upper-case, e.g. `AWS_REGION`.
+fn validate_variable_name(name: &str) -> Result<()> {
+    if name.chars().any(|c| c.is_ascii_lowercase()) {
+        return Err(GithubClientError::InvalidInput(format!(
+            "Invalid variable name '{}': use upper-case letters, digits and underscores",
+            name
+        )));
+    }
+    validate_actions_name("variable", name)
+}
+
+/// Rules GitHub applies to Actions secret and variable names; `kind` is
+/// used in the error message.
+fn validate_actions_name(kind: &str, name: &str) -> Result<()> {
+    if name.is_empty() {
+        return Err(GithubClientError::InvalidInput(format!(
+            "Invalid {} name: must not be empty",
+            kind
+        )));
+    }
+    if !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+        return Err(GithubClientError::InvalidInput(format!(
+            "Invalid {} name '{}': only letters, digits and underscores are allowed",
+            kind, name
+        )));
+    }
+    if name.starts_with(|c: char| c.is_ascii_digit()) {
+        return Err(GithubClientError::InvalidInput(format!(
+            "Invalid {} name '{}': must not start with a digit",
+            kind, name
+        )));
+    }
+    if name.to_ascii_uppercase().starts_with("GITHUB_") {
+        return Err(GithubClientError::InvalidInput(format!(
+            "Invalid {} name '{}': the GITHUB_ prefix is reserved",
+            kind, name
+        )));
+    }
+    Ok(())
+}
+
+#[derive(Deserialize)]
+struct TreeEntry {
+    path: String,
+    r#type: String,
+    sha: String,
+    #[serde(default)]
+    mode: String,
+    /// Blob size in bytes; absent for trees and submodules.
+    #[serde(default)]
+    size: Option<u64>,
+}
+
+/// Git file modes of tree entries.
+const MODE_FILE: &str = "100644";
+const MODE_SYMLINK: &str = "120000";
+const MODE_SUBMODULE: &str = "160000";
+
+impl TreeEntry {
+    fn mode(&self) -> &str {
+        if self.mode.is_empty() {
+            MODE_FILE
+        } else {
+            &self.mode
+        }
+    }
+
+    fn is_symlink(&self) -> bool {
+        self.mode() == MODE_SYMLINK
+    }
+
+    fn is_submodule(&self) -> bool {
+        self.r#type == "commit" || self.mode() == MODE_SUBMODULE
+    }
+
+    fn is_larger_than(&self, threshold: u64) -> bool {
+        self.size.is_some_and(|size| size > threshold)
+    }
+}
+
+#[derive(Deserialize)]
+struct GitTree {
+    tree: Vec<TreeEntry>,
+}
+
+#[derive(Deserialize)]
+struct Blob {
+    /// Empty for blobs too large to inline; see [`fetch_blob`].
+    #[serde(default)]
+    content: String,
+    #[serde(default)]
+    encoding: String,
+    #[serde(default)]
+    size: u64,
+}
+
+/// Downloads a blob of the repository at `repo_url` and returns its bytes.
+///
+/// Large blobs come back with an empty `content` field; those are fetched
+/// again as raw bytes instead of being seeded as empty files.
+async fn fetch_blob(
+    client: &GithubClient,
+    repo_url: &str,
+    path: &str,
+    sha: &str,
+) -> Result<Vec<u8>> {
+    let blob_url = format!("{}/git/blobs/{}", repo_url, sha);
+    let resp = client.send(client.get(&blob_url)).await?;
+    if !resp.status().is_success() {
+        return Err(error_from_response(client, resp, &format!("fetch blob for '{}'", path)).await);
+    }
+    let blob: Blob = resp.json().await?;
+    if !blob.content.is_empty() || blob.size == 0 {
+        if !blob.encoding.eq_ignore_ascii_case("base64") {
+            return Err(GithubClientError::InvalidResponse(format!(
+                "Unexpected blob encoding for '{}': {}",
+                path, blob.encoding
+            )));
+        }
+        return BASE64.decode(blob.content.replace('\n', "")).map_err(|e| {
+            GithubClientError::InvalidResponse(format!(
+                "blob for '{}' is not valid base64: {}",
+                path, e
+            ))
+        });
+    }
+
+    debug!(
+        "Blob for '{}' has no inline content ({} bytes), downloading raw",
+        path, blob.size
+    );
+    let resp = client
+        .send(
+            client
+                .get(&blob_url)
+                .header(reqwest::header::ACCEPT, "application/vnd.github.raw"),
+        )
+        .await?;
+    if !resp.status().is_success() {
+        return Err(error_from_response(
+            client,
+            resp,
+            &format!("download raw blob for '{}'", path),
+        )
+        .await);
+    }
+    let bytes = resp.bytes().await?;
+    if bytes.len() as u64 != blob.size {
+        return Err(GithubClientError::InvalidResponse(format!(
+            "raw blob for '{}' has {} bytes, expected {}",
+            path,
+            bytes.len(),
+            blob.size
+        )));
+    }
+    Ok(bytes.to_vec())
+}
+
+pub async fn get_repo_default_branch(
+    api_base: &str,
+    token: &str,
+    full_name: &str,
+) -> Result<String> {
+    get_repo_default_branch_with_client(&GithubClient::new(api_base, token)?, full_name).await
+}
+
+/// [`get_repo_default_branch`] on an existing client.
+pub async fn get_repo_default_branch_with_client(
+    client: &GithubClient,
+    full_name: &str,
+) -> Result<String> {
+    Ok(get_repo_info_with_client(client, full_name)
+        .await?
+        .default_branch)
+}
+
+/// Repository settings that decide whether it can serve as a template.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct RepoMeta {
+    pub default_branch: String,
+    #[serde(default)]
+    pub is_template: bool,
+    #[serde(default)]
+    pub private: bool,
+    #[serde(default)]
+    pub archived: bool,
+}
+
+/// Fetches [`RepoMeta`] for `full_name`.
+///
+/// GitHub answers 404 both for a missing repository and for one the token
+/// cannot read, so the error says so.
+pub async fn get_repo_info(api_base: &str, token: &str, full_name: &str) -> Result<RepoMeta> {
+    get_repo_info_with_client(&GithubClient::new(api_base, token)?, full_name).await
+}
+
+/// [`get_repo_info`] on an existing client.
+pub async fn get_repo_info_with_client(client: &GithubClient, full_name: &str) -> Result<RepoMeta> {
+    let (owner, repo) = split_template_name(full_name)?;
+    let url = format!("{}/repos/{}/{}", client.api_base(), owner, repo);
+    let resp = match client.get_json_cached(&url).await? {
+        Ok(meta) => return Ok(meta),
+        Err(resp) => resp,
+    };
+    if resp.status().as_u16() == 404 {
+        return Err(GithubClientError::NotFound {
+            resource: format!(
+                "repository '{}' not found or the token has no read access to it{}",
+                full_name,
+                response_context(resp.headers())
+            ),
+        });
+    }
+    Err(error_from_response(client, resp, &format!("read repo info for '{}'", full_name)).await)
+}
+
+/// Checks that `template_name` exists, is marked as a template and is not
+/// archived, so a bad `--template-name` fails before anything is created
+/// instead of with the generate endpoint's bare 404.
+pub async fn verify_template(api_base: &str, token: &str, template_name: &str) -> Result<RepoMeta> {
+    verify_template_with_client(&GithubClient::new(api_base, token)?, template_name).await
+}
+
+/// [`verify_template`] on an existing client.
+pub async fn verify_template_with_client(
+    client: &GithubClient,
+    template_name: &str,
+) -> Result<RepoMeta> {
+    let meta = get_repo_info_with_client(client, template_name).await?;
+    if !meta.is_template {
+        return Err(GithubClientError::InvalidInput(format!(
+            "repository '{}' exists but is not marked as a template; enable 'Template repository' in its settings",
+            template_name
+        )));
+    }
+    if meta.archived {
+        return Err(GithubClientError::InvalidInput(format!(
+            "template '{}' is archived; unarchive it before generating from it",
+            template_name
+        )));
+    }
+    Ok(meta)
+}
+
+/// Makes `branch` the default branch of `full_name`.
+///
+/// Does nothing when it already is. Otherwise waits for the branch to exist
+/// (see [`ClientConfig::branch_wait`]) so a missing branch fails with a
+/// timeout naming it rather than GitHub's bare 422.
+pub async fn set_default_branch(
+    api_base: &str,
+    token: &str,
+    full_name: &str,
+    branch: &str,
+) -> Result<()> {
+    set_default_branch_with_client(&GithubClient::new(api_base, token)?, full_name, branch).await
+}
+
+/// [`set_default_branch`] on an existing client.
+pub async fn set_default_branch_with_client(
+    client: &GithubClient,
+    full_name: &str,
+    branch: &str,
+) -> Result<()> {
+    let (owner, repo) = split_template_name(full_name)?;
+    let url = format!("{}/repos/{}/{}", client.api_base(), owner, repo);
+
+    // A repository created during this dry run does not exist yet
+    let current = match get_repo_default_branch_with_client(client, full_name).await {
+        Ok(current) => Some(current),
+        Err(err) if err.is_not_found() && client.is_dry_run() => None,
+        Err(err) => return Err(err),
+    };
+    if current.as_deref() == Some(branch) {
+        debug!(
+            "'{}' is already the default branch of '{}'",
+            branch, full_name
+        );
+        if client.is_dry_run() {
+            client.record_skip(format!("default branch '{}' (already set)", branch));
+        }
+        return Ok(());
+    }
+
+    if client.is_dry_run() {
+        client.record(
+            Method::PATCH,
+            &url,
+            format!("set default branch to '{}'", branch),
+        );
+        return Ok(());
+    }
+
+    ensure_branch_exists(client, full_name, branch, client.branch_wait()).await?;
+    let resp = client
+        .send(
+            client
+                .patch(&url)
+                .json(&serde_json::json!({ "default_branch": branch })),
+        )
+        .await?;
+    let status = resp.status();
+    if status.is_success() {
+        info!("Default branch of '{}' is now '{}'", full_name, branch);
+        return Ok(());
+    }
+
+    let err = error_from_response(
+        client,
+        resp,
+        &format!("set default branch of '{}' to '{}'", full_name, branch),
+    )
+    .await;
+    warn!("Failed to set default branch {}: {}", status, err);
+    Err(err)
+}
+
+/// Repository settings applied with `PATCH /repos/{owner}/{repo}`.
+///
+/// Only fields that are `Some` are sent, so unset toggles keep whatever the
+/// repository already has.
+#[derive(Serialize, Default, Debug, Clone)]
+pub struct RepoSettings {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub allow_squash_merge: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub allow_merge_commit: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub allow_rebase_merge: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub allow_auto_merge: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub delete_branch_on_merge: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub has_issues: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub has_wiki: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub has_projects: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub has_discussions: Option<bool>,
+}
+
+impl RepoSettings {
+    /// True when no field is set, i.e. the PATCH would be a no-op.
+    pub fn is_empty(&self) -> bool {
+        self.allow_squash_merge.is_none()
+            && self.allow_merge_commit.is_none()
+            && self.allow_rebase_merge.is_none()
+            && self.allow_auto_merge.is_none()
+            && self.delete_branch_on_merge.is_none()
+            && self.has_issues.is_none()
+            && self.has_wiki.is_none()
+            && self.has_projects.is_none()
+            && self.has_discussions.is_none()
+    }
+
+    /// Copies the features that are set in `features`, so merge settings and
+    /// features go out in one PATCH.
+    pub fn apply_features(&mut self, features: &RepoFeatures) {
+        let RepoFeatures {
+            has_issues,
+            has_wiki,
+            has_projects,
+            has_discussions,
+        } = *features;
+        self.has_issues = has_issues.or(self.has_issues);
+        self.has_wiki = has_wiki.or(self.has_wiki);
+        self.has_projects = has_projects.or(self.has_projects);
+        self.has_discussions = has_discussions.or(self.has_discussions);
+    }
+}
+
+pub async fn update_repo_settings(
+    api_base: &str,
+    token: &str,
+    full_name: &str,
+    settings: &RepoSettings,
+) -> Result<()> {
+    update_repo_settings_with_client(&GithubClient::new(api_base, token)?, full_name, settings)
+        .await
+}
+
+/// [`update_repo_settings`] on an existing client.
+pub async fn update_repo_settings_with_client(
+    client: &GithubClient,
+    full_name: &str,
+    settings: &RepoSettings,
+) -> Result<()> {
+    patch_repo(client, full_name, settings, "repository settings").await
+}
+
+/// Repository features to enable or disable; a subset of [`RepoSettings`]
+/// (see [`RepoSettings::apply_features`]).
+///
+/// `None` fields are left out of the request so features the caller does
+/// not mention stay as they are.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RepoFeatures {
+    pub has_issues: Option<bool>,
+    pub has_wiki: Option<bool>,
+    pub has_projects: Option<bool>,
+    pub has_discussions: Option<bool>,
+}
+
+impl RepoFeatures {
+    /// True when no feature is set, i.e. the PATCH would be a no-op.
+    pub fn is_empty(&self) -> bool {
+        self.has_issues.is_none()
+            && self.has_wiki.is_none()
+            && self.has_projects.is_none()
+            && self.has_discussions.is_none()
+    }
+}
+
+/// Enables or disables issues, wiki, projects and discussions on
+/// `full_name`. Does nothing when `features` is empty.
+pub async fn set_repo_features(
+    api_base: &str,
+    token: &str,
+    full_name: &str,
+    features: &RepoFeatures,
+) -> Result<()> {
+    set_repo_features_with_client(&GithubClient::new(api_base, token)?, full_name, features).await
+}
+
+/// [`set_repo_features`] on an existing client.
+pub async fn set_repo_features_with_client(
+    client: &GithubClient,
+    full_name: &str,
+    features: &RepoFeatures,
+) -> Result<()> {
+    if features.is_empty() {
+        return Ok(());
+    }
+    let mut settings = RepoSettings::default();
+    settings.apply_features(features);
+    patch_repo(client, full_name, &settings, "repository features").await
+}
+
+/// Archives `full_name`, or unarchives it with `archived` false. An archived
+/// repository keeps its history and its name but becomes read-only: later
+/// writes to it fail with [`GithubClientError::Archived`].
+pub async fn set_repo_archived(
+    api_base: &str,
+    token: &str,
+    full_name: &str,
+    archived: bool,
+) -> Result<()> {
+    set_repo_archived_with_client(&GithubClient::new(api_base, token)?, full_name, archived).await
+}
+
+/// [`set_repo_archived`] on an existing client.
+pub async fn set_repo_archived_with_client(
+    client: &GithubClient,
+    full_name: &str,
+    archived: bool,
+) -> Result<()> {
+    patch_repo(
+        client,
+        full_name,
+        &serde_json::json!({ "archived": archived }),
+        "archived state",
+    )
+    .await
+}
+
+async fn patch_repo<T: Serialize + std::fmt::Debug>(
+    client: &GithubClient,
+    full_name: &str,
+    body: &T,
+    what: &str,
+) -> Result<()> {
+    let (owner, repo) = split_template_name(full_name)?;
+    let url = format!("{}/repos/{}/{}", client.api_base(), owner, repo);
+
+    if client.is_dry_run() {
+        client.record(
+            Method::PATCH,
+            &url,
+            serde_json::to_string(body).unwrap_or_default(),
+        );
+        return Ok(());
+    }
+
+    debug!("PATCH {}: {:?}", what, body);
+    let resp = client.send(client.patch(url).json(body)).await?;
+    let status = resp.status();
+    if status.is_success() {
+        info!("Updated {} for '{}'", what, full_name);
+        return Ok(());
+    }
+
+    let err = error_from_response(
+        client,
+        resp,
+        &format!("update {} for '{}'", what, full_name),
+    )
+    .await;
+    warn!("Failed to update {} {}: {}", what, status, err);
+    Err(err)
+}
+
+/// Most topics GitHub allows on one repository.
+const MAX_TOPICS: usize = 20;
+/// Longest topic GitHub accepts.
+const MAX_TOPIC_LEN: usize = 50;
+
+/// Lower-cases topics, turns spaces and underscores into hyphens and drops
+/// duplicates, then checks them against GitHub's rules: letters, digits and
+/// hyphens only, starting with a letter or digit, at most 50 characters, and
+/// at most 20 topics.
+fn normalize_topics(topics: &[&str]) -> Result<Vec<String>> {
+    let mut normalized: Vec<String> = Vec::new();
+    for raw in topics {
+        let topic: String = raw
+            .trim()
+            .to_lowercase()
+            .chars()
+            .map(|c| if c == ' ' || c == '_' { '-' } else { c })
+            .collect();
+        let valid = topic.len() <= MAX_TOPIC_LEN
+            && topic
+                .chars()
+                .next()
+                .map(|c| c.is_ascii_alphanumeric())
+                .unwrap_or(false)
+            && topic
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-');
+        if !valid {
+            return Err(GithubClientError::InvalidInput(format!(
+                "invalid topic '{}': use lowercase letters, digits and hyphens, starting with a letter or digit, at most {} characters",
+                raw, MAX_TOPIC_LEN
+            )));
+        }
+        if !normalized.contains(&topic) {
+            normalized.push(topic);
+        }
+    }
+    if normalized.len() > MAX_TOPICS {
+        return Err(GithubClientError::InvalidInput(format!(
+            "{} topics given, GitHub allows at most {}",
+            normalized.len(),
+            MAX_TOPICS
+        )));
+    }
+    Ok(normalized)
+}
+
+/// Replaces the topics of `full_name` with `topics`. An empty list removes
+/// all topics.
+///
+/// Topics are lower-cased, spaces and underscores become hyphens, and any
+/// topic GitHub would reject fails with `InvalidInput` before the request.
+pub async fn set_repo_topics(
+    api_base: &str,
+    token: &str,
+    full_name: &str,
+    topics: &[&str],
+) -> Result<()> {
+    set_repo_topics_with_client(&GithubClient::new(api_base, token)?, full_name, topics).await
+}
+
+/// [`set_repo_topics`] on an existing client.
+pub async fn set_repo_topics_with_client(
+    client: &GithubClient,
+    full_name: &str,
+    topics: &[&str],
+) -> Result<()> {
+    let (owner, repo) = split_template_name(full_name)?;
+    let names = normalize_topics(topics)?;
+    let url = format!("{}/repos/{}/{}/topics", client.api_base(), owner, repo);
+
+    if client.is_dry_run() {
+        client.record(Method::PUT, &url, format!("topics [{}]", names.join(", ")));
+        return Ok(());
+    }
+
+    let resp = client
+        .send(
+            client
+                .put(url)
+                .header(reqwest::header::ACCEPT, "application/vnd.github+json")
+                .json(&serde_json::json!({ "names": names })),
+        )
+        .await?;
+    let status = resp.status();
+    if status.is_success() {
+        info!("Set topics on '{}': {}", full_name, names.join(", "));
+        return Ok(());
+    }
+
+    let err = error_from_response(client, resp, &format!("set topics on '{}'", full_name)).await;
+    warn!("Failed to set topics {}: {}", status, err);
+    Err(err)
+}
+
+/// A custom property value of a repository, as returned by
+/// [`get_custom_properties`].
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct CustomProperty {
+    pub property_name: String,
+    /// A string, or a list of strings for multi-select properties; `null`
+    /// when unset.
+    pub value: serde_json::Value,
+}
+
+/// Sets custom property values on `full_name`, leaving other properties
+/// as they are. Does nothing when `props` is empty.
+///
+/// The organization must define each property first; unknown names fail
+/// with GitHub's 422 details.
+pub async fn set_custom_properties(
+    api_base: &str,
+    token: &str,
+    full_name: &str,
+    props: &[(String, String)],
+) -> Result<()> {
+    set_custom_properties_with_client(&GithubClient::new(api_base, token)?, full_name, props).await
+}
+
+/// [`set_custom_properties`] on an existing client.
+pub async fn set_custom_properties_with_client(
+    client: &GithubClient,
+    full_name: &str,
+    props: &[(String, String)],
+) -> Result<()> {
+    if props.is_empty() {
+        return Ok(());
+    }
+    let (owner, repo) = split_template_name(full_name)?;
+    let url = format!(
+        "{}/repos/{}/{}/properties/values",
+        client.api_base(),
+        owner,
+        repo
+    );
+
+    if client.is_dry_run() {
+        let pairs: Vec<String> = props.iter().map(|(k, v)| format!("{}={}", k, v)).collect();
+        client.record(
+            Method::PATCH,
+            &url,
+            format!("custom properties {}", pairs.join(", ")),
+        );
+        return Ok(());
+    }
+
+    let properties: Vec<CustomProperty> = props
+        .iter()
+        .map(|(name, value)| CustomProperty {
+            property_name: name.clone(),
+            value: serde_json::Value::String(value.clone()),
+        })
+        .collect();
+    let resp = client
+        .send(
+            client
+                .patch(url)
+                .json(&serde_json::json!({ "properties": properties })),
+        )
+        .await?;
+    let status = resp.status();
+    if status.is_success() {
+        let names: Vec<&str> = props.iter().map(|(k, _)| k.as_str()).collect();
+        info!(
+            "Set custom properties on '{}': {}",
+            full_name,
+            names.join(", ")
+        );
+        return Ok(());
+    }
+
+    let err = match error_from_response(
+        client,
+        resp,
+        &format!("set custom properties on '{}'", full_name),
+    )
+    .await
+    {
+        GithubClientError::Forbidden { message } => GithubClientError::Forbidden {
+            message: format!(
+                "{}. The token needs the 'Custom properties: Read and write' repository permission, and the organization must allow repository admins to set these properties",
+                message
+            ),
+        },
+        err => err,
+    };
+    warn!("Failed to set custom properties {}: {}", status, err);
+    Err(err)
+}
+
+/// Lists the custom property values of `full_name`.
+pub async fn get_custom_properties(
+    api_base: &str,
+    token: &str,
+    full_name: &str,
+) -> Result<Vec<CustomProperty>> {
+    get_custom_properties_with_client(&GithubClient::new(api_base, token)?, full_name).await
+}
+
+/// [`get_custom_properties`] on an existing client.
+pub async fn get_custom_properties_with_client(
+    client: &GithubClient,
+    full_name: &str,
+) -> Result<Vec<CustomProperty>> {
+    let (owner, repo) = split_template_name(full_name)?;
+    let url = format!(
+        "{}/repos/{}/{}/properties/values",
+        client.api_base(),
+        owner,
+        repo
+    );
+    let resp = client.send(client.get(url)).await?;
+    if !resp.status().is_success() {
+        return Err(error_from_response(
+            client,
+            resp,
+            &format!("read custom properties of '{}'", full_name),
+        )
+        .await);
+    }
+    Ok(resp.json().await?)
+}
+
+/// An issue label.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Label {
+    pub name: String,
+    /// Hex color without the leading `#`, e.g. `d73a4a`.
+    pub color: String,
+    #[serde(default)]
+    pub description: Option<String>,
+}
+
+impl Label {
+    /// Whether `other` has the same color and description; GitHub returns
+    /// colors in lower case, and an empty description is the same as none.
+    fn same_style(&self, other: &Label) -> bool {
+        self.color
+            .trim_start_matches('#')
+            .eq_ignore_ascii_case(other.color.trim_start_matches('#'))
+            && self.description.as_deref().unwrap_or("")
+                == other.description.as_deref().unwrap_or("")
+    }
+}
+
+/// Outcome of [`sync_labels_from_repo`] and [`ensure_labels`], as label names.
+#[derive(Serialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct LabelSyncReport {
+    pub created: Vec<String>,
+    pub updated: Vec<String>,
+    pub deleted: Vec<String>,
+}
+
+const LABELS_PER_PAGE: usize = 100;
+
+/// Lists every label of the repository at `repo_url`, following pagination.
+/// In dry run a missing repository has no labels.
+async fn list_labels(client: &GithubClient, repo_url: &str) -> Result<Vec<Label>> {
+    let url = format!("{}/labels", repo_url);
+    let mut labels = Vec::new();
+    for page in 1.. {
+        let resp = client
+            .send(
+                client
+                    .get(&url)
+                    .query(&[("per_page", LABELS_PER_PAGE), ("page", page)]),
+            )
+            .await?;
+        if resp.status().as_u16() == 404 && client.is_dry_run() {
+            break;
+        }
+        if !resp.status().is_success() {
+            return Err(error_from_response(client, resp, "list labels").await);
+        }
+        let batch: Vec<Label> = resp.json().await?;
+        let count = batch.len();
+        labels.extend(batch);
+        if count < LABELS_PER_PAGE {
+            break;
+        }
+    }
+    Ok(labels)
+}
+
+/// URL of label `name` under `repo_url`, with the name percent-encoded as a
+/// single path segment so spaces, `/` and non-ASCII characters survive.
+fn label_url(repo_url: &str, name: &str) -> Result<reqwest::Url> {
+    let mut url = reqwest::Url::parse(&format!("{}/labels", repo_url))
+        .map_err(|e| GithubClientError::InvalidInput(format!("invalid API URL: {}", e)))?;
+    url.path_segments_mut()
+        .map_err(|_| GithubClientError::InvalidInput(format!("invalid API URL: {}", repo_url)))?
+        .push(name);
+    Ok(url)
+}
+
+/// Creates (POST) or updates (PATCH) `label` at `url`; in dry run the request
+/// is only recorded.
+async fn write_label(
+    client: &GithubClient,
+    method: Method,
+    url: reqwest::Url,
+    action: &str,
+    label: &Label,
+    full_name: &str,
+) -> Result<()> {
+    if client.is_dry_run() {
+        client.record(
+            method,
+            url.as_str(),
+            format!("{} label '{}' (#{})", action, label.name, label.color),
+        );
+        return Ok(());
+    }
+    let body = serde_json::json!({
+        "name": label.name,
+        "color": label.color.trim_start_matches('#'),
+        "description": label.description.as_deref().unwrap_or(""),
+    });
+    let resp = client.send(client.request(method, url).json(&body)).await?;
+    if !resp.status().is_success() {
+        return Err(error_from_response(
+            client,
+            resp,
+            &format!("{} label '{}' on '{}'", action, label.name, full_name),
+        )
+        .await);
+    }
+    debug!("Label '{}' {}d on '{}'", label.name, action, full_name);
+    Ok(())
+}
+
+/// Makes sure each of `labels` exists on `full_name` with the given color and
+/// description: missing labels are created and differing ones updated, so a
+/// re-run against a repository already in sync sends no writes. Other labels
+/// are left alone.
+pub async fn ensure_labels(
+    api_base: &str,
+    token: &str,
+    full_name: &str,
+    labels: &[Label],
+) -> Result<LabelSyncReport> {
+    ensure_labels_with_client(&GithubClient::new(api_base, token)?, full_name, labels).await
+}
+
+/// [`ensure_labels`] on an existing client.
+pub async fn ensure_labels_with_client(
+    client: &GithubClient,
+    full_name: &str,
+    labels: &[Label],
+) -> Result<LabelSyncReport> {
+    let (owner, repo) = split_template_name(full_name)?;
+    let repo_url = format!("{}/repos/{}/{}", client.api_base(), owner, repo);
+    let mut report = LabelSyncReport::default();
+
+    for label in labels {
+        let url = label_url(&repo_url, &label.name)?;
+        let resp = client.send(client.get(url.clone())).await?;
+        let existing: Option<Label> = match resp.status().as_u16() {
+            404 => None,
+            _ if resp.status().is_success() => Some(resp.json().await?),
+            _ => {
+                return Err(error_from_response(
+                    client,
+                    resp,
+                    &format!("get label '{}' on '{}'", label.name, full_name),
+                )
+                .await)
+            }
+        };
+        match existing {
+            None => {
+                let create_url =
+                    reqwest::Url::parse(&format!("{}/labels", repo_url)).map_err(|e| {
+                        GithubClientError::InvalidInput(format!("invalid API URL: {}", e))
+                    })?;
+                write_label(client, Method::POST, create_url, "create", label, full_name).await?;
+                report.created.push(label.name.clone());
+            }
+            Some(current) if !current.same_style(label) => {
+                let url = label_url(&repo_url, &current.name)?;
+                write_label(client, Method::PATCH, url, "update", label, full_name).await?;
+                report.updated.push(label.name.clone());
+            }
+            Some(_) => debug!(
+                "Label '{}' already up to date on '{}'",
+                label.name, full_name
+            ),
+        }
+    }
+
+    info!(
+        "Labels on '{}': {} created, {} updated, {} unchanged",
+        full_name,
+        report.created.len(),
+        report.updated.len(),
+        labels.len() - report.created.len() - report.updated.len()
+    );
+    Ok(report)
+}
+
+/// Makes the labels of `target_full_name` match those of `source_full_name`:
+/// creates missing labels and updates ones whose color or description differ.
+/// With `delete_extra`, labels that only exist on the target are deleted.
+pub async fn sync_labels_from_repo(
+    api_base: &str,
+    token: &str,
+    source_full_name: &str,
+    target_full_name: &str,
+    delete_extra: bool,
+) -> Result<LabelSyncReport> {
+    sync_labels_from_repo_with_client(
+        &GithubClient::new(api_base, token)?,
+        source_full_name,
+        target_full_name,
+        delete_extra,
+    )
+    .await
+}
+
+/// [`sync_labels_from_repo`] on an existing client.
+pub async fn sync_labels_from_repo_with_client(
+    client: &GithubClient,
+    source_full_name: &str,
+    target_full_name: &str,
+    delete_extra: bool,
+) -> Result<LabelSyncReport> {
+    let (src_owner, src_repo) = split_template_name(source_full_name)?;
+    let (dst_owner, dst_repo) = split_template_name(target_full_name)?;
+    let src_url = format!("{}/repos/{}/{}", client.api_base(), src_owner, src_repo);
+    let dst_url = format!("{}/repos/{}/{}", client.api_base(), dst_owner, dst_repo);
+
+    let source = list_labels(client, &src_url).await?;
+    let target = list_labels(client, &dst_url).await?;
+    let mut report = LabelSyncReport::default();
+
+    for label in &source {
+        // GitHub matches label names case-insensitively
+        let existing = target
+            .iter()
+            .find(|t| t.name.eq_ignore_ascii_case(&label.name));
+        let (method, url, action) = match existing {
+            None => (
+                Method::POST,
+                reqwest::Url::parse(&format!("{}/labels", dst_url)).map_err(|e| {
+                    GithubClientError::InvalidInput(format!("invalid API URL: {}", e))
+                })?,
+                "create",
+            ),
+            Some(t) if !t.same_style(label) => {
+                (Method::PATCH, label_url(&dst_url, &t.name)?, "update")
+            }
+            Some(_) => continue,
+        };
+        write_label(client, method.clone(), url, action, label, target_full_name).await?;
+        if method == Method::POST {
+            report.created.push(label.name.clone());
+        } else {
+            report.updated.push(label.name.clone());
+        }
+    }
+
+    if delete_extra {
+        for extra in target
+            .iter()
+            .filter(|t| !source.iter().any(|s| s.name.eq_ignore_ascii_case(&t.name)))
+        {
+            let url = label_url(&dst_url, &extra.name)?;
+            if client.is_dry_run() {
+                client.record(
+                    Method::DELETE,
+                    url.as_str(),
+                    format!("delete label '{}'", extra.name),
+                );
+            } else {
+                let resp = client.send(client.delete(url)).await?;
+                if !resp.status().is_success() && resp.status().as_u16() != 404 {
+                    return Err(error_from_response(
+                        client,
+                        resp,
+                        &format!("delete label '{}' on '{}'", extra.name, target_full_name),
+                    )
+                    .await);
+                }
+            }
+            report.deleted.push(extra.name.clone());
+        }
+    }
+
+    info!(
+        "Labels on '{}': {} created, {} updated, {} deleted",
+        target_full_name,
+        report.created.len(),
+        report.updated.len(),
+        report.deleted.len()
+    );
+    Ok(report)
+}
+
+/// Repository access level granted to a team or collaborator.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Permission {
+    Pull,
+    Triage,
+    Push,
+    Maintain,
+    Admin,
+}
+
+impl Permission {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Permission::Pull => "pull",
+            Permission::Triage => "triage",
+            Permission::Push => "push",
+            Permission::Maintain => "maintain",
+            Permission::Admin => "admin",
+        }
+    }
+}
+
+impl fmt::Display for Permission {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Permission {
+    type Err = GithubClientError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "pull" | "read" => Ok(Permission::Pull),
+            "triage" => Ok(Permission::Triage),
+            "push" | "write" => Ok(Permission::Push),
+            "maintain" => Ok(Permission::Maintain),
+            "admin" => Ok(Permission::Admin),
+            _ => Err(GithubClientError::InvalidInput(format!(
+                "unknown permission '{}', expected pull, triage, push, maintain or admin",
+                s
+            ))),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct PermissionRequest {
+    permission: Permission,
+}
+
+#[derive(Deserialize)]
+struct InvitationResponse {
+    id: u64,
+}
+
+/// Grants `team_slug` of `org` `permission` on `full_name`.
+///
+/// Re-running with a different permission updates the team's access.
+pub async fn add_team_permission(
+    api_base: &str,
+    token: &str,
+    org: &str,
+    team_slug: &str,
+    full_name: &str,
+    permission: Permission,
+) -> Result<()> {
+    add_team_permission_with_client(
+        &GithubClient::new(api_base, token)?,
+        org,
+        team_slug,
+        full_name,
+        permission,
+    )
+    .await
+}
+
+/// [`add_team_permission`] on an existing client.
+pub async fn add_team_permission_with_client(
+    client: &GithubClient,
+    org: &str,
+    team_slug: &str,
+    full_name: &str,
+    permission: Permission,
+) -> Result<()> {
+    let (owner, repo) = split_template_name(full_name)?;
+    let url = format!(
+        "{}/orgs/{}/teams/{}/repos/{}/{}",
+        client.api_base(),
+        encode_segment(org),
+        encode_segment(team_slug),
+        owner,
+        repo
+    );
+
+    if client.is_dry_run() {
+        client.record(
+            Method::PUT,
+            &url,
+            format!("grant team {}/{} {}", org, team_slug, permission),
+        );
+        return Ok(());
+    }
+
+    let resp = client
+        .send(client.put(url).json(&PermissionRequest { permission }))
+        .await?;
+    let status = resp.status();
+    if status.is_success() {
+        info!(
+            "Granted team {}/{} '{}' on '{}'",
+            org, team_slug, permission, full_name
+        );
+        return Ok(());
+    }
+
+    let context = response_context(resp.headers());
+    let err = match error_from_response(
+        client,
+        resp,
+        &format!("grant team {}/{} access to '{}'", org, team_slug, full_name),
+    )
+    .await
+    {
+        GithubClientError::NotFound { .. } => GithubClientError::NotFound {
+            resource: format!(
+                "team '{}/{}' (check the slug, and that the token can see the team and '{}'){}",
+                org, team_slug, full_name, context
+            ),
+        },
+        err => err,
+    };
+    warn!("Failed to grant team access {}: {}", status, err);
+    Err(err)
+}
+
+/// Alias of [`add_team_permission`], named to pair with [`add_collaborator`].
+pub async fn add_team_to_repo(
+    api_base: &str,
+    token: &str,
+    org: &str,
+    team_slug: &str,
+    full_name: &str,
+    permission: Permission,
+) -> Result<()> {
+    add_team_to_repo_with_client(
+        &GithubClient::new(api_base, token)?,
+        org,
+        team_slug,
+        full_name,
+        permission,
+    )
+    .await
+}
+
+/// [`add_team_to_repo`] on an existing client.
+pub async fn add_team_to_repo_with_client(
+    client: &GithubClient,
+    org: &str,
+    team_slug: &str,
+    full_name: &str,
+    permission: Permission,
+) -> Result<()> {
+    add_team_permission_with_client(client, org, team_slug, full_name, permission).await
+}
+
+/// Adds `username` to `full_name` with `permission`.
+///
+/// Returns the invitation id when GitHub sent an invitation (201), or `None`
+/// when the user already had access (204).
+pub async fn add_collaborator(
+    api_base: &str,
+    token: &str,
+    full_name: &str,
+    username: &str,
+    permission: Permission,
+) -> Result<Option<u64>> {
+    add_collaborator_with_client(
+        &GithubClient::new(api_base, token)?,
+        full_name,
+        username,
+        permission,
+    )
+    .await
+}
+
+/// [`add_collaborator`] on an existing client.
+pub async fn add_collaborator_with_client(
+    client: &GithubClient,
+    full_name: &str,
+    username: &str,
+    permission: Permission,
+) -> Result<Option<u64>> {
+    let (owner, repo) = split_template_name(full_name)?;
+    let url = format!(
+        "{}/repos/{}/{}/collaborators/{}",
+        client.api_base(),
+        owner,
+        repo,
+        encode_segment(username)
+    );
+
+    if client.is_dry_run() {
+        client.record(
+            Method::PUT,
+            &url,
+            format!("add collaborator {} with {}", username, permission),
+        );
+        return Ok(None);
+    }
+
+    let resp = client
+        .send(client.put(url).json(&PermissionRequest { permission }))
+        .await?;
+    let status = resp.status();
+    if status.as_u16() == 204 {
+        info!("'{}' already has access to '{}'", username, full_name);
+        return Ok(None);
+    }
+    if status.is_success() {
+        let invitation: InvitationResponse = resp.json().await?;
+        info!(
+            "Invited '{}' to '{}' with '{}' (invitation {})",
+            username, full_name, permission, invitation.id
+        );
+        return Ok(Some(invitation.id));
+    }
+
+    let context = response_context(resp.headers());
+    let err = match error_from_response(
+        client,
+        resp,
+        &format!("add collaborator '{}' to '{}'", username, full_name),
+    )
+    .await
+    {
+        GithubClientError::NotFound { .. } => GithubClientError::NotFound {
+            resource: format!(
+                "user '{}' or repository '{}' (check the login){}",
+                username, full_name, context
+            ),
+        },
+        err => err,
+    };
+    warn!("Failed to add collaborator {}: {}", status, err);
+    Err(err)
+}
+
+/// Payload format GitHub uses when delivering webhook events.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum WebhookContentType {
+    #[default]
+    Json,
+    Form,
+}
+
+/// A repository webhook, created or updated with [`create_webhook`].
+#[derive(Debug, Clone)]
+pub struct WebhookConfig {
+    pub url: String,
+    pub content_type: WebhookContentType,
+    /// Used by GitHub to sign deliveries; never logged or recorded in a plan.
+    pub secret: Option<String>,
+    /// Skip TLS certificate verification when delivering. Avoid in production.
+    pub insecure_ssl: bool,
+    pub active: bool,
+    /// Events that trigger a delivery, e.g. `push` or `pull_request`.
+    /// Empty means [`DEFAULT_WEBHOOK_EVENTS`].
+    pub events: Vec<String>,
+}
+
+/// Events a webhook is subscribed to when none are given.
+pub const DEFAULT_WEBHOOK_EVENTS: [&str; 2] = ["push", "pull_request"];
+
+impl WebhookConfig {
+    /// An active JSON webhook for `url` delivering the
+    /// [`DEFAULT_WEBHOOK_EVENTS`].
+    pub fn new(url: &str) -> Self {
+        Self {
+            url: url.to_string(),
+            content_type: WebhookContentType::Json,
+            secret: None,
+            insecure_ssl: false,
+            active: true,
+            events: DEFAULT_WEBHOOK_EVENTS.map(String::from).to_vec(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct WebhookRequest<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    name: Option<&'a str>,
+    active: bool,
+    events: &'a [String],
+    config: WebhookRequestConfig<'a>,
+}
+
+#[derive(Serialize)]
+struct WebhookRequestConfig<'a> {
+    url: &'a str,
+    content_type: WebhookContentType,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    secret: Option<&'a str>,
+    /// GitHub expects "0" or "1" here rather than a boolean.
+    insecure_ssl: &'static str,
+}
+
+#[derive(Deserialize)]
+struct WebhookSummary {
+    id: u64,
+    #[serde(default)]
+    config: WebhookSummaryConfig,
+}
+
+#[derive(Deserialize, Default)]
+struct WebhookSummaryConfig {
+    url: Option<String>,
+}
+
+/// Creates a repository webhook, or updates the existing hook with the same
+/// URL so repeated runs do not add duplicates. Returns the hook id (0 in dry
+/// run when no hook exists yet).
+pub async fn create_webhook(
+    api_base: &str,
+    token: &str,
+    full_name: &str,
+    config: &WebhookConfig,
+) -> Result<u64> {
+    create_webhook_with_client(&GithubClient::new(api_base, token)?, full_name, config).await
+}
+
+/// [`create_webhook`] on an existing client.
+pub async fn create_webhook_with_client(
+    client: &GithubClient,
+    full_name: &str,
+    config: &WebhookConfig,
+) -> Result<u64> {
+    let (owner, repo) = split_template_name(full_name)?;
+    let hooks_url = format!("{}/repos/{}/{}/hooks", client.api_base(), owner, repo);
+
+    let list_resp = client.send(client.get(&hooks_url)).await?;
+    // A repository created during this dry run does not exist yet
+    let existing: Vec<WebhookSummary> = if list_resp.status().as_u16() == 404 && client.is_dry_run()
+    {
+        Vec::new()
+    } else if list_resp.status().is_success() {
+        list_resp.json().await?
+    } else {
+        return Err(error_from_response(
+            client,
+            list_resp,
+            &format!("list webhooks on '{}'", full_name),
+        )
+        .await);
+    };
+    let found = existing
+        .iter()
+        .find(|h| h.config.url.as_deref() == Some(config.url.as_str()));
+
+    let events = if config.events.is_empty() {
+        DEFAULT_WEBHOOK_EVENTS.map(String::from).to_vec()
+    } else {
+        config.events.clone()
+    };
+    let mut body = WebhookRequest {
+        name: Some("web"),
+        active: config.active,
+        events: &events,
+        config: WebhookRequestConfig {
+            url: &config.url,
+            content_type: config.content_type,
+            secret: config.secret.as_deref(),
+            insecure_ssl: if config.insecure_ssl { "1" } else { "0" },
+        },
+    };
+    let (method, url, action) = match found {
+        Some(hook) => {
+            debug!(
+                "Webhook for '{}' exists (id {}), updating",
+                config.url, hook.id
+            );
+            body.name = None;
+            (
+                Method::PATCH,
+                format!("{}/{}", hooks_url, hook.id),
+                format!("update webhook {} on '{}'", hook.id, full_name),
+            )
+        }
+        None => (
+            Method::POST,
+            hooks_url.clone(),
+            format!("create webhook on '{}'", full_name),
+        ),
+    };
+
+    if client.is_dry_run() {
+        client.record(
+            method,
+            &url,
+            format!(
+                "webhook {} for [{}]{}",
+                config.url,
+                events.join(", "),
+                if config.secret.is_some() {
+                    " (with secret)"
+                } else {
+                    ""
+                }
+            ),
+        );
+        return Ok(found.map(|h| h.id).unwrap_or(0));
+    }
+
+    info!("Configuring webhook {} on '{}'", config.url, full_name);
+    let resp = client
+        .send(client.request(method, &url).json(&body))
+        .await?;
+    let status = resp.status();
+    if !status.is_success() {
+        let err = error_from_response(client, resp, &action).await;
+        warn!("Failed to configure webhook {}: {}", status, err);
+        return Err(err);
+    }
+    let hook: WebhookSummary = resp.json().await?;
+    info!("Webhook {} configured (id {})", config.url, hook.id);
+    Ok(hook.id)
+}
+
+/// Chooses which files of a source repository are seeded into a target.
+///
+/// Built either from directory prefixes (`terraform/`) or from
+/// gitignore-style glob patterns (`helm/**/*.yaml`, `!**/secrets.tfvars`).
+/// Either kind can be narrowed further with [`CopySelector::excluding`].
+/// Patterns are case-sensitive and match the path as it appears in the
+/// source tree, without a leading slash.
+#[derive(Clone, Debug)]
+pub struct CopySelector {
+    mode: SelectorMode,
+    excluded: Vec<GlobSet>,
+}
+
+#[derive(Clone, Debug)]
+enum SelectorMode {
+    /// `(source, destination)` prefix pairs; bare prefixes map to themselves.
+    Prefixes(Vec<(String, String)>),
+    Globs {
+        /// `None` when every pattern is an exclusion, which selects all other paths.
+        include: Option<GlobSet>,
+        exclude: GlobSet,
+    },
+}
+
+impl CopySelector {
+    /// Selects paths starting with any of `prefixes`.
+    pub fn prefixes(prefixes: &[&str]) -> Self {
+        let mappings: Vec<(&str, &str)> = prefixes.iter().map(|p| (*p, *p)).collect();
+        Self::mapped(&mappings)
+    }
+
+    /// Selects paths starting with any of the source prefixes and seeds each
+    /// under the paired destination prefix instead, e.g.
+    /// `("modules/bootstrap/terraform/", "terraform/")` seeds
+    /// `modules/bootstrap/terraform/main.tf` as `terraform/main.tf`. The first
+    /// matching pair wins.
+    pub fn mapped(mappings: &[(&str, &str)]) -> Self {
+        Self {
+            mode: SelectorMode::Prefixes(
+                mappings
+                    .iter()
+                    .map(|(src, dst)| (src.to_string(), dst.to_string()))
+                    .collect(),
+            ),
+            excluded: Vec::new(),
+        }
+    }
+
+    /// Selects paths matching any of `patterns`, minus those matching a
+    /// pattern prefixed with `!`. Exclusions win regardless of order. `*`
+    /// does not cross `/`; use `**` for any depth. A pattern ending in `/`
+    /// selects everything below that directory.
+    pub fn globs(patterns: &[&str]) -> Result<Self> {
+        let mut include = GlobSetBuilder::new();
+        let mut exclude = GlobSetBuilder::new();
+        let mut has_include = false;
+        for pattern in patterns {
+            let (builder, raw) = match pattern.strip_prefix('!') {
+                Some(rest) => (&mut exclude, rest),
+                None => {
+                    has_include = true;
+                    (&mut include, *pattern)
+                }
+            };
+            builder.add(copy_glob(pattern, raw)?);
+        }
+        Ok(Self {
+            mode: SelectorMode::Globs {
+                include: if has_include {
+                    Some(build_glob_set(include)?)
+                } else {
+                    None
+                },
+                exclude: build_glob_set(exclude)?,
+            },
+            excluded: Vec::new(),
+        })
+    }
+
+    /// Additionally drops paths matching any of `patterns`, which follow the
+    /// same rules as [`CopySelector::globs`]: `helm/tests/` or `helm/tests/**`
+    /// for a directory, `**/*.md` for an extension at any depth.
+    pub fn excluding(mut self, patterns: &[&str]) -> Result<Self> {
+        if patterns.is_empty() {
+            return Ok(self);
+        }
+        let mut builder = GlobSetBuilder::new();
+        for pattern in patterns {
+            builder.add(copy_glob(pattern, pattern)?);
+        }
+        self.excluded.push(build_glob_set(builder)?);
+        Ok(self)
+    }
+
+    /// Whether the repository path `path` (no leading slash) is selected.
+    pub fn matches(&self, path: &str) -> bool {
+        let selected = match &self.mode {
+            SelectorMode::Prefixes(prefixes) => prefixes
+                .iter()
+                .any(|(src, _)| path.starts_with(src.as_str())),
+            SelectorMode::Globs { include, exclude } => {
+                include.as_ref().map(|i| i.is_match(path)).unwrap_or(true)
+                    && !exclude.is_match(path)
+            }
+        };
+        selected && !self.excluded.iter().any(|set| set.is_match(path))
+    }
+
+    /// Where the selected source path `path` is seeded in the target
+    /// repository; unchanged unless a [`CopySelector::mapped`] pair renames it.
+    pub fn target_path(&self, path: &str) -> String {
+        if let SelectorMode::Prefixes(prefixes) = &self.mode {
+            for (src, dst) in prefixes {
+                if let Some(rest) = path.strip_prefix(src.as_str()) {
+                    return format!("{}{}", dst, rest);
+                }
+            }
+        }
+        path.to_string()
+    }
+}
+
+/// The entries of `tree` that `selector` matches, each with the path it is
+/// seeded at. Fails before anything is written if two source paths would be
+/// seeded at the same destination.
+fn selected_entries<'t>(
+    tree: &'t GitTree,
+    selector: &CopySelector,
+) -> Result<Vec<(&'t TreeEntry, String)>> {
+    let mut sources: HashMap<String, &str> = HashMap::new();
+    let mut selected = Vec::new();
+    for entry in tree
+        .tree
+        .iter()
+        .filter(|e| e.r#type != "tree" && selector.matches(&e.path))
+    {
+        let target = selector.target_path(&entry.path);
+        if let Some(other) = sources.insert(target.clone(), &entry.path) {
+            return Err(GithubClientError::InvalidInput(format!(
+                "'{}' and '{}' would both be seeded as '{}'; check the path mappings",
+                other, entry.path, target
+            )));
+        }
+        selected.push((entry, target));
+    }
+    Ok(selected)
+}
+
+/// Compiles one copy pattern; `raw` is `pattern` without its `!` marker.
+fn copy_glob(pattern: &str, raw: &str) -> Result<globset::Glob> {
+    let raw = raw.trim_start_matches('/');
+    let expanded = if raw.ends_with('/') {
+        format!("{}**", raw)
+    } else {
+        raw.to_string()
+    };
+    GlobBuilder::new(&expanded)
+        .literal_separator(true)
+        .build()
+        .map_err(|e| {
+            GithubClientError::InvalidInput(format!("invalid copy pattern '{}': {}", pattern, e))
+        })
+}
+
+fn build_glob_set(builder: GlobSetBuilder) -> Result<GlobSet> {
+    builder
+        .build()
+        .map_err(|e| GithubClientError::InvalidInput(format!("invalid copy patterns: {}", e)))
+}
+
+/// Commit metadata for files seeded by [`copy_dirs_from_repo`] and
+/// [`seed_dirs_as_single_commit`].
+///
+/// Unset fields keep GitHub's defaults: the token owner as author and
+/// committer, and the built-in commit messages.
+#[derive(Debug, Clone, Default)]
+pub struct CopyOptions {
+    /// Author and committer name; must be set together with `author_email`.
+    pub author_name: Option<String>,
+    pub author_email: Option<String>,
+    /// Commit message; `{path}` is replaced with the seeded file's path, or
+    /// with the file count when seeding as a single commit.
+    pub commit_message_template: Option<String>,
+    /// Values for `{{KEY}}` placeholders in seeded text files. Keys are
+    /// upper-case letters, digits and underscores, so Go/Helm template
+    /// actions such as `{{ .Values.x }}` are never touched. Files that are not
+    /// valid UTF-8 are copied byte for byte.
+    pub template_vars: HashMap<String, String>,
+    /// Restricts substitution to matching destination paths (e.g. to skip lockfiles);
+    /// `None` substitutes in every text file.
+    pub template_paths: Option<CopySelector>,
+    /// Fail on a placeholder with no value instead of leaving it as-is.
+    pub strict_template_vars: bool,
+    /// Files [`copy_dirs_from_repo`] copies at once; `None` means
+    /// [`DEFAULT_COPY_CONCURRENCY`]. Keep it low: GitHub's secondary rate
+    /// limits punish bursts of concurrent writes.
+    pub concurrency: Option<usize>,
+    /// Skip symlinks with a warning instead of recreating them as symlinks.
+    pub skip_symlinks: bool,
+    /// Size in bytes above which a file counts as large; `None` means
+    /// [`DEFAULT_LARGE_FILE_THRESHOLD`].
+    pub large_file_threshold: Option<u64>,
+    pub large_files: LargeFilePolicy,
+}
+
+/// Default number of files [`copy_dirs_from_repo`] copies at once.
+pub const DEFAULT_COPY_CONCURRENCY: usize = 5;
+
+/// Default [`CopyOptions::large_file_threshold`]: 1 MiB, beyond which the
+/// contents API becomes unreliable.
+pub const DEFAULT_LARGE_FILE_THRESHOLD: u64 = 1024 * 1024;
+
+/// What to do with files above [`CopyOptions::large_file_threshold`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LargeFilePolicy {
+    /// Upload them through the Git Data API, which accepts blobs up to 100 MB.
+    #[default]
+    GitData,
+    /// Leave them out and list them in [`CopyReport::too_large`].
+    Skip,
+}
+
+#[derive(Serialize)]
+struct CommitIdentity<'a> {
+    name: &'a str,
+    email: &'a str,
+}
+
+impl CopyOptions {
+    fn concurrency(&self) -> usize {
+        self.concurrency.unwrap_or(DEFAULT_COPY_CONCURRENCY).max(1)
+    }
+
+    fn large_file_threshold(&self) -> u64 {
+        self.large_file_threshold
+            .unwrap_or(DEFAULT_LARGE_FILE_THRESHOLD)
+    }
+
+    /// Whether `entry` is a large file that [`LargeFilePolicy::Skip`] leaves out.
+    fn skips_as_too_large(&self, entry: &TreeEntry) -> bool {
+        self.large_files == LargeFilePolicy::Skip
+            && entry.is_larger_than(self.large_file_threshold())
+    }
+
+    fn identity(&self) -> Result<Option<CommitIdentity<'_>>> {
+        match (self.author_name.as_deref(), self.author_email.as_deref()) {
+            (Some(name), Some(email)) => Ok(Some(CommitIdentity { name, email })),
+            (None, None) => Ok(None),
+            _ => Err(GithubClientError::InvalidInput(
+                "commit author name and email must be set together".to_string(),
+            )),
+        }
+    }
+
+    /// Applies `template_vars` to a file from the source repository and
+    /// returns the content to upload.
+    fn render(&self, path: &str, bytes: Vec<u8>) -> Result<Vec<u8>> {
+        let selected = self
+            .template_paths
+            .as_ref()
+            .map(|s| s.matches(path))
+            .unwrap_or(true);
+        if !selected || (self.template_vars.is_empty() && !self.strict_template_vars) {
+            return Ok(bytes);
+        }
+        let text = match String::from_utf8(bytes) {
+            Ok(text) => text,
+            Err(binary) => return Ok(binary.into_bytes()),
+        };
+        let (rendered, missing) = substitute_placeholders(&text, &self.template_vars);
+        if self.strict_template_vars && !missing.is_empty() {
+            return Err(GithubClientError::InvalidInput(format!(
+                "'{}' uses template variables with no value: {}",
+                path,
+                missing.join(", ")
+            )));
+        }
+        Ok(rendered.into_bytes())
+    }
+
+    fn commit_message(&self, path: &str, default: impl FnOnce() -> String) -> String {
+        match &self.commit_message_template {
+            Some(template) => template.replace("{path}", path),
+            None => default(),
+        }
+    }
+}
+
+/// Replaces `{{KEY}}` placeholders with values from `vars` and returns the
+/// result along with the keys that had no value (left in place).
+fn substitute_placeholders(text: &str, vars: &HashMap<String, String>) -> (String, Vec<String>) {
+    let mut out = String::with_capacity(text.len());
+    let mut missing: Vec<String> = Vec::new();
+    let mut rest = text;
+    while let Some(start) = rest.find("{{") {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        let key = after.find("}}").map(|end| &after[..end]).filter(|key| {
+            key.starts_with(|c: char| c.is_ascii_uppercase())
+                && key
+                    .chars()
+                    .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '_')
+        });
+        match key {
+            Some(key) => {
+                match vars.get(key) {
+                    Some(value) => out.push_str(value),
+                    None => {
+                        if !missing.iter().any(|m| m == key) {
+                            missing.push(key.to_string());
+                        }
+                        out.push_str(&rest[start..start + key.len() + 4]);
+                    }
+                }
+                rest = &after[key.len() + 2..];
+            }
+            None => {
+                out.push_str("{{");
+                rest = after;
+            }
+        }
+    }
+    out.push_str(rest);
+    (out, missing)
+}
+
+/// Outcome of seeding files into a repository. In dry run, `seeded` lists
+/// the files that would be written.
+#[derive(Serialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct CopyReport {
+    pub seeded: Vec<String>,
+    /// Already present on the target branch and left untouched, plus
+    /// submodules and (with [`CopyOptions::skip_symlinks`]) symlinks.
+    pub skipped: Vec<String>,
+    /// Could not be read or written; see the warnings in the log.
+    pub failed: Vec<String>,
+    /// Above [`CopyOptions::large_file_threshold`] and left out under
+    /// [`LargeFilePolicy::Skip`].
+    pub too_large: Vec<String>,
+    /// Commit created by [`seed_dirs_as_single_commit`].
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub commit: Option<String>,
+}
+
+/// Seeds the files of `source_full_name` that `selector` matches into
+/// `target_branch` of `target_full_name`, skipping files that already exist.
+/// Each file lands at [`CopySelector::target_path`], so a
+/// [`CopySelector::mapped`] selector can move directories around.
+///
+/// Up to [`CopyOptions::concurrency`] files are copied at once; each file's
+/// existence check and upload still happen in order. The report lists paths
+/// sorted, whatever order the copies finished in.
+///
+/// The contents API always writes regular files, so executables and symlinks
+/// are instead committed together through the Git Data API, keeping their
+/// mode. So are files above [`CopyOptions::large_file_threshold`], unless
+/// [`LargeFilePolicy::Skip`] leaves them out. Submodules are skipped with a
+/// warning.
+pub async fn copy_dirs_from_repo(
+    api_base: &str,
+    token: &str,
+    source_full_name: &str,
+    target_full_name: &str,
+    target_branch: &str,
+    selector: &CopySelector,
+    options: &CopyOptions,
+) -> Result<CopyReport> {
+    copy_dirs_from_repo_with_client(
+        &GithubClient::new(api_base, token)?,
+        source_full_name,
+        target_full_name,
+        target_branch,
+        selector,
+        options,
+    )
+    .await
+}
+
+/// [`copy_dirs_from_repo`] on an existing client.
+pub async fn copy_dirs_from_repo_with_client(
+    client: &GithubClient,
+    source_full_name: &str,
+    target_full_name: &str,
+    target_branch: &str,
+    selector: &CopySelector,
+    options: &CopyOptions,
+) -> Result<CopyReport> {
+    copy_dirs_from_repo_with_progress_with_client(
+        client,
+        source_full_name,
+        target_full_name,
+        target_branch,
+        selector,
+        options,
+        |_| {},
+    )
+    .await
+}
+
+/// What happened to one file during [`copy_dirs_from_repo_with_progress`].
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum CopyAction {
+    Created,
+    /// Already present on the target branch and left untouched.
+    Skipped,
+    Failed,
+    /// Left out under [`LargeFilePolicy::Skip`].
+    TooLarge,
+}
+
+/// Progress of a copy, reported once per file after its outcome is known.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CopyProgress {
+    /// Files selected for copying.
+    pub total_files: usize,
+    /// Files finished so far, including this one.
+    pub files_done: usize,
+    pub current_path: String,
+    pub action: CopyAction,
+}
+
+/// Like [`copy_dirs_from_repo`], calling `progress` as each file finishes so
+/// callers can drive their own progress display. Calls come in completion
+/// order, which varies between runs when files are copied concurrently.
+#[allow(clippy::too_many_arguments)]
+pub async fn copy_dirs_from_repo_with_progress(
+    api_base: &str,
+    token: &str,
+    source_full_name: &str,
+    target_full_name: &str,
+    target_branch: &str,
+    selector: &CopySelector,
+    options: &CopyOptions,
+    progress: impl FnMut(CopyProgress),
+) -> Result<CopyReport> {
+    copy_dirs_from_repo_with_progress_with_client(
+        &GithubClient::new(api_base, token)?,
+        source_full_name,
+        target_full_name,
+        target_branch,
+        selector,
+        options,
+        progress,
+    )
+    .await
+}
+
+/// [`copy_dirs_from_repo_with_progress`] on an existing client.
+pub async fn copy_dirs_from_repo_with_progress_with_client(
+    client: &GithubClient,
+    source_full_name: &str,
+    target_full_name: &str,
+    target_branch: &str,
+    selector: &CopySelector,
+    options: &CopyOptions,
+    mut progress: impl FnMut(CopyProgress),
+) -> Result<CopyReport> {
+    validate_ref_name(target_branch)?;
+    let (src_owner, src_repo) = split_template_name(source_full_name)?;
+    let (dst_owner, dst_repo) = split_template_name(target_full_name)?;
+    let identity = options.identity()?;
+    let source_default = get_repo_default_branch_with_client(client, source_full_name).await?;
+    let base = client.api_base();
+    let src_url = format!("{}/repos/{}/{}", base, src_owner, src_repo);
+    let tree = fetch_tree(client, &src_url, &source_default).await?;
+
+    let target = FileCopyTarget {
+        client,
+        src_url,
+        dst_url: format!("{}/repos/{}/{}", base, dst_owner, dst_repo),
+        branch: target_branch,
+        options,
+        identity: identity.as_ref(),
+    };
+    let threshold = options.large_file_threshold();
+    let mut regular: Vec<(&TreeEntry, String)> = Vec::new();
+    let mut via_git_data: Vec<(&TreeEntry, String)> = Vec::new();
+    let mut left_out: Vec<(String, CopyAction)> = Vec::new();
+    for (entry, path) in selected_entries(&tree, selector)? {
+        if entry.is_submodule() {
+            warn!(
+                "Skipping submodule '{}': submodules are not copied",
+                entry.path
+            );
+            left_out.push((path, CopyAction::Skipped));
+        } else if entry.r#type != "blob" {
+            continue;
+        } else if entry.is_symlink() && options.skip_symlinks {
+            warn!("Skipping symlink '{}'", entry.path);
+            left_out.push((path, CopyAction::Skipped));
+        } else if options.skips_as_too_large(entry) {
+            warn!(
+                "Skipping '{}': {} bytes is above the {} byte limit",
+                entry.path,
+                entry.size.unwrap_or_default(),
+                threshold
+            );
+            left_out.push((path, CopyAction::TooLarge));
+        } else if entry.mode() == MODE_FILE && !entry.is_larger_than(threshold) {
+            regular.push((entry, path));
+        } else {
+            via_git_data.push((entry, path));
+        }
+    }
+    let total_files = regular.len() + via_git_data.len() + left_out.len();
+    let mut report = CopyReport::default();
+    let mut files_done = 0;
+    let mut finish = |path: String, action: CopyAction| {
+        files_done += 1;
+        progress(CopyProgress {
+            total_files,
+            files_done,
+            current_path: path.clone(),
+            action,
+        });
+        match action {
+            CopyAction::Created => report.seeded.push(path),
+            CopyAction::Skipped => report.skipped.push(path),
+            CopyAction::Failed => report.failed.push(path),
+            CopyAction::TooLarge => report.too_large.push(path),
+        }
+    };
+    for (path, action) in left_out {
+        finish(path, action);
+    }
+
+    // Check and render every file before the first write, so a strict
+    // template failure or an existing file never leaves a half-seeded branch
+    // The index keeps Git Data files in tree order despite concurrent checks
+    let selected = regular
+        .into_iter()
+        .map(|(entry, path)| (entry, path, None))
+        .chain(
+            via_git_data
+                .into_iter()
+                .enumerate()
+                .map(|(index, (entry, path))| (entry, path, Some(index))),
+        );
+    let mut writes: Vec<(String, Vec<u8>)> = Vec::new();
+    let mut git_data_writes: Vec<(usize, GitDataFile)> = Vec::new();
+    run_bounded(
+        selected,
+        options.concurrency(),
+        |(entry, path, git_data_index)| {
+            let target = &target;
+            async move {
+                let prepared = target.prepare(entry, &path).await?;
+                Ok((entry, path, git_data_index, prepared))
+            }
+        },
+        |(entry, path, git_data_index, prepared)| match (prepared, git_data_index) {
+            (PreparedFile::Done(action), _) => finish(path, action),
+            (PreparedFile::Write(content), Some(index)) => {
+                git_data_writes.push((index, (path, entry.mode(), content)))
+            }
+            (PreparedFile::Write(content), None) => writes.push((path, content)),
+        },
+    )
+    .await?;
+
+    run_bounded(
+        writes,
+        options.concurrency(),
+        |(path, content)| {
+            let target = &target;
+            async move {
+                let action = target.write(&path, &content).await?;
+                Ok((path, action))
+            }
+        },
+        |(path, action): (String, CopyAction)| finish(path, action),
+    )
+    .await?;
+    git_data_writes.sort_by_key(|(index, _)| *index);
+    let git_data_writes: Vec<_> = git_data_writes.into_iter().map(|(_, file)| file).collect();
+    for (path, action) in target.write_via_git_data(&git_data_writes).await? {
+        finish(path, action);
+    }
+
+    report.seeded.sort();
+    report.skipped.sort();
+    report.failed.sort();
+    report.too_large.sort();
+    Ok(report)
+}
+
+/// Runs `op` on every item with at most `limit` running at once, passing
+/// each result to `on_done` as it completes. Stops at the first error.
+async fn run_bounded<I, R, F, Fut>(
+    items: I,
+    limit: usize,
+    op: F,
+    mut on_done: impl FnMut(R),
+) -> Result<()>
+where
+    I: IntoIterator,
+    F: FnMut(I::Item) -> Fut,
+    Fut: std::future::Future<Output = Result<R>>,
+{
+    let mut pending = stream::iter(items).map(op).buffer_unordered(limit.max(1));
+    while let Some(result) = pending.next().await {
+        on_done(result?);
+    }
+    Ok(())
+}
+
+/// Everything [`copy_dirs_from_repo`] needs to copy a single file.
+struct FileCopyTarget<'a> {
+    client: &'a GithubClient,
+    src_url: String,
+    dst_url: String,
+    branch: &'a str,
+    options: &'a CopyOptions,
+    identity: Option<&'a CommitIdentity<'a>>,
+}
+
+/// A file for [`FileCopyTarget::write_via_git_data`]: path, mode and content.
+type GitDataFile<'a> = (String, &'a str, Vec<u8>);
+
+/// A selected file after [`FileCopyTarget::prepare`]: either settled without
+/// writing anything, or rendered and ready to write.
+enum PreparedFile {
+    Done(CopyAction),
+    Write(Vec<u8>),
+}
+
+impl FileCopyTarget<'_> {
+    /// Attempts for an upload rejected with 409 because a concurrent upload
+    /// moved the branch first.
+    const PUT_ATTEMPTS: u32 = 3;
+
+    /// Checks whether `path` is already on the target branch and, if not,
+    /// fetches `entry` and renders it. Nothing is written, so a file that
+    /// fails `strict_template_vars` stops the copy before any upload.
+    async fn prepare(&self, entry: &TreeEntry, path: &str) -> Result<PreparedFile> {
+        let client = self.client;
+        let get_content_url = format!(
+            "{}/contents/{}?ref={}",
+            self.dst_url,
+            encode_path(path),
+            encode_path(self.branch)
+        );
+        let exists = client.send(client.get(&get_content_url)).await?;
+        if exists.status().is_success() {
+            debug!("Skipping existing '{}'", path);
+            if client.is_dry_run() {
+                client.record_skip(format!("{} (already exists)", path));
+            }
+            return Ok(PreparedFile::Done(CopyAction::Skipped));
+        }
+
+        let bytes = match fetch_blob(client, &self.src_url, &entry.path, &entry.sha).await {
+            Ok(bytes) => bytes,
+            Err(err @ GithubClientError::Transport(_)) => return Err(err),
+            Err(err) => {
+                warn!("Failed to fetch '{}': {}", entry.path, err);
+                return Ok(PreparedFile::Done(CopyAction::Failed));
+            }
+        };
+        // A symlink's content is its target path, which must not be templated
+        let content = if entry.is_symlink() {
+            bytes
+        } else {
+            self.options.render(path, bytes)?
+        };
+        Ok(PreparedFile::Write(content))
+    }
+
+    /// Writes `content` at `path` through the contents API.
+    async fn write(&self, path: &str, content: &[u8]) -> Result<CopyAction> {
+        let client = self.client;
+        let put_url = format!("{}/contents/{}", self.dst_url, encode_path(path));
+        if client.is_dry_run() {
+            client.record(
+                Method::PUT,
+                &put_url,
+                format!("would create {} on '{}'", path, self.branch),
+            );
+            return Ok(CopyAction::Created);
+        }
+        let mut payload = serde_json::json!({
+            "message": self.options.commit_message(path, || {
+                format!("chore: seed {} from service-template", path)
+            }),
+            "content": BASE64.encode(content),
+            "branch": self.branch
+        });
+        if let Some(identity) = self.identity {
+            payload["author"] = serde_json::json!(identity);
+            payload["committer"] = serde_json::json!(identity);
+        }
+        let mut attempt = 1;
+        loop {
+            let put_resp = client.send(client.put(&put_url).json(&payload)).await?;
+            let status = put_resp.status();
+            if status.is_success() {
+                debug!("Seeded '{}'", path);
+                return Ok(CopyAction::Created);
+            }
+            if status.as_u16() == 409 && attempt < Self::PUT_ATTEMPTS {
+                debug!("Branch moved while writing '{}', retrying", path);
+                sleep(Duration::from_millis(200 * u64::from(attempt))).await;
+                attempt += 1;
+                continue;
+            }
+            // Other files would fail the same way, so an archived target ends the copy
+            if status.as_u16() == 403 {
+                let err = error_from_response(client, put_resp, &format!("write '{}'", path)).await;
+                if err.is_archived() {
+                    return Err(err);
+                }
+                warn!("Failed to write '{}': {}", path, err);
+                return Ok(CopyAction::Failed);
+            }
+            warn!(
+                "Failed to write '{}' ({}): {}",
+                path,
+                status,
+                decode_github_error(client, put_resp).await
+            );
+            return Ok(CopyAction::Failed);
+        }
+    }
+
+    /// Writes files the contents API cannot, because of their mode
+    /// (executables and symlinks) or their size, in a single commit through
+    /// the Git Data API.
+    async fn write_via_git_data(
+        &self,
+        files: &[GitDataFile<'_>],
+    ) -> Result<Vec<(String, CopyAction)>> {
+        let client = self.client;
+        if files.is_empty() {
+            return Ok(Vec::new());
+        }
+        if client.is_dry_run() {
+            for (path, mode, _) in files {
+                client.record(
+                    Method::POST,
+                    &format!("{}/git/trees", self.dst_url),
+                    format!(
+                        "would create {} with mode {} on '{}'",
+                        path, mode, self.branch
+                    ),
+                );
+            }
+            return Ok(files
+                .iter()
+                .map(|(path, _, _)| (path.clone(), CopyAction::Created))
+                .collect());
+        }
+
+        let mut blobs: Vec<(&str, &str, String)> = Vec::new();
+        for (path, mode, content) in files {
+            let sha = create_blob(client, &self.dst_url, path, content).await?;
+            blobs.push((path, mode, sha));
+        }
+        let tree_entries: Vec<NewTreeEntry> = blobs
+            .iter()
+            .map(|(path, mode, sha)| NewTreeEntry {
+                path,
+                mode,
+                r#type: "blob",
+                sha,
+            })
+            .collect();
+        let subject = match blobs.as_slice() {
+            [(path, _, _)] => path.to_string(),
+            _ => format!("{} files", blobs.len()),
+        };
+        let message = self.options.commit_message(&subject, || {
+            format!("chore: seed {} from service-template", subject)
+        });
+        let committed = match branch_head(client, &self.dst_url, self.branch).await {
+            Ok(Some((head_sha, base_tree_sha))) => {
+                commit_tree(
+                    client,
+                    &self.dst_url,
+                    self.branch,
+                    (&head_sha, &base_tree_sha),
+                    &tree_entries,
+                    &message,
+                    self.identity,
+                )
+                .await
+            }
+            Ok(None) => Err(GithubClientError::NotFound {
+                resource: format!("branch '{}'", self.branch),
+            }),
+            Err(err) => Err(err),
+        };
+        let action = match committed {
+            Ok(sha) => {
+                debug!(
+                    "Seeded {} file(s) through the Git Data API in {}",
+                    blobs.len(),
+                    sha
+                );
+                CopyAction::Created
+            }
+            Err(err @ GithubClientError::Transport(_)) => return Err(err),
+            Err(err) => {
+                warn!(
+                    "Failed to commit {} file(s) through the Git Data API: {}",
+                    blobs.len(),
+                    err
+                );
+                CopyAction::Failed
+            }
+        };
+        Ok(blobs
+            .into_iter()
+            .map(|(path, _, _)| (path.to_string(), action))
+            .collect())
+    }
+}
+
+/// Lists the paths [`copy_dirs_from_repo`] would try to seed: the blobs in the
+/// source repository's default branch that `selector` matches, at their
+/// destination paths.
+pub async fn list_source_paths(
+    api_base: &str,
+    token: &str,
+    source_full_name: &str,
+    selector: &CopySelector,
+) -> Result<Vec<String>> {
+    list_source_paths_with_client(
+        &GithubClient::new(api_base, token)?,
+        source_full_name,
+        selector,
+    )
+    .await
+}
+
+/// [`list_source_paths`] on an existing client.
+pub async fn list_source_paths_with_client(
+    client: &GithubClient,
+    source_full_name: &str,
+    selector: &CopySelector,
+) -> Result<Vec<String>> {
+    let (src_owner, src_repo) = split_template_name(source_full_name)?;
+    let source_default = get_repo_default_branch_with_client(client, source_full_name).await?;
+    let src_url = format!("{}/repos/{}/{}", client.api_base(), src_owner, src_repo);
+
+    let tree = fetch_tree(client, &src_url, &source_default).await?;
+    Ok(selected_entries(&tree, selector)?
+        .into_iter()
+        .filter(|(e, _)| e.r#type == "blob")
+        .map(|(_, path)| path)
+        .collect())
+}
+
+/// Checks that every path in `expected_paths` exists on `branch` and returns
+/// the ones that are missing.
+pub async fn verify_seed(
+    api_base: &str,
+    token: &str,
+    full_name: &str,
+    branch: &str,
+    expected_paths: &[&str],
+) -> Result<Vec<String>> {
+    verify_seed_with_client(
+        &GithubClient::new(api_base, token)?,
+        full_name,
+        branch,
+        expected_paths,
+    )
+    .await
+}
+
+/// [`verify_seed`] on an existing client.
+pub async fn verify_seed_with_client(
+    client: &GithubClient,
+    full_name: &str,
+    branch: &str,
+    expected_paths: &[&str],
+) -> Result<Vec<String>> {
+    let (owner, repo) = split_template_name(full_name)?;
+    let base = client.api_base();
+
+    let mut missing = Vec::new();
+    for path in expected_paths {
+        let url = format!(
+            "{}/repos/{}/{}/contents/{}?ref={}",
+            base,
+            owner,
+            repo,
+            encode_path(path),
+            encode_path(branch)
+        );
+        let resp = client.send(client.get(&url)).await?;
+        match resp.status().as_u16() {
+            200 => debug!("Verified '{}'", path),
+            404 => {
+                warn!("Expected '{}' is missing on '{}'", path, branch);
+                missing.push(path.to_string());
+            }
+            _ => {
+                return Err(error_from_response(client, resp, &format!("verify '{}'", path)).await);
+            }
+        }
+    }
+    Ok(missing)
+}
+
+/// Creates `path` on `branch` through the contents API unless it already exists.
+///
+/// Returns `true` when the file was written and `false` when it was skipped
+/// because it is already present.
+pub async fn put_file(
+    api_base: &str,
+    token: &str,
+    full_name: &str,
+    branch: &str,
+    path: &str,
+    content: &[u8],
+    message: &str,
+) -> Result<bool> {
+    put_file_with_client(
+        &GithubClient::new(api_base, token)?,
+        full_name,
+        branch,
+        path,
+        content,
+        message,
+    )
+    .await
+}
+
+/// [`put_file`] on an existing client.
+pub async fn put_file_with_client(
+    client: &GithubClient,
+    full_name: &str,
+    branch: &str,
+    path: &str,
+    content: &[u8],
+    message: &str,
+) -> Result<bool> {
+    let (owner, repo) = split_template_name(full_name)?;
+    let base = client.api_base();
+
+    // Skip if exists
+    let get_content_url = format!(
+        "{}/repos/{}/{}/contents/{}?ref={}",
+        base,
+        owner,
+        repo,
+        encode_path(path),
+        encode_path(branch)
+    );
+    let exists = client.send(client.get(&get_content_url)).await?;
+    if exists.status().is_success() {
+        debug!("Skipping existing '{}'", path);
+        if client.is_dry_run() {
+            client.record_skip(format!("{} (already exists)", path));
+        }
+        return Ok(false);
+    }
+
+    let put_url = format!(
+        "{}/repos/{}/{}/contents/{}",
+        base,
+        owner,
+        repo,
+        encode_path(path)
+    );
+    if client.is_dry_run() {
+        client.record(
+            Method::PUT,
+            &put_url,
+            format!("would create {} on '{}'", path, branch),
+        );
+        return Ok(true);
+    }
+    let payload = serde_json::json!({
+        "message": message,
+        "content": BASE64.encode(content),
+        "branch": branch
+    });
+    let put_resp = client.send(client.put(&put_url).json(&payload)).await?;
+    if !put_resp.status().is_success() {
+        return Err(error_from_response(client, put_resp, &format!("write '{}'", path)).await);
+    }
+    info!("Wrote '{}'", path);
+    Ok(true)
 }
 
-pub async fn ensure_environment_with_branches(
+/// Creates a `.gitkeep` in each of `dirs` so that empty template directories
+/// (which git does not track) still exist in the target repository.
+pub async fn seed_empty_dirs(
     api_base: &str,
     token: &str,
     full_name: &str,
-    env_name: &str,
-    allowed_patterns: &[&str],
+    branch: &str,
+    dirs: &[&str],
 ) -> Result<()> {
-    let (owner, repo) = split_template_name(full_name)?;
-    let base = api_base.trim_end_matches('/');
-    let env_url = format!(
-        "{}/repos/{}/{}/environments/{}",
-        base, owner, repo, env_name
-    );
-
-    let mut headers = HeaderMap::new();
-    headers.insert(
-        AUTHORIZATION,
-        HeaderValue::from_str(&format!("Bearer {}", token))?,
-    );
-    headers.insert(
-        ACCEPT,
-        HeaderValue::from_static("application/vnd.github+json"),
-    );
-    headers.insert(
-        USER_AGENT,
-        HeaderValue::from_static("github-client-rust/0.1"),
-    );
-    headers.insert(
-        HeaderName::from_static("x-github-api-version"),
-        HeaderValue::from_static("2022-11-28"),
-    );
-    let client = reqwest::Client::builder()
-        .default_headers(headers.clone())
-        .build()?;
-
-    // Enable custom branch policies
-    let body = EnvironmentRequest {
-        deployment_branch_policy: DeploymentBranchPolicy {
-            protected_branches: false,
-            custom_branch_policies: true,
-        },
-    };
-    let resp = client.put(&env_url).json(&body).send().await?;
-    if !resp.status().is_success() {
-        let text = resp.text().await.unwrap_or_default();
-        return Err(anyhow!(format!(
-            "Failed to create/update environment '{}': {}",
-            env_name, text
-        )));
-    }
+    seed_empty_dirs_with_client(
+        &GithubClient::new(api_base, token)?,
+        full_name,
+        branch,
+        dirs,
+    )
+    .await
+}
 
-    // Add allowed branch patterns
-    let policy_url = format!("{}/deployment-branch-policies", env_url);
-    for pat in allowed_patterns {
-        let payload = serde_json::json!({ "name": pat });
-        let add = client.post(&policy_url).json(&payload).send().await?;
-        if !add.status().is_success() && add.status().as_u16() != 201 {
-            let text = add.text().await.unwrap_or_default();
-            warn!(
-                "Failed to add branch policy '{}' to env '{}': {}",
-                pat, env_name, text
-            );
-        } else {
-            info!(
-                "Environment '{}' allows branches matching '{}'",
-                env_name, pat
-            );
+/// [`seed_empty_dirs`] on an existing client.
+pub async fn seed_empty_dirs_with_client(
+    client: &GithubClient,
+    full_name: &str,
+    branch: &str,
+    dirs: &[&str],
+) -> Result<()> {
+    for dir in dirs {
+        let dir = dir.trim().trim_matches('/');
+        if dir.is_empty() {
+            continue;
         }
+        let path = format!("{}/.gitkeep", dir);
+        put_file_with_client(
+            client,
+            full_name,
+            branch,
+            &path,
+            b"",
+            &format!("chore: add {}", path),
+        )
+        .await?;
     }
     Ok(())
 }
 
-#[derive(Deserialize)]
-struct TreeEntry {
-    path: String,
-    r#type: String,
-    sha: String,
-}
-
-#[derive(Deserialize)]
-struct GitTree {
-    tree: Vec<TreeEntry>,
+#[derive(Serialize)]
+struct NewTreeEntry<'a> {
+    path: &'a str,
+    mode: &'a str,
+    r#type: &'a str,
+    sha: &'a str,
 }
 
 #[derive(Deserialize)]
-struct RepoInfo {
-    default_branch: String,
+struct ShaResponse {
+    sha: String,
 }
 
 #[derive(Deserialize)]
-struct Blob {
-    content: String,
-    encoding: String,
+struct CommitResponse {
+    tree: ShaResponse,
 }
 
-pub async fn get_repo_default_branch(
+/// Seeds the files `selector` matches from the source repository as a single
+/// commit.
+///
+/// Unlike [`copy_dirs_from_repo`], which creates one commit per file through
+/// the contents API, this uses the Git Data API: it uploads a blob per file,
+/// builds one tree on top of the target branch's current tree, commits it and
+/// fast-forwards the branch, keeping each file's mode so executables and
+/// symlinks survive. Paths already present on the target branch are
+/// left untouched. The report's `commit` is the new commit SHA, or `None`
+/// when there was nothing to seed.
+pub async fn seed_dirs_as_single_commit(
     api_base: &str,
     token: &str,
-    full_name: &str,
-) -> Result<String> {
-    let (owner, repo) = split_template_name(full_name)?;
-    let url = format!(
-        "{}/repos/{}/{}",
-        api_base.trim_end_matches('/'),
-        owner,
-        repo
-    );
-    let mut headers = HeaderMap::new();
-    headers.insert(
-        AUTHORIZATION,
-        HeaderValue::from_str(&format!("Bearer {}", token))?,
-    );
-    headers.insert(
-        ACCEPT,
-        HeaderValue::from_static("application/vnd.github+json"),
-    );
-    headers.insert(
-        USER_AGENT,
-        HeaderValue::from_static("github-client-rust/0.1"),
-    );
-    headers.insert(
-        HeaderName::from_static("x-github-api-version"),
-        HeaderValue::from_static("2022-11-28"),
-    );
-    let client = reqwest::Client::builder()
-        .default_headers(headers)
-        .build()?;
-    let resp = client.get(url).send().await?;
-    if !resp.status().is_success() {
-        let text = resp.text().await.unwrap_or_default();
-        return Err(anyhow!(format!(
-            "Failed to read repo info for '{}': {}",
-            full_name, text
-        )));
-    }
-    let info: RepoInfo = resp.json().await?;
-    Ok(info.default_branch)
+    source_full_name: &str,
+    target_full_name: &str,
+    target_branch: &str,
+    selector: &CopySelector,
+    options: &CopyOptions,
+) -> Result<CopyReport> {
+    seed_dirs_as_single_commit_with_client(
+        &GithubClient::new(api_base, token)?,
+        source_full_name,
+        target_full_name,
+        target_branch,
+        selector,
+        options,
+    )
+    .await
 }
 
-pub async fn copy_dirs_from_repo(
-    api_base: &str,
-    token: &str,
+/// [`seed_dirs_as_single_commit`] on an existing client.
+pub async fn seed_dirs_as_single_commit_with_client(
+    client: &GithubClient,
     source_full_name: &str,
     target_full_name: &str,
     target_branch: &str,
-    dir_prefixes: &[&str],
-) -> Result<()> {
+    selector: &CopySelector,
+    options: &CopyOptions,
+) -> Result<CopyReport> {
     let (src_owner, src_repo) = split_template_name(source_full_name)?;
     let (dst_owner, dst_repo) = split_template_name(target_full_name)?;
-    let source_default = get_repo_default_branch(api_base, token, source_full_name).await?;
-    let base = api_base.trim_end_matches('/');
-
-    let mut headers = HeaderMap::new();
-    headers.insert(
-        AUTHORIZATION,
-        HeaderValue::from_str(&format!("Bearer {}", token))?,
-    );
-    headers.insert(
-        ACCEPT,
-        HeaderValue::from_static("application/vnd.github+json"),
-    );
-    headers.insert(
-        USER_AGENT,
-        HeaderValue::from_static("github-client-rust/0.1"),
-    );
-    headers.insert(
-        HeaderName::from_static("x-github-api-version"),
-        HeaderValue::from_static("2022-11-28"),
-    );
-    let client = reqwest::Client::builder()
-        .default_headers(headers.clone())
-        .build()?;
+    let identity = options.identity()?;
+    let source_default = get_repo_default_branch_with_client(client, source_full_name).await?;
+    let base = client.api_base();
+    let src_url = format!("{}/repos/{}/{}", base, src_owner, src_repo);
+    let dst_url = format!("{}/repos/{}/{}", base, dst_owner, dst_repo);
 
-    // Fetch source tree recursively
-    let tree_url = format!(
-        "{}/repos/{}/{}/git/trees/{}?recursive=1",
-        base, src_owner, src_repo, source_default
-    );
-    let tree_resp = client.get(&tree_url).send().await?;
-    if !tree_resp.status().is_success() {
-        let text = tree_resp.text().await.unwrap_or_default();
-        return Err(anyhow!(format!("Failed to read source tree: {}", text)));
+    // Resolve the target branch head; the branch must already exist. A dry run
+    // may target a repository that has not been created yet, so plan against
+    // an empty branch instead.
+    let head = branch_head(client, &dst_url, target_branch).await?;
+    if head.is_none() && !client.is_dry_run() {
+        return Err(GithubClientError::NotFound {
+            resource: format!(
+                "target branch '{}' in '{}'; it must exist before seeding as a single commit",
+                target_branch, target_full_name
+            ),
+        });
     }
-    let tree: GitTree = tree_resp.json().await?;
 
-    for entry in tree.tree.iter().filter(|e| e.r#type == "blob") {
-        if !dir_prefixes.iter().any(|p| entry.path.starts_with(p)) {
+    // Paths already on the target branch are skipped, like copy_dirs_from_repo does
+    let existing: HashSet<String> = match &head {
+        Some((_, base_tree_sha)) => fetch_tree(client, &dst_url, base_tree_sha)
+            .await?
+            .tree
+            .into_iter()
+            .map(|e| e.path)
+            .collect(),
+        None => HashSet::new(),
+    };
+
+    let source_tree = fetch_tree(client, &src_url, &source_default).await?;
+    let mut new_entries: Vec<(String, &str, String)> = Vec::new();
+    let mut report = CopyReport::default();
+    for (entry, path) in selected_entries(&source_tree, selector)? {
+        if entry.is_submodule() {
+            warn!(
+                "Skipping submodule '{}': submodules are not copied",
+                entry.path
+            );
+            report.skipped.push(path);
             continue;
         }
-        // Fetch blob
-        let blob_url = format!(
-            "{}/repos/{}/{}/git/blobs/{}",
-            base, src_owner, src_repo, entry.sha
-        );
-        let blob_resp = client.get(&blob_url).send().await?;
-        if !blob_resp.status().is_success() {
-            let text = blob_resp.text().await.unwrap_or_default();
-            warn!("Failed to fetch blob for '{}': {}", entry.path, text);
+        if entry.r#type != "blob" {
+            continue;
+        }
+        if entry.is_symlink() && options.skip_symlinks {
+            warn!("Skipping symlink '{}'", entry.path);
+            report.skipped.push(path);
             continue;
         }
-        let blob: Blob = blob_resp.json().await?;
-        if blob.encoding.to_lowercase() != "base64" {
+        if options.skips_as_too_large(entry) {
             warn!(
-                "Unexpected blob encoding for '{}': {}",
-                entry.path, blob.encoding
+                "Skipping '{}': {} bytes is above the {} byte limit",
+                entry.path,
+                entry.size.unwrap_or_default(),
+                options.large_file_threshold()
             );
+            report.too_large.push(path);
             continue;
         }
-        let content_b64 = blob.content.replace('\n', "");
-
-        // Skip if exists
-        let get_content_url = format!(
-            "{}/repos/{}/{}/contents/{}?ref={}",
-            base, dst_owner, dst_repo, entry.path, target_branch
-        );
-        let exists = client.get(&get_content_url).send().await?;
-        if exists.status().is_success() {
-            debug!("Skipping existing '{}'", entry.path);
+        if existing.contains(&path) {
+            debug!("Skipping existing '{}'", path);
+            if client.is_dry_run() {
+                client.record_skip(format!("{} (already exists)", path));
+            }
+            report.skipped.push(path);
+            continue;
+        }
+        if client.is_dry_run() {
+            new_entries.push((path, entry.mode(), entry.sha.clone()));
             continue;
         }
 
-        // PUT file
-        let put_url = format!(
-            "{}/repos/{}/{}/contents/{}",
-            base, dst_owner, dst_repo, entry.path
-        );
-        let payload = serde_json::json!({
-            "message": format!("chore: seed {} from service-template", entry.path),
-            "content": content_b64,
-            "branch": target_branch
-        });
-        let put_resp = client.put(&put_url).json(&payload).send().await?;
-        if !put_resp.status().is_success() && put_resp.status().as_u16() != 201 {
-            let text = put_resp.text().await.unwrap_or_default();
-            warn!("Failed to write '{}': {}", entry.path, text);
+        let bytes = fetch_blob(client, &src_url, &entry.path, &entry.sha).await?;
+        // A symlink's content is its target path, which must not be templated
+        let content = if entry.is_symlink() {
+            bytes
         } else {
-            info!("Seeded '{}'", entry.path);
+            options.render(&path, bytes)?
+        };
+        let sha = create_blob(client, &dst_url, &path, &content).await?;
+        new_entries.push((path, entry.mode(), sha));
+    }
+
+    report.seeded = new_entries.iter().map(|(p, _, _)| p.clone()).collect();
+    if client.is_dry_run() {
+        if !new_entries.is_empty() {
+            let paths: Vec<&str> = new_entries.iter().map(|(p, _, _)| p.as_str()).collect();
+            client.record(
+                Method::POST,
+                &format!("{}/git/commits", dst_url),
+                format!(
+                    "seed {} file(s) onto '{}' in one commit: {}",
+                    paths.len(),
+                    target_branch,
+                    paths.join(", ")
+                ),
+            );
+        }
+        return Ok(report);
+    }
+    let Some((head_sha, base_tree_sha)) = head else {
+        return Ok(report);
+    };
+
+    if new_entries.is_empty() {
+        info!("Nothing to seed into '{}'", target_full_name);
+        return Ok(report);
+    }
+
+    let tree_entries: Vec<NewTreeEntry> = new_entries
+        .iter()
+        .map(|(path, mode, sha)| NewTreeEntry {
+            path,
+            mode,
+            r#type: "blob",
+            sha,
+        })
+        .collect();
+    let message = options.commit_message(&format!("{} files", new_entries.len()), || {
+        "chore: seed scaffolding".to_string()
+    });
+    let commit = commit_tree(
+        client,
+        &dst_url,
+        target_branch,
+        (&head_sha, &base_tree_sha),
+        &tree_entries,
+        &message,
+        identity.as_ref(),
+    )
+    .await?;
+
+    info!(
+        "Seeded {} file(s) into '{}' in commit {}",
+        new_entries.len(),
+        target_full_name,
+        commit
+    );
+    report.commit = Some(commit);
+    Ok(report)
+}
+
+/// Resolves `branch` to its head commit and that commit's tree, or `None`
+/// when the branch does not exist.
+async fn branch_head(
+    client: &GithubClient,
+    repo_url: &str,
+    branch: &str,
+) -> Result<Option<(String, String)>> {
+    let ref_resp = client
+        .send(client.get(format!(
+            "{}/git/ref/heads/{}",
+            repo_url,
+            encode_path(branch)
+        )))
+        .await?;
+    if ref_resp.status().as_u16() == 404 {
+        return Ok(None);
+    }
+    if !ref_resp.status().is_success() {
+        return Err(error_from_response(
+            client,
+            ref_resp,
+            &format!("read target branch '{}'", branch),
+        )
+        .await);
+    }
+    let head: GitRef = ref_resp.json().await?;
+    let head_sha = head.obj.sha;
+
+    let commit_resp = client
+        .send(client.get(format!("{}/git/commits/{}", repo_url, head_sha)))
+        .await?;
+    if !commit_resp.status().is_success() {
+        return Err(error_from_response(
+            client,
+            commit_resp,
+            &format!("read head commit of '{}'", branch),
+        )
+        .await);
+    }
+    let head_commit: CommitResponse = commit_resp.json().await?;
+    Ok(Some((head_sha, head_commit.tree.sha)))
+}
+
+/// Uploads `content` as a blob and returns its SHA.
+async fn create_blob(
+    client: &GithubClient,
+    repo_url: &str,
+    path: &str,
+    content: &[u8],
+) -> Result<String> {
+    let create_resp = client
+        .send(
+            client
+                .post(format!("{}/git/blobs", repo_url))
+                .json(&serde_json::json!({
+                    "content": BASE64.encode(content),
+                    "encoding": "base64"
+                })),
+        )
+        .await?;
+    if !create_resp.status().is_success() {
+        return Err(error_from_response(
+            client,
+            create_resp,
+            &format!("create blob for '{}'", path),
+        )
+        .await);
+    }
+    let created: ShaResponse = create_resp.json().await?;
+    Ok(created.sha)
+}
+
+/// Commits `entries` on top of `head` (commit and tree SHA) and fast-forwards
+/// `branch` to the new commit, whose SHA is returned.
+async fn commit_tree(
+    client: &GithubClient,
+    repo_url: &str,
+    branch: &str,
+    (head_sha, base_tree_sha): (&str, &str),
+    entries: &[NewTreeEntry<'_>],
+    message: &str,
+    identity: Option<&CommitIdentity<'_>>,
+) -> Result<String> {
+    let commit = create_commit(
+        client,
+        repo_url,
+        head_sha,
+        Some(base_tree_sha),
+        entries,
+        message,
+        identity,
+    )
+    .await?;
+
+    let update_resp = client
+        .send(
+            client
+                .patch(format!(
+                    "{}/git/refs/heads/{}",
+                    repo_url,
+                    encode_path(branch)
+                ))
+                .json(&serde_json::json!({ "sha": commit, "force": false })),
+        )
+        .await?;
+    if !update_resp.status().is_success() {
+        return Err(error_from_response(
+            client,
+            update_resp,
+            &format!("update branch '{}'", branch),
+        )
+        .await);
+    }
+    Ok(commit)
+}
+
+/// Creates a tree from `entries` and a commit of it with `parent` as its only
+/// parent, and returns the commit SHA. Without `base_tree` the tree holds
+/// exactly `entries`.
+async fn create_commit(
+    client: &GithubClient,
+    repo_url: &str,
+    parent: &str,
+    base_tree: Option<&str>,
+    entries: &[NewTreeEntry<'_>],
+    message: &str,
+    identity: Option<&CommitIdentity<'_>>,
+) -> Result<String> {
+    let mut tree_body = serde_json::json!({ "tree": entries });
+    if let Some(base_tree) = base_tree {
+        tree_body["base_tree"] = serde_json::json!(base_tree);
+    }
+    let tree_resp = client
+        .send(
+            client
+                .post(format!("{}/git/trees", repo_url))
+                .json(&tree_body),
+        )
+        .await?;
+    if !tree_resp.status().is_success() {
+        return Err(error_from_response(client, tree_resp, "create tree").await);
+    }
+    let tree: ShaResponse = tree_resp.json().await?;
+
+    let mut commit_body = serde_json::json!({
+        "message": message,
+        "tree": tree.sha,
+        "parents": [parent]
+    });
+    if let Some(identity) = identity {
+        commit_body["author"] = serde_json::json!(identity);
+        commit_body["committer"] = serde_json::json!(identity);
+    }
+    let commit_resp = client
+        .send(
+            client
+                .post(format!("{}/git/commits", repo_url))
+                .json(&commit_body),
+        )
+        .await?;
+    if !commit_resp.status().is_success() {
+        return Err(error_from_response(client, commit_resp, "create commit").await);
+    }
+    let commit: ShaResponse = commit_resp.json().await?;
+    Ok(commit.sha)
+}
+
+async fn fetch_tree(client: &GithubClient, repo_url: &str, tree_ish: &str) -> Result<GitTree> {
+    let url = format!(
+        "{}/git/trees/{}?recursive=1",
+        repo_url,
+        encode_path(tree_ish)
+    );
+    match client.get_json_cached(&url).await? {
+        Ok(tree) => Ok(tree),
+        Err(resp) => {
+            Err(error_from_response(client, resp, &format!("read tree '{}'", tree_ish)).await)
         }
     }
-    Ok(())
 }
 #[cfg(test)]
 mod tests {
-    use super::split_template_name;
+    use super::{encode_path, encode_segment, validate_env_name, validate_ref_name};
+    use super::{
+        normalize_api_base, normalize_topics, redact, run_bounded, scope_covers, seal_secret,
+        split_template_name, substitute_placeholders, validate_secret_name, validate_variable_name,
+        CopySelector, GithubClient, RepoResponse, TokenKind,
+    };
+    use base64::engine::general_purpose::STANDARD as BASE64;
+    use base64::Engine;
+    use crypto_box::SecretKey;
+    use std::collections::HashMap;
 
     #[test]
     fn split_template_name_ok() {
@@ -834,4 +5899,326 @@ mod tests {
         assert!(split_template_name("owner/").is_err());
         assert!(split_template_name("/repo").is_err());
     }
+
+    #[test]
+    fn validates_branch_names() {
+        for ok in ["main", "feature/new-thing", "release/1.2", "user@fix"] {
+            validate_ref_name(ok).unwrap();
+        }
+        let reason = |name: &str| validate_ref_name(name).unwrap_err().to_string();
+        assert!(
+            reason("feature/new thing").contains("must not contain spaces"),
+            "{}",
+            reason("feature/new thing")
+        );
+        assert!(reason("../escape").contains("'..'"));
+        assert!(reason("/main").contains("start or end with '/'"));
+        assert!(reason("feature/.hidden").contains("start with '.'"));
+        assert!(reason("").contains("must not be empty"));
+        assert_eq!(
+            reason("refs/heads/x"),
+            "invalid branch name 'refs/heads/x': give the branch name without 'refs/heads/', e.g. 'x'"
+        );
+    }
+
+    #[test]
+    fn validates_environment_names() {
+        validate_env_name("production").unwrap();
+        validate_env_name("qa/eu west").unwrap();
+        assert!(validate_env_name("").is_err());
+        assert!(validate_env_name(" prod").is_err());
+        assert!(validate_env_name("..").is_err());
+        assert!(validate_env_name("prod\n").is_err());
+        assert!(validate_env_name(&"e".repeat(256)).is_err());
+    }
+
+    #[test]
+    fn encodes_names_for_urls() {
+        assert_eq!(encode_path("feature/new thing"), "feature/new%20thing");
+        assert_eq!(encode_path("docs/a#b?.md"), "docs/a%23b%3F.md");
+        assert_eq!(encode_path("fix+1&2"), "fix%2B1%262");
+        assert_eq!(encode_segment("../escape"), "..%2Fescape");
+        assert_eq!(encode_segment("qa/eu west"), "qa%2Feu%20west");
+    }
+
+    #[test]
+    fn seal_secret_round_trips() {
+        let secret_key = SecretKey::from([7u8; 32]);
+        let public_key_b64 = BASE64.encode(secret_key.public_key().as_bytes());
+
+        let sealed = seal_secret(&public_key_b64, "s3cr3t").unwrap();
+        let opened = secret_key.unseal(&BASE64.decode(sealed).unwrap()).unwrap();
+        assert_eq!(opened, b"s3cr3t");
+    }
+
+    #[test]
+    fn seal_secret_rejects_bad_key() {
+        assert!(seal_secret("not base64!", "x").is_err());
+        assert!(seal_secret(&BASE64.encode([1u8; 8]), "x").is_err());
+    }
+
+    #[test]
+    fn copy_selector_prefixes_match_path_starts() {
+        let selector = CopySelector::prefixes(&["terraform/", "helm/"]);
+        assert!(selector.matches("terraform/main.tf"));
+        assert!(selector.matches("helm/charts/app/values.yaml"));
+        assert!(!selector.matches("README.md"));
+    }
+
+    #[test]
+    fn copy_selector_globs_apply_exclusions() {
+        let selector = CopySelector::globs(&["terraform/", "*.md", "!**/secrets.tfvars"]).unwrap();
+        assert!(selector.matches("terraform/env/prod/main.tf"));
+        assert!(!selector.matches("terraform/env/prod/secrets.tfvars"));
+        assert!(selector.matches("README.md"));
+        // `*` does not cross directory boundaries
+        assert!(!selector.matches("docs/guide.md"));
+
+        let all_but = CopySelector::globs(&["!**/*.tfvars"]).unwrap();
+        assert!(all_but.matches("helm/values.yaml"));
+        assert!(!all_but.matches("terraform/prod.tfvars"));
+    }
+
+    #[test]
+    fn copy_selector_excluding_drops_matches_after_prefix() {
+        let selector = CopySelector::prefixes(&["helm/"])
+            .excluding(&["helm/tests/**", "**/*.md"])
+            .unwrap();
+        assert!(selector.matches("helm/templates/deployment.yaml"));
+        // Directory glob
+        assert!(!selector.matches("helm/tests/deployment_test.yaml"));
+        // Extension glob at any depth
+        assert!(!selector.matches("helm/README.md"));
+        assert!(!selector.matches("helm/charts/app/NOTES.md"));
+        // Case-sensitive
+        assert!(selector.matches("helm/CHANGELOG.MD"));
+        // Exclusions never add paths outside the prefixes
+        assert!(!selector.matches("terraform/main.tf"));
+
+        let dir_form = CopySelector::prefixes(&["helm/"])
+            .excluding(&["helm/tests/"])
+            .unwrap();
+        assert!(!dir_form.matches("helm/tests/a/b.yaml"));
+        assert!(CopySelector::prefixes(&["helm/"])
+            .excluding(&["helm/[oops"])
+            .is_err());
+    }
+
+    #[test]
+    fn copy_selector_mapped_renames_prefixes() {
+        let selector = CopySelector::mapped(&[
+            ("modules/bootstrap/terraform/", "terraform/"),
+            ("helm/", "helm/"),
+        ]);
+        assert!(selector.matches("modules/bootstrap/terraform/env/main.tf"));
+        assert!(!selector.matches("terraform/main.tf"));
+        assert_eq!(
+            selector.target_path("modules/bootstrap/terraform/env/main.tf"),
+            "terraform/env/main.tf"
+        );
+        assert_eq!(selector.target_path("helm/values.yaml"), "helm/values.yaml");
+        let globs = CopySelector::globs(&["docs/"]).unwrap();
+        assert_eq!(globs.target_path("docs/a.md"), "docs/a.md");
+    }
+
+    #[test]
+    fn normalize_topics_lowercases_and_validates() {
+        assert_eq!(
+            normalize_topics(&["Service", " rust ", "data_platform", "rust"]).unwrap(),
+            vec!["service", "rust", "data-platform"]
+        );
+        assert!(normalize_topics(&["-leading"]).is_err());
+        assert!(normalize_topics(&["c++"]).is_err());
+        assert!(normalize_topics(&[&"a".repeat(51)]).is_err());
+        let many: Vec<String> = (0..21).map(|i| format!("t{}", i)).collect();
+        let many: Vec<&str> = many.iter().map(String::as_str).collect();
+        assert!(normalize_topics(&many).is_err());
+    }
+
+    #[test]
+    fn copy_selector_rejects_invalid_glob() {
+        assert!(CopySelector::globs(&["terraform/[abc"]).is_err());
+    }
+
+    #[test]
+    fn substitute_placeholders_replaces_known_keys_only() {
+        let vars = HashMap::from([("SERVICE_NAME".to_string(), "billing".to_string())]);
+        let (out, missing) = substitute_placeholders(
+            "{{SERVICE_NAME}}-{{TEAM}} {{ .Values.x }} {{end}} {{SERVICE_NAME",
+            &vars,
+        );
+        assert_eq!(
+            out,
+            "billing-{{TEAM}} {{ .Values.x }} {{end}} {{SERVICE_NAME"
+        );
+        assert_eq!(missing, vec!["TEAM".to_string()]);
+    }
+
+    #[test]
+    fn redact_masks_every_token_occurrence() {
+        let body = r#"{"message":"Bad credentials ghp_abc123","echo":"Bearer ghp_abc123"}"#;
+        let masked = redact("ghp_abc123", body);
+        assert!(!masked.contains("ghp_abc123"));
+        assert_eq!(
+            masked,
+            r#"{"message":"Bad credentials ***","echo":"Bearer ***"}"#
+        );
+        assert_eq!(redact("", "unchanged"), "unchanged");
+    }
+
+    #[test]
+    fn validate_secret_name_rules() {
+        assert!(validate_secret_name("SONAR_TOKEN").is_ok());
+        assert!(validate_secret_name("deploy_key_2").is_ok());
+        assert!(validate_secret_name("").is_err());
+        assert!(validate_secret_name("MY-SECRET").is_err());
+        assert!(validate_secret_name("HAS SPACE").is_err());
+        assert!(validate_secret_name("1PASSWORD").is_err());
+        assert!(validate_secret_name("GITHUB_TOKEN").is_err());
+        assert!(validate_secret_name("github_anything").is_err());
+    }
+
+    #[test]
+    fn validate_variable_name_rules() {
+        assert!(validate_variable_name("AWS_REGION").is_ok());
+        assert!(validate_variable_name("TIER_2").is_ok());
+        assert!(validate_variable_name("aws_region").is_err());
+        assert!(validate_variable_name("AWS-REGION").is_err());
+        assert!(validate_variable_name("2FA").is_err());
+        assert!(validate_variable_name("GITHUB_SHA").is_err());
+        assert!(validate_variable_name("").is_err());
+    }
+
+    #[test]
+    fn repo_response_serializes_as_valid_json() {
+        let repo = RepoResponse {
+            full_name: "me/odd\"name".to_string(),
+            html_url: "https://github.com/me/odd\\name".to_string(),
+            default_branch: "main".to_string(),
+        };
+        let json = serde_json::to_string(&repo).unwrap();
+        let parsed: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed["full_name"], "me/odd\"name");
+        assert_eq!(parsed["html_url"], "https://github.com/me/odd\\name");
+    }
+
+    #[test]
+    fn keeps_github_com_api_base() {
+        assert_eq!(
+            normalize_api_base("https://api.github.com"),
+            "https://api.github.com"
+        );
+        assert_eq!(
+            normalize_api_base("https://api.github.com/"),
+            "https://api.github.com"
+        );
+        assert_eq!(
+            normalize_api_base("https://api.acme.ghe.com"),
+            "https://api.acme.ghe.com"
+        );
+    }
+
+    #[test]
+    fn appends_api_v3_to_bare_enterprise_host() {
+        assert_eq!(
+            normalize_api_base("https://ghe.example.com"),
+            "https://ghe.example.com/api/v3"
+        );
+        assert_eq!(
+            normalize_api_base("https://ghe.example.com/"),
+            "https://ghe.example.com/api/v3"
+        );
+        assert_eq!(
+            normalize_api_base("https://ghe.example.com:8443"),
+            "https://ghe.example.com:8443/api/v3"
+        );
+    }
+
+    #[test]
+    fn keeps_explicit_paths_and_local_hosts() {
+        assert_eq!(
+            normalize_api_base("https://ghe.example.com/api/v3/"),
+            "https://ghe.example.com/api/v3"
+        );
+        assert_eq!(
+            normalize_api_base("https://proxy.example.com/github"),
+            "https://proxy.example.com/github"
+        );
+        assert_eq!(
+            normalize_api_base("http://127.0.0.1:8080"),
+            "http://127.0.0.1:8080"
+        );
+        assert_eq!(normalize_api_base("http://[::1]:8080"), "http://[::1]:8080");
+        assert_eq!(
+            normalize_api_base("http://localhost:3000"),
+            "http://localhost:3000"
+        );
+    }
+
+    #[test]
+    fn builds_repo_urls_without_double_prefix() {
+        for (base, expected) in [
+            ("https://api.github.com", "https://api.github.com/repos/o/r"),
+            (
+                "https://ghe.example.com",
+                "https://ghe.example.com/api/v3/repos/o/r",
+            ),
+            (
+                "https://ghe.example.com/api/v3",
+                "https://ghe.example.com/api/v3/repos/o/r",
+            ),
+        ] {
+            let client = GithubClient::new(base, "t").unwrap();
+            assert_eq!(format!("{}/repos/o/r", client.api_base()), expected);
+        }
+    }
+
+    #[test]
+    fn broader_scopes_cover_narrower_ones() {
+        assert!(scope_covers("repo", "repo"));
+        assert!(scope_covers("repo", "public_repo"));
+        assert!(!scope_covers("public_repo", "repo"));
+        assert!(scope_covers("admin:org", "read:org"));
+        assert!(!scope_covers("repo", "delete_repo"));
+    }
+
+    #[test]
+    fn token_kind_from_prefix() {
+        assert_eq!(
+            TokenKind::from_token("github_pat_11AB"),
+            TokenKind::FineGrained
+        );
+        assert_eq!(TokenKind::from_token("ghs_abc"), TokenKind::Installation);
+        assert_eq!(TokenKind::from_token("ghp_abc"), TokenKind::Classic);
+        assert_eq!(TokenKind::from_token("gho_abc"), TokenKind::Other);
+    }
+
+    #[tokio::test]
+    async fn run_bounded_respects_limit() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let in_flight = AtomicUsize::new(0);
+        let peak = AtomicUsize::new(0);
+        let mut results = Vec::new();
+        run_bounded(
+            0..12,
+            3,
+            |i| {
+                let (in_flight, peak) = (&in_flight, &peak);
+                async move {
+                    let now = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                    peak.fetch_max(now, Ordering::SeqCst);
+                    tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+                    in_flight.fetch_sub(1, Ordering::SeqCst);
+                    Ok(i)
+                }
+            },
+            |i| results.push(i),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(results.len(), 12);
+        assert_eq!(peak.load(Ordering::SeqCst), 3);
+    }
 }
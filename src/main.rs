@@ -1,97 +1,684 @@
+use std::collections::HashMap;
 use std::env;
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
 
 use anyhow::{anyhow, Context, Result};
-use clap::Parser;
-use github_client::RepoResponse;
-use tracing::{debug, info};
+use cli::{
+    ArchiveArgs, Cli, Command, CreateArgs, EnvArgs, GlobalArgs, ProtectArgs, ReviewerSpec, SeedArgs,
+};
+use config::{GitflowConfig, LabelsFile, Manifest, ManifestEntry};
+use github_client::{
+    BatchReport, ClientConfig, CopyOptions, CopyProgress, CopySelector, EnvironmentOptions,
+    EnvironmentReport, ExecutionMode, GithubClient, GithubClientError, ProvisionSummary,
+    PullRequestOutcome, ReconcileStatus, RepoFeatures, RepoResponse, RepoSettings, RepoSpec,
+    Reviewer, ReviewerType, Ruleset, WebhookConfig,
+};
+use tracing::{debug, info, warn};
 use tracing_subscriber::EnvFilter;
 
-#[derive(Parser, Debug)]
-#[command(
-    name = "github-client",
-    version,
-    about = "Create a repo from a GitHub template"
-)]
-struct Opts {
-    /// Repository name to create
-    #[arg(long, env = "REPO_NAME")]
-    repo_name: String,
+mod cli;
+mod config;
 
-    /// Repository description
-    #[arg(long, env = "REPO_DESC")]
-    repo_desc: String,
+#[tokio::main]
+async fn main() -> Result<()> {
+    let cli = Cli::parse_with_default(env::args_os()).unwrap_or_else(|err| err.exit());
+    let global = &cli.global;
 
-    /// Repository type: public | private
-    #[arg(long, env = "REPO_TYPE", value_parser = ["public", "private"])]
-    repo_type: String,
+    // Initialize logging with RUST_LOG, or the level -v/-q select; try_init
+    // leaves a subscriber that is already installed in place
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(
+            EnvFilter::try_from_default_env()
+                .unwrap_or_else(|_| EnvFilter::new(log_level(global.verbose, global.quiet))),
+        )
+        .with_target(false)
+        .compact()
+        .try_init();
 
-    /// Template repository in the form 'owner/repo'
-    #[arg(long, env = "TEMPLATE_NAME")]
-    template_name: String,
+    let (connect_secs, timeout_secs) = (global.connect_timeout_secs, global.timeout_secs);
+    let json_output = global.output == "json";
+    let result = run(cli).await.map_err(|err| {
+        if github_client::is_timeout(&err) {
+            err.context(format!(
+                "Timed out waiting for GitHub (connect {}s, request {}s). Raise --timeout-secs / GITHUB_TIMEOUT on slow links.",
+                connect_secs, timeout_secs
+            ))
+        } else {
+            err
+        }
+    });
+    match result {
+        Err(err) if json_output => {
+            let causes: Vec<String> = err.chain().skip(1).map(|c| c.to_string()).collect();
+            eprintln!(
+                "{}",
+                serde_json::json!({ "error": err.to_string(), "causes": causes })
+            );
+            std::process::exit(1);
+        }
+        other => other,
+    }
+}
 
-    /// Include all branches from template (true/false)
-    #[arg(long, env = "BRANCH", default_value_t = false)]
-    branch: bool,
+/// The log level when RUST_LOG is unset: `info`, raised to `debug` and
+/// `trace` by each `-v` and lowered to `warn` and `error` by each `-q`.
+fn log_level(verbose: u8, quiet: u8) -> &'static str {
+    const LEVELS: [&str; 5] = ["error", "warn", "info", "debug", "trace"];
+    let index = (2 + i32::from(verbose) - i32::from(quiet)).clamp(0, 4);
+    LEVELS[index as usize]
+}
 
-    /// GitHub API base URL, defaults to public GitHub
-    #[arg(long, env = "GITHUB_API_URL", default_value = "https://api.github.com")]
-    api_base: String,
+async fn run(cli: Cli) -> Result<()> {
+    let global = &cli.global;
+    let token = resolve_token(global.token.as_deref(), global.token_file.as_deref())?;
+    let client = build_client(global, &token)?;
+    // Fail before anything is created rather than with a 403 halfway through
+    if !global.skip_token_check {
+        let info = github_client::verify_token_with_client(&client)
+            .await
+            .context("Token pre-flight check failed; fix the token or pass --skip-token-check")?;
+        info!(
+            "Authenticated as {} ({:?} token, {}/{} API requests left)",
+            info.login.as_deref().unwrap_or("an app installation"),
+            info.kind,
+            info.rate_limit.remaining,
+            info.rate_limit.limit
+        );
+        if info.rate_limit.remaining == 0 {
+            return Err(GithubClientError::RateLimited {
+                reset_at: Some(info.rate_limit.reset),
+            }
+            .into());
+        }
+        if global.check_scopes {
+            info.require_scopes(&required_scopes(cli.command.as_ref()))?;
+        }
+    }
+    match cli.command {
+        Some(Command::Create(opts)) => create(global, &opts, &client).await?,
+        Some(Command::Protect(args)) => protect(global, &args, &client).await?,
+        Some(Command::Seed(args)) => seed(global, &args, &client).await?,
+        Some(Command::Env(args)) => environment(global, &args, &client).await?,
+        Some(Command::Archive(args)) => archive(global, &args, &client).await?,
+        None => unreachable!("parse_with_default always selects a subcommand"),
+    }
+    if global.dry_run {
+        print_output(global, &client.take_plan())?;
+    }
+    Ok(())
+}
 
-    /// GitHub token; falls back to GH_TOKEN if not set
-    #[arg(long, env = "GITHUB_TOKEN")]
-    token: Option<String>,
+/// The client every request of the run goes through, configured from the
+/// connection flags.
+fn build_client(global: &GlobalArgs, token: &str) -> Result<GithubClient> {
+    let config = ClientConfig {
+        pool_max_idle_per_host: global.pool_max_idle,
+        mode: if global.dry_run {
+            ExecutionMode::DryRun
+        } else {
+            ExecutionMode::Live
+        },
+        connect_timeout: Duration::from_secs(global.connect_timeout_secs),
+        timeout: Duration::from_secs(global.timeout_secs),
+        max_retries: global.max_retries,
+        ready_wait: Duration::from_secs(global.ready_wait_secs),
+        created_wait: Duration::from_secs(global.created_wait_secs),
+        branch_wait: Duration::from_secs(global.branch_wait_secs),
+        proxy: global.proxy.clone(),
+        no_proxy: global.no_proxy,
+        ca_bundle: global.ca_bundle.clone(),
+        danger_accept_invalid_certs: global.danger_accept_invalid_certs,
+        user_agent: global.user_agent.clone(),
+        api_version: global.api_version.clone(),
+        ..ClientConfig::default()
+    };
+    GithubClient::builder(&global.api_base, token)
+        .config(config)
+        .build()
+        .context("Failed to configure HTTP client")
+}
 
-    /// Apply branch protection to the default branch after creation
-    #[arg(long, env = "PROTECT_DEFAULT_BRANCH", default_value_t = true)]
-    protect_default_branch: bool,
+fn log_copy_progress(progress: CopyProgress) {
+    info!(
+        "[{}/{}] {:?} {}",
+        progress.files_done, progress.total_files, progress.action, progress.current_path
+    );
+}
 
-    /// Override source for seeding service-* scaffolding (default: <owner>/service-template)
-    #[arg(long, env = "SERVICE_TEMPLATE_REPO")]
-    service_template_repo: Option<String>,
+/// Classic token scopes the selected subcommand needs.
+fn required_scopes(command: Option<&Command>) -> Vec<&'static str> {
+    match command {
+        Some(Command::Create(opts)) => {
+            let private = |visibility: Option<&String>| {
+                visibility.or(opts.repo_type.as_ref()).map(String::as_str) == Some("private")
+            };
+            let any_private = match &opts.manifest {
+                Some(manifest) => manifest.0.iter().any(|e| private(e.visibility.as_ref())),
+                None => private(None),
+            };
+            let mut scopes = vec![if any_private { "repo" } else { "public_repo" }];
+            if opts.rollback_on_failure {
+                scopes.push("delete_repo");
+            }
+            scopes
+        }
+        _ => vec!["public_repo"],
+    }
 }
 
-#[tokio::main]
-async fn main() -> Result<()> {
-    // Initialize logging with RUST_LOG or default to info
-    let _ = tracing_subscriber::fmt()
-        .with_env_filter(
-            EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info")),
+/// Prints `value` as JSON or, with `--output text`, through its Display impl.
+fn print_output<T: serde::Serialize + std::fmt::Display>(
+    global: &GlobalArgs,
+    value: &T,
+) -> Result<()> {
+    match global.output.as_str() {
+        "text" => print!("{}", value),
+        _ => println!("{}", serde_json::to_string(value)?),
+    }
+    Ok(())
+}
+
+async fn protect(global: &GlobalArgs, args: &ProtectArgs, client: &GithubClient) -> Result<()> {
+    let contexts: Vec<&str> = args.contexts.iter().map(String::as_str).collect();
+    github_client::protect_branch_with_options_with_client(
+        client,
+        &args.repo,
+        &args.branch,
+        &args.protection.options(&contexts),
+    )
+    .await
+    .with_context(|| {
+        format!(
+            "Failed to protect branch '{}' on '{}'",
+            args.branch, args.repo
         )
-        .with_target(false)
-        .compact()
-        .try_init();
+    })?;
+    if !global.dry_run {
+        match global.output.as_str() {
+            "text" => println!("Protected {}:{}", args.repo, args.branch),
+            _ => println!(
+                "{}",
+                serde_json::json!({ "repo": args.repo, "branch": args.branch, "contexts": contexts })
+            ),
+        }
+    }
+    Ok(())
+}
+
+async fn archive(global: &GlobalArgs, args: &ArchiveArgs, client: &GithubClient) -> Result<()> {
+    let archived = !args.unarchive;
+    github_client::set_repo_archived_with_client(client, &args.repo, archived)
+        .await
+        .with_context(|| {
+            format!(
+                "Failed to {} '{}'",
+                if archived { "archive" } else { "unarchive" },
+                args.repo
+            )
+        })?;
+    if !global.dry_run {
+        match global.output.as_str() {
+            "text" => println!(
+                "{} {}",
+                if archived { "Archived" } else { "Unarchived" },
+                args.repo
+            ),
+            _ => println!(
+                "{}",
+                serde_json::json!({ "repo": args.repo, "archived": archived })
+            ),
+        }
+    }
+    Ok(())
+}
+
+/// Source/destination pairs for seeding: the `--map`/`--seed-map` pairs,
+/// then each default prefix copied as-is unless a pair already seeds into it.
+fn seed_mappings<'a>(
+    prefixes: &[&'a str],
+    maps: &'a [(String, String)],
+) -> Vec<(&'a str, &'a str)> {
+    let mut mappings: Vec<(&str, &str)> = maps
+        .iter()
+        .map(|(src, dst)| (src.as_str(), dst.as_str()))
+        .collect();
+    for prefix in prefixes {
+        if !maps.iter().any(|(_, dst)| dst == prefix) {
+            mappings.push((prefix, prefix));
+        }
+    }
+    mappings
+}
+
+async fn seed(global: &GlobalArgs, args: &SeedArgs, client: &GithubClient) -> Result<()> {
+    let prefixes: Vec<&str> = args.prefixes.iter().map(String::as_str).collect();
+    let excludes: Vec<&str> = args.exclude.iter().map(String::as_str).collect();
+    let selector = CopySelector::mapped(&seed_mappings(&prefixes, &args.map))
+        .excluding(&excludes)
+        .context("Invalid --exclude")?;
+    let branch = match &args.branch {
+        Some(branch) => branch.clone(),
+        None => github_client::get_repo_default_branch_with_client(client, &args.target)
+            .await
+            .with_context(|| format!("Failed to read default branch of '{}'", args.target))?,
+    };
+    let report = github_client::copy_dirs_from_repo_with_progress_with_client(
+        client,
+        &args.source,
+        &args.target,
+        &branch,
+        &selector,
+        &CopyOptions {
+            concurrency: args.concurrency,
+            skip_symlinks: args.skip_symlinks,
+            large_file_threshold: args.large_file_threshold,
+            large_files: args.large_files,
+            ..CopyOptions::default()
+        },
+        log_copy_progress,
+    )
+    .await
+    .with_context(|| format!("Failed to seed '{}' from '{}'", args.target, args.source))?;
+    if !global.dry_run {
+        match global.output.as_str() {
+            "text" => println!(
+                "Seeded {} files into {}:{} ({} skipped, {} failed, {} too large)",
+                report.seeded.len(),
+                args.target,
+                branch,
+                report.skipped.len(),
+                report.failed.len(),
+                report.too_large.len()
+            ),
+            _ => println!("{}", serde_json::to_string(&report)?),
+        }
+    }
+    Ok(())
+}
+
+async fn environment(global: &GlobalArgs, args: &EnvArgs, client: &GithubClient) -> Result<()> {
+    let patterns: Vec<&str> = args.patterns.iter().map(String::as_str).collect();
+    let report = github_client::ensure_environment_with_client(
+        client,
+        &args.repo,
+        &args.name,
+        &EnvironmentOptions {
+            allowed_patterns: &patterns,
+            prune: args.prune,
+            ..Default::default()
+        },
+    )
+    .await
+    .with_context(|| format!("Failed to configure environment '{}'", args.name))?;
+    if !global.dry_run {
+        match global.output.as_str() {
+            "text" => println!(
+                "Environment {} on {} ({} branch policies added, {} existing, {} removed)",
+                report.name,
+                args.repo,
+                report.branch_policies_added.len(),
+                report.branch_policies_existing.len(),
+                report.branch_policies_removed.len()
+            ),
+            _ => println!("{}", serde_json::to_string(&report)?),
+        }
+    }
+    Ok(())
+}
+
+async fn create(global: &GlobalArgs, opts: &CreateArgs, client: &GithubClient) -> Result<()> {
+    if let Some(manifest) = &opts.manifest {
+        if opts.repo_name.is_some() {
+            warn!("--repo-name is ignored with --manifest");
+        }
+        let report = provision_batch(global, opts, manifest, client).await?;
+        if !global.dry_run {
+            print_output(global, &report)?;
+        }
+        let failed = report.failed();
+        if !failed.is_empty() {
+            return Err(anyhow!(
+                "{} of {} repositories failed: {}",
+                failed.len(),
+                report.repos.len(),
+                failed.join(", ")
+            ));
+        }
+        return Ok(());
+    }
+
+    let spec = repo_spec(opts)?;
+    let summary = create_one(global, opts, &spec, client).await?;
+    // In a dry run nothing was created; run() reports the plan instead
+    if !global.dry_run {
+        print_output(global, &summary)?;
+    }
+    Ok(())
+}
 
-    let opts = Opts::parse();
+/// The repository the single-repository flags describe.
+fn repo_spec(opts: &CreateArgs) -> Result<RepoSpec> {
+    let required = |value: &Option<String>, flag: &str| {
+        value
+            .clone()
+            .ok_or_else(|| anyhow!("{} is required without --manifest", flag))
+    };
+    Ok(RepoSpec {
+        name: required(&opts.repo_name, "--repo-name")?,
+        description: required(&opts.repo_desc, "--repo-desc")?,
+        private: required(&opts.repo_type, "--repo-type")? == "private",
+        template: required(&opts.template_name, "--template-name")?,
+        ..repo_spec_defaults(opts)
+    })
+}
+
+/// The repository a manifest entry describes, and the options to provision
+/// it with: the create flags plus the entry's topics and variables.
+fn manifest_spec(opts: &CreateArgs, entry: &ManifestEntry) -> Result<(RepoSpec, CreateArgs)> {
+    let visibility = entry.visibility.as_ref().or(opts.repo_type.as_ref());
+    let template = entry
+        .template
+        .clone()
+        .or_else(|| opts.template_name.clone())
+        .ok_or_else(|| {
+            anyhow!(
+                "Manifest entry '{}' has no template and --template-name is not set",
+                entry.name
+            )
+        })?;
+    let spec = RepoSpec {
+        name: entry.name.clone(),
+        description: entry
+            .description
+            .clone()
+            .or_else(|| opts.repo_desc.clone())
+            .unwrap_or_default(),
+        private: visibility.map(|v| v == "private").ok_or_else(|| {
+            anyhow!(
+                "Manifest entry '{}' has no visibility and --repo-type is not set",
+                entry.name
+            )
+        })?,
+        template,
+        ..repo_spec_defaults(opts)
+    };
+    let mut entry_opts = opts.clone();
+    entry_opts.manifest = None;
+    entry_opts.topics.extend(entry.topics.iter().cloned());
+    entry_opts
+        .repo_vars
+        .extend(entry.vars.iter().map(|(k, v)| (k.clone(), v.clone())));
+    Ok((spec, entry_opts))
+}
+
+/// The parts of a [`RepoSpec`] that come from flags shared by every repository.
+fn repo_spec_defaults(opts: &CreateArgs) -> RepoSpec {
+    RepoSpec {
+        owner: opts.owner.clone(),
+        include_all_branches: opts.branch,
+        fail_if_exists: opts.fail_if_exists,
+        adopt_existing: opts.adopt_existing,
+        ..RepoSpec::default()
+    }
+}
+
+/// Provisions every manifest entry exactly like a single-repository run,
+/// sharing the token check and HTTP client. Entries are checked up front; a
+/// failing entry is recorded and the rest still run unless `--fail-fast`.
+async fn provision_batch(
+    global: &GlobalArgs,
+    opts: &CreateArgs,
+    manifest: &Manifest,
+    client: &GithubClient,
+) -> Result<BatchReport> {
+    let entries = manifest
+        .0
+        .iter()
+        .map(|entry| manifest_spec(opts, entry))
+        .collect::<Result<Vec<_>>>()?;
+    let mut report = BatchReport::default();
+    for (i, (spec, entry_opts)) in entries.iter().enumerate() {
+        info!("[{}/{}] Provisioning '{}'", i + 1, entries.len(), spec.name);
+        match create_one(global, entry_opts, spec, client).await {
+            Ok(summary) => report.add_success(&spec.name, summary),
+            Err(err) if opts.fail_fast => {
+                return Err(err.context(format!("Failed to provision '{}'", spec.name)))
+            }
+            Err(err) => {
+                warn!("Failed to provision '{}': {:#}", spec.name, err);
+                report.add_failure(&spec.name, format!("{:#}", err));
+            }
+        }
+    }
+    Ok(report)
+}
+
+/// Creates (or with `--reconcile`, converges) one repository and configures it.
+async fn create_one(
+    global: &GlobalArgs,
+    opts: &CreateArgs,
+    spec: &RepoSpec,
+    client: &GithubClient,
+) -> Result<ProvisionSummary> {
     info!("Starting GitHub template generation");
     debug!(
-        "Parsed options: repo_name='{}', repo_type='{}', template='{}', branch={}",
-        opts.repo_name, opts.repo_type, opts.template_name, opts.branch
+        "Parsed options: repo_name='{}', private={}, template='{}', branch={}",
+        spec.name, spec.private, spec.template, opts.branch
     );
 
-    let token = resolve_token(opts.token.as_deref())?;
-    let is_private = opts.repo_type.eq_ignore_ascii_case("private");
-
-    let repo: RepoResponse = github_client::generate_from_template(
-        &opts.api_base,
-        &token,
-        &opts.template_name,
-        &opts.repo_name,
-        &opts.repo_desc,
-        is_private,
-        opts.branch,
+    // Reject bad seeding patterns before anything is created
+    let excludes: Vec<&str> = opts.seed_exclude.iter().map(String::as_str).collect();
+    let seed_selector = CopySelector::mapped(&seed_mappings(
+        &["terraform/", "helm/", "kustomize/"],
+        &opts.seed_map,
+    ))
+    .excluding(&excludes)
+    .context("Invalid --seed-exclude")?;
+
+    if opts.reconcile {
+        return reconcile(global, opts, spec, client, &seed_selector).await;
+    }
+
+    let generated = github_client::provision_repo_with_client(client, spec)
+        .await
+        .context("Failed to create the repository from the template")?;
+    let created = generated.was_created();
+    if !created {
+        info!(
+            "Adopted existing repository '{}'; re-applying configuration",
+            generated.repo().full_name
+        );
+    }
+    let repo: RepoResponse = generated.into_repo();
+
+    // Everything after this point configures the repo; with --rollback-on-failure
+    // a failure deletes it so the run can be retried. Adopted repos are never deleted.
+    let mut summary = ProvisionSummary::new(&repo, created);
+    github_client::rollback_on_failure_with_client(
+        client,
+        &repo.full_name,
+        opts.rollback_on_failure && created,
+        provision(
+            global,
+            opts,
+            spec,
+            client,
+            &repo,
+            &seed_selector,
+            &mut summary,
+        ),
     )
-    .await
-    .context("Failed to call GitHub API")?;
+    .await?;
 
-    println!(
-        "{{\"full_name\":\"{}\",\"html_url\":\"{}\",\"default_branch\":\"{}\"}}",
-        repo.full_name, repo.html_url, repo.default_branch
-    );
-    info!("Repository created: {}", repo.full_name);
+    info!("Repository provisioned: {}", repo.full_name);
+    Ok(summary)
+}
+
+/// An environment counts as changed when branch policies were added or
+/// removed; its protection rules are re-applied either way.
+fn environment_status(report: &EnvironmentReport) -> ReconcileStatus {
+    if report.branch_policies_added.is_empty() && report.branch_policies_removed.is_empty() {
+        ReconcileStatus::Compliant
+    } else {
+        ReconcileStatus::Changed
+    }
+}
+
+/// Looks up the ids GitHub needs for environment reviewers.
+async fn resolve_reviewers(client: &GithubClient, specs: &[ReviewerSpec]) -> Result<Vec<Reviewer>> {
+    let mut reviewers = Vec::new();
+    for spec in specs {
+        let reviewer = match spec {
+            ReviewerSpec::User(login) => Reviewer {
+                reviewer_type: ReviewerType::User,
+                id: github_client::get_user_id_with_client(client, login)
+                    .await
+                    .with_context(|| format!("Failed to resolve reviewer '{}'", login))?,
+            },
+            ReviewerSpec::Team { org, slug } => Reviewer {
+                reviewer_type: ReviewerType::Team,
+                id: github_client::get_team_id_with_client(client, org, slug)
+                    .await
+                    .with_context(|| {
+                        format!("Failed to resolve reviewer team '{}/{}'", org, slug)
+                    })?,
+            },
+        };
+        reviewers.push(reviewer);
+    }
+    Ok(reviewers)
+}
+
+/// Converges an existing repository to the desired configuration without
+/// generating it, reporting per step whether anything had to change.
+async fn reconcile(
+    global: &GlobalArgs,
+    opts: &CreateArgs,
+    spec: &RepoSpec,
+    client: &GithubClient,
+    selector: &CopySelector,
+) -> Result<ProvisionSummary> {
+    let owner = github_client::resolve_owner_with_client(client, spec.owner.as_deref())
+        .await
+        .context("Failed to resolve the authenticated user")?;
+    let full_name = format!("{}/{}", owner, spec.name);
+    let repo = match github_client::get_repo_with_client(client, &full_name).await {
+        Ok(repo) => repo,
+        Err(err) if err.is_not_found() => {
+            return Err(anyhow!(
+                "Repository '{}' does not exist; run without --reconcile to create it",
+                full_name
+            ))
+        }
+        Err(err) => return Err(err).context("Failed to read the repository to reconcile"),
+    };
+    info!("Reconciling existing repository '{}'", repo.full_name);
+
+    let mut summary = ProvisionSummary::new(&repo, false);
+    provision(global, opts, spec, client, &repo, selector, &mut summary).await?;
+
+    if summary.is_compliant() {
+        info!("Repository '{}' was already compliant", repo.full_name);
+    } else {
+        info!("Repository '{}' reconciled", repo.full_name);
+    }
+    Ok(summary)
+}
+
+/// Post-creation steps: access grants, settings, seeding, protection,
+/// environments and secrets.
+///
+/// With `--reconcile` the steps that can tell whether anything changed record
+/// that in `summary.reconciled`.
+async fn provision(
+    global: &GlobalArgs,
+    opts: &CreateArgs,
+    spec: &RepoSpec,
+    client: &GithubClient,
+    repo: &RepoResponse,
+    selector: &CopySelector,
+    summary: &mut ProvisionSummary,
+) -> Result<()> {
+    // Grant access before anything else so the owning team can start working
+    let (repo_owner, _) = repo.full_name.split_once('/').unwrap_or_default();
+    for (org, slug, permission) in &opts.teams {
+        let org = org.as_deref().unwrap_or(repo_owner);
+        github_client::add_team_permission_with_client(
+            client,
+            org,
+            slug,
+            &repo.full_name,
+            *permission,
+        )
+        .await
+        .with_context(|| format!("Failed to grant team {}/{} access", org, slug))?;
+    }
+    for (login, permission) in &opts.collaborators {
+        let invitation = github_client::add_collaborator_with_client(
+            client,
+            &repo.full_name,
+            login,
+            *permission,
+        )
+        .await
+        .with_context(|| format!("Failed to add collaborator '{}'", login))?;
+        if let Some(id) = invitation {
+            summary
+                .invitations
+                .push(format!("{} (invitation {})", login, id));
+        }
+    }
+
+    // Governance tooling flags repositories whose custom properties are unset
+    github_client::set_custom_properties_with_client(client, &repo.full_name, &opts.properties)
+        .await
+        .context("Failed to set custom properties")?;
+
+    // Apply merge settings and features requested via flags in one PATCH
+    let mut settings = RepoSettings::default();
+    if opts.squash_only {
+        settings.allow_squash_merge = Some(true);
+        settings.allow_merge_commit = Some(false);
+        settings.allow_rebase_merge = Some(false);
+    }
+    if opts.delete_branch_on_merge {
+        settings.delete_branch_on_merge = Some(true);
+    }
+    let mut features = RepoFeatures::default();
+    for (name, enabled) in &opts.features {
+        let field = match name.as_str() {
+            "issues" => &mut features.has_issues,
+            "wiki" => &mut features.has_wiki,
+            "projects" => &mut features.has_projects,
+            _ => &mut features.has_discussions,
+        };
+        *field = Some(*enabled);
+    }
+    settings.apply_features(&features);
+    if !settings.is_empty() {
+        github_client::update_repo_settings_with_client(client, &repo.full_name, &settings)
+            .await
+            .context("Failed to update repository settings")?;
+    }
+
+    if !opts.topics.is_empty() {
+        let topics: Vec<&str> = opts.topics.iter().map(String::as_str).collect();
+        github_client::set_repo_topics_with_client(client, &repo.full_name, &topics)
+            .await
+            .context("Failed to set repository topics")?;
+    }
+
+    if let Some(LabelsFile(labels)) = &opts.labels_file {
+        github_client::ensure_labels_with_client(client, &repo.full_name, labels)
+            .await
+            .context("Failed to create labels from the labels file")?;
+    }
 
     // Detect service-* template name
-    let is_service = opts
-        .template_name
+    let is_service = spec
+        .template
         .rsplit('/')
         .next()
         .map(|n| n.starts_with("service-"))
@@ -113,39 +700,172 @@ async fn main() -> Result<()> {
             "Seeding 'terraform/', 'helm/', and 'kustomize/' from {} into {}",
             source_full_name, repo.full_name
         );
-        github_client::copy_dirs_from_repo(
-            &opts.api_base,
-            &token,
-            &source_full_name,
+        let mut template_vars = HashMap::from([("SERVICE_NAME".to_string(), spec.name.clone())]);
+        template_vars.extend(opts.template_vars.iter().cloned());
+        let template_paths = if opts.template_paths.is_empty() {
+            None
+        } else {
+            let patterns: Vec<&str> = opts.template_paths.iter().map(String::as_str).collect();
+            Some(CopySelector::globs(&patterns).context("Invalid --template-path")?)
+        };
+        let copy_options = CopyOptions {
+            author_name: opts.commit_author.clone(),
+            author_email: opts.commit_email.clone(),
+            commit_message_template: opts.commit_message.clone(),
+            template_vars,
+            template_paths,
+            strict_template_vars: opts.strict_template_vars,
+            concurrency: opts.seed_concurrency,
+            skip_symlinks: opts.skip_symlinks,
+            large_file_threshold: opts.large_file_threshold,
+            large_files: opts.large_files,
+        };
+        let report = if opts.seed_single_commit {
+            github_client::seed_dirs_as_single_commit_with_client(
+                client,
+                &source_full_name,
+                &repo.full_name,
+                &repo.default_branch,
+                selector,
+                &copy_options,
+            )
+            .await
+            .context("Failed to seed content from service-template")?
+        } else {
+            github_client::copy_dirs_from_repo_with_progress_with_client(
+                client,
+                &source_full_name,
+                &repo.full_name,
+                &repo.default_branch,
+                selector,
+                &copy_options,
+                log_copy_progress,
+            )
+            .await
+            .context("Failed to seed content from service-template")?
+        };
+        if opts.reconcile {
+            let status = if report.seeded.is_empty() {
+                ReconcileStatus::Compliant
+            } else {
+                ReconcileStatus::Changed
+            };
+            summary.add_reconcile_step("seeded files", status);
+        }
+        summary.add_copy_report(report);
+
+        // Repositories generated from a template do not inherit its labels
+        if !opts.no_label_sync {
+            github_client::sync_labels_from_repo_with_client(
+                client,
+                &spec.template,
+                &repo.full_name,
+                false,
+            )
+            .await
+            .context("Failed to sync labels from the template repository")?;
+        }
+
+        // Catch files that failed to seed instead of silently continuing
+        if opts.verify_seed && !global.dry_run {
+            let expected =
+                github_client::list_source_paths_with_client(client, &source_full_name, selector)
+                    .await
+                    .context("Failed to list expected seed paths")?;
+            let expected: Vec<&str> = expected.iter().map(String::as_str).collect();
+            let missing = github_client::verify_seed_with_client(
+                client,
+                &repo.full_name,
+                &repo.default_branch,
+                &expected,
+            )
+            .await
+            .context("Failed to verify seeded content")?;
+            if !missing.is_empty() {
+                return Err(anyhow!(
+                    "Seeding incomplete; missing {} file(s): {}",
+                    missing.len(),
+                    missing.join(", ")
+                ));
+            }
+            info!("Verified {} seeded file(s)", expected.len());
+        }
+    }
+
+    // Git does not track empty directories; create placeholders on request
+    if !opts.seed_empty_dirs.is_empty() {
+        let dirs: Vec<&str> = opts.seed_empty_dirs.iter().map(String::as_str).collect();
+        github_client::seed_empty_dirs_with_client(
+            client,
             &repo.full_name,
             &repo.default_branch,
-            &["terraform/", "helm/", "kustomize/"],
+            &dirs,
         )
         .await
-        .context("Failed to seed content from service-template")?;
+        .context("Failed to seed empty directories")?;
     }
 
     // Optionally apply branch protection to the default branch
+    // Explicit --required-checks values win; otherwise service-* repos keep the
+    // 'branch-policy' check and other repos get no status checks
+    let required_checks: Vec<&str> = match &opts.required_checks {
+        Some(checks) => checks
+            .iter()
+            .map(|c| c.trim())
+            .filter(|c| !c.is_empty())
+            .collect(),
+        None if is_service => vec!["branch-policy"],
+        None => Vec::new(),
+    };
+    let protection = opts.protection.options(&required_checks);
+    if opts.use_rulesets && opts.protection.no_enforce_admins {
+        warn!("--no-enforce-admins has no effect with --use-rulesets");
+    }
+
     if opts.protect_default_branch {
-        if is_service {
-            github_client::protect_branch_with_checks(
-                &opts.api_base,
-                &token,
+        if opts.use_rulesets {
+            github_client::create_ruleset_with_client(
+                client,
+                &repo.full_name,
+                &Ruleset::with_options(
+                    "default-branch-protection",
+                    &["~DEFAULT_BRANCH"],
+                    &protection,
+                ),
+            )
+            .await
+            .context("Failed to apply default branch ruleset")?;
+            summary
+                .protections
+                .push(format!("{} (ruleset)", repo.default_branch));
+        } else if opts.reconcile {
+            let status = github_client::ensure_branch_protection_with_client(
+                client,
                 &repo.full_name,
                 &repo.default_branch,
-                &["branch-policy"],
+                &protection,
             )
             .await
-            .context("Failed to apply branch protection with checks")?;
+            .context("Failed to reconcile branch protection")?;
+            summary.add_reconcile_step(
+                format!("branch protection on '{}'", repo.default_branch),
+                status,
+            );
+            summary
+                .protections
+                .push(format!("{} (branch protection)", repo.default_branch));
         } else {
-            github_client::protect_branch(
-                &opts.api_base,
-                &token,
+            github_client::protect_branch_with_options_with_client(
+                client,
                 &repo.full_name,
                 &repo.default_branch,
+                &protection,
             )
             .await
             .context("Failed to apply branch protection")?;
+            summary
+                .protections
+                .push(format!("{} (branch protection)", repo.default_branch));
         }
         info!(
             "Branch protection applied on '{}:{}'",
@@ -155,77 +875,576 @@ async fn main() -> Result<()> {
         info!("Skipping branch protection as requested");
     }
 
-    // Auto-setup gitflow and environments for service-* templates
-    if opts
-        .template_name
-        .rsplit('/')
-        .next()
-        .map(|n| n.starts_with("service-"))
-        .unwrap_or(false)
-    {
-        info!("Detected service-* template; setting up gitflow branches and environments");
-        // Create 'dev' branch from default
-        github_client::create_branch_from_base(
-            &opts.api_base,
-            &token,
-            &repo.full_name,
-            &repo.default_branch,
-            "dev",
-        )
-        .await
-        .context("Failed to create 'dev' branch")?;
+    // Gitflow branches and environments from --config, or the service-* defaults
+    let gitflow = match &opts.config {
+        Some(config) => Some(config.clone()),
+        None if is_service => {
+            info!("Detected service-* template; setting up gitflow branches and environments");
+            Some(GitflowConfig::service_default())
+        }
+        None => None,
+    };
+    if let Some(gitflow) = &gitflow {
+        for branch in &gitflow.branches {
+            let name = branch.name.as_str();
+            // Create the branch unless an adopted or reconciled repository has it already
+            let status = github_client::ensure_branch_with_client(
+                client,
+                &repo.full_name,
+                branch.from.as_deref().unwrap_or(&repo.default_branch),
+                name,
+            )
+            .await
+            .with_context(|| format!("Failed to create '{}' branch", name))?;
+            if status == ReconcileStatus::Changed {
+                summary.branches_created.push(name.to_string());
+            }
+            if opts.reconcile {
+                summary.add_reconcile_step(format!("branch '{}'", name), status);
+            }
+
+            if !(branch.protect && opts.protect_default_branch) {
+                continue;
+            }
+            let branch_protection = match &branch.required_checks {
+                Some(checks) => {
+                    let checks: Vec<&str> = checks.iter().map(String::as_str).collect();
+                    opts.protection.options(&checks)
+                }
+                None => protection.clone(),
+            };
+            if opts.use_rulesets {
+                github_client::create_ruleset_with_client(
+                    client,
+                    &repo.full_name,
+                    &Ruleset::with_options(
+                        &format!("{}-branch-protection", name),
+                        &[&format!("refs/heads/{}", name)],
+                        &branch_protection,
+                    ),
+                )
+                .await
+                .with_context(|| format!("Failed to apply '{}' branch ruleset", name))?;
+                summary.protections.push(format!("{} (ruleset)", name));
+            } else if opts.reconcile {
+                let status = github_client::ensure_branch_protection_with_client(
+                    client,
+                    &repo.full_name,
+                    name,
+                    &branch_protection,
+                )
+                .await
+                .with_context(|| format!("Failed to reconcile '{}' branch protection", name))?;
+                summary.add_reconcile_step(format!("branch protection on '{}'", name), status);
+                summary
+                    .protections
+                    .push(format!("{} (branch protection)", name));
+            } else {
+                github_client::protect_branch_with_options_with_client(
+                    client,
+                    &repo.full_name,
+                    name,
+                    &branch_protection,
+                )
+                .await
+                .with_context(|| format!("Failed to protect '{}' branch", name))?;
+                summary
+                    .protections
+                    .push(format!("{} (branch protection)", name));
+            }
+        }
 
-        // Protect 'dev' branch as well
-        if opts.protect_default_branch {
-            github_client::protect_branch_with_checks(
-                &opts.api_base,
-                &token,
+        for environment in &gitflow.environments {
+            let name = environment.name.as_str();
+            // --release-reviewer and --release-wait-timer fill in for the
+            // 'release' environment when the config does not set them
+            let is_release = name == "release";
+            let specs = if environment.reviewers.is_empty() && is_release {
+                &opts.release_reviewers
+            } else {
+                &environment.reviewers
+            };
+            let reviewers = resolve_reviewers(client, specs).await?;
+            let wait_timer = environment.wait_timer.or(if is_release {
+                opts.release_wait_timer
+            } else {
+                None
+            });
+            let patterns: Vec<&str> = environment.branches.iter().map(String::as_str).collect();
+            let report = github_client::ensure_environment_with_client(
+                client,
                 &repo.full_name,
-                "dev",
-                &["branch-policy"],
+                name,
+                &EnvironmentOptions {
+                    allowed_patterns: &patterns,
+                    wait_timer,
+                    reviewers: &reviewers,
+                    prevent_self_review: None,
+                    prune: false,
+                },
             )
             .await
-            .context("Failed to protect 'dev' branch")?;
+            .with_context(|| format!("Failed to configure '{}' environment", name))?;
+            if opts.reconcile {
+                summary.add_reconcile_step(
+                    format!("environment '{}'", name),
+                    environment_status(&report),
+                );
+            }
+            summary.environments.push(report);
+        }
+
+        info!("Gitflow branches and environments configured");
+    }
+
+    // Switch the default branch last so the steps above still target the template's
+    if let Some(branch) = &opts.default_branch {
+        github_client::set_default_branch_with_client(client, &repo.full_name, branch)
+            .await
+            .with_context(|| format!("Failed to set the default branch to '{}'", branch))?;
+        summary.default_branch = branch.clone();
+    }
+
+    if opts.open_release_pr {
+        match gitflow.as_ref().and_then(|g| g.branches.first()) {
+            Some(head) => {
+                let pr = github_client::create_pull_request_with_client(client, &repo.full_name,
+                    &head.name,
+                    &summary.default_branch,
+                    &format!("Release: {} → {}", head.name, summary.default_branch),
+                    &format!(
+                        "Running release pull request. Merge it to release what has landed on `{}`.",
+                        head.name
+                    ),
+                    true,
+                )
+                .await;
+                match pr {
+                    Ok(PullRequestOutcome::Opened(pr)) => summary.release_pr = Some(pr.html_url),
+                    Ok(PullRequestOutcome::NothingToCompare) => info!(
+                        "No commits between '{}' and '{}' yet; not opening a release pull request",
+                        summary.default_branch, head.name
+                    ),
+                    Ok(PullRequestOutcome::Planned) => {}
+                    // Re-runs against an adopted repository find the PR already open
+                    Err(e) if e.is_already_exists() => {
+                        info!("A release pull request from '{}' is already open", head.name)
+                    }
+                    Err(e) => return Err(e).context("Failed to open the release pull request"),
+                }
+            }
+            None => warn!("--open-release-pr needs a gitflow branch (service-* template or --config); skipping"),
         }
+    }
 
-        // Environments
-        github_client::ensure_environment_with_branches(
-            &opts.api_base,
-            &token,
+    // Protect further branches by name or pattern, e.g. existing release/* branches
+    let patterns: Vec<&str> = opts
+        .protect_branches
+        .iter()
+        .map(|p| p.trim())
+        .filter(|p| !p.is_empty())
+        .collect();
+    if !patterns.is_empty() && opts.use_rulesets {
+        // Rulesets match by pattern, so they also cover branches created later
+        let include: Vec<String> = patterns
+            .iter()
+            .map(|p| format!("refs/heads/{}", p))
+            .collect();
+        let include: Vec<&str> = include.iter().map(String::as_str).collect();
+        github_client::create_ruleset_with_client(
+            client,
             &repo.full_name,
-            "dev",
-            &["dev", "feature/*", "hotfix/*"],
+            &Ruleset::with_options("protected-branches", &include, &protection),
         )
         .await
-        .context("Failed to configure 'dev' environment")?;
+        .context("Failed to apply --protect-branches ruleset")?;
+        summary
+            .protections
+            .push(format!("{} (ruleset)", patterns.join(", ")));
+    } else if !patterns.is_empty() {
+        let report = github_client::protect_branches_matching_with_client(
+            client,
+            &repo.full_name,
+            &patterns,
+            &protection,
+        )
+        .await
+        .context("Failed to protect --protect-branches branches")?;
+        summary.protections.extend(
+            report
+                .protected
+                .iter()
+                .map(|b| format!("{} (branch protection)", b)),
+        );
+        if !report.failed.is_empty() {
+            return Err(anyhow!(
+                "Failed to protect {} branch(es): {}",
+                report.failed.len(),
+                report.failed.join(", ")
+            ));
+        }
+    }
+
+    // Actions secrets and variables needed before the first workflow run
+    for name in &opts.repo_secrets {
+        let value = env::var(name).map_err(|_| {
+            anyhow!(
+                "Secret '{}' requested but env var '{}' is not set",
+                name,
+                name
+            )
+        })?;
+        github_client::set_repo_secret_with_client(client, &repo.full_name, name, &value)
+            .await
+            .with_context(|| format!("Failed to set repository secret '{}'", name))?;
+    }
+
+    for (name, value) in &opts.repo_vars {
+        github_client::set_repo_variable_with_client(client, &repo.full_name, name, value)
+            .await
+            .with_context(|| format!("Failed to set repository variable '{}'", name))?;
+    }
 
-        github_client::ensure_environment_with_branches(
-            &opts.api_base,
-            &token,
+    // Populate environment variables and secrets
+    for (env_name, name, value) in &opts.env_vars {
+        github_client::set_environment_variable_with_client(
+            client,
             &repo.full_name,
-            "release",
-            &["release/*", "main"],
+            env_name,
+            name,
+            value,
         )
         .await
-        .context("Failed to configure 'release' environment")?;
+        .with_context(|| format!("Failed to set variable '{}' in '{}'", name, env_name))?;
+    }
+    for (env_name, name) in &opts.env_secrets {
+        let value = env::var(name).map_err(|_| {
+            anyhow!(
+                "Secret '{}' requested but env var '{}' is not set",
+                name,
+                name
+            )
+        })?;
+        github_client::set_environment_secret_with_client(
+            client,
+            &repo.full_name,
+            env_name,
+            name,
+            &value,
+        )
+        .await
+        .with_context(|| format!("Failed to set secret '{}' in '{}'", name, env_name))?;
+    }
 
-        info!("Gitflow branches and environments configured");
+    if let Some(url) = &opts.webhook_url {
+        let webhook = WebhookConfig {
+            events: opts
+                .webhook_events
+                .iter()
+                .map(|e| e.trim().to_string())
+                .filter(|e| !e.is_empty())
+                .collect(),
+            secret: opts.webhook_secret.clone(),
+            ..WebhookConfig::new(url)
+        };
+        github_client::create_webhook_with_client(client, &repo.full_name, &webhook)
+            .await
+            .with_context(|| format!("Failed to configure webhook '{}'", url))?;
     }
     Ok(())
 }
 
-fn resolve_token(primary: Option<&str>) -> Result<String> {
-    if let Some(t) = primary {
+/// Picks the token from, in order: `--token`, `--token-file` /
+/// `GITHUB_TOKEN_FILE`, `GITHUB_TOKEN`, `GH_TOKEN`.
+fn resolve_token(explicit: Option<&str>, token_file: Option<&Path>) -> Result<String> {
+    resolve_token_with(explicit, token_file, |name| env::var(name).ok())
+}
+
+fn resolve_token_with(
+    explicit: Option<&str>,
+    token_file: Option<&Path>,
+    env_var: impl Fn(&str) -> Option<String>,
+) -> Result<String> {
+    if let Some(t) = explicit {
         if !t.is_empty() {
             return Ok(t.to_string());
         }
     }
-    if let Ok(t) = env::var("GH_TOKEN") {
-        if !t.is_empty() {
-            return Ok(t);
+    if let Some(path) = token_file {
+        return read_token_file(path);
+    }
+    for name in ["GITHUB_TOKEN", "GH_TOKEN"] {
+        if let Some(t) = env_var(name) {
+            if !t.is_empty() {
+                return Ok(t);
+            }
         }
     }
     Err(anyhow!(
-        "Missing token. Provide via --token, GITHUB_TOKEN, or GH_TOKEN env var"
+        "Missing token. Provide via --token, --token-file, GITHUB_TOKEN_FILE, GITHUB_TOKEN, or GH_TOKEN"
     ))
 }
+
+/// Reads a token file, ignoring surrounding whitespace such as the trailing
+/// newline `echo` adds.
+fn read_token_file(path: &Path) -> Result<String> {
+    let contents = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read token file '{}'", path.display()))?;
+    let token = contents.trim();
+    if token.is_empty() {
+        return Err(anyhow!("Token file '{}' is empty", path.display()));
+    }
+    Ok(token.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn verbosity_flags_pick_the_log_level() {
+        assert_eq!(log_level(0, 0), "info");
+        assert_eq!(log_level(1, 0), "debug");
+        assert_eq!(log_level(2, 0), "trace");
+        assert_eq!(log_level(5, 0), "trace");
+        assert_eq!(log_level(0, 1), "warn");
+        assert_eq!(log_level(0, 2), "error");
+        assert_eq!(log_level(0, 5), "error");
+    }
+
+    fn token_file(name: &str, contents: &str) -> std::path::PathBuf {
+        let path = env::temp_dir().join(format!("github-client-{}-{}", std::process::id(), name));
+        fs::write(&path, contents).unwrap();
+        path
+    }
+
+    fn env_with(vars: &[(&str, &str)]) -> impl Fn(&str) -> Option<String> {
+        let vars: HashMap<String, String> = vars
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        move |name| vars.get(name).cloned()
+    }
+
+    #[test]
+    fn seed_mappings_replace_defaults_with_the_same_destination() {
+        let maps = vec![(
+            "modules/bootstrap/terraform/".to_string(),
+            "terraform/".to_string(),
+        )];
+        assert_eq!(
+            seed_mappings(&["terraform/", "helm/"], &maps),
+            vec![
+                ("modules/bootstrap/terraform/", "terraform/"),
+                ("helm/", "helm/")
+            ]
+        );
+        assert_eq!(seed_mappings(&["helm/"], &[]), vec![("helm/", "helm/")]);
+    }
+
+    #[test]
+    fn token_file_is_trimmed() {
+        let printf = token_file("printf", "ghp_printf");
+        let echo = token_file("echo", "ghp_echo\n");
+        let crlf = token_file("crlf", "ghp_crlf\r\n");
+        assert_eq!(read_token_file(&printf).unwrap(), "ghp_printf");
+        assert_eq!(read_token_file(&echo).unwrap(), "ghp_echo");
+        assert_eq!(read_token_file(&crlf).unwrap(), "ghp_crlf");
+    }
+
+    #[test]
+    fn missing_or_empty_token_file_is_an_error() {
+        let empty = token_file("empty", "\n");
+        let err = read_token_file(&empty).unwrap_err();
+        assert!(err.to_string().contains("is empty"));
+
+        let missing = env::temp_dir().join("github-client-does-not-exist");
+        let err = resolve_token_with(None, Some(&missing), env_with(&[])).unwrap_err();
+        assert!(err.to_string().contains("Failed to read token file"));
+    }
+
+    #[test]
+    fn token_sources_have_a_fixed_precedence() {
+        let file = token_file("precedence", "from-file\n");
+        let env = [("GITHUB_TOKEN", "from-github"), ("GH_TOKEN", "from-gh")];
+
+        let token = resolve_token_with(Some("explicit"), Some(&file), env_with(&env)).unwrap();
+        assert_eq!(token, "explicit");
+        let token = resolve_token_with(None, Some(&file), env_with(&env)).unwrap();
+        assert_eq!(token, "from-file");
+        let token = resolve_token_with(Some(""), None, env_with(&env)).unwrap();
+        assert_eq!(token, "from-github");
+        let token = resolve_token_with(None, None, env_with(&env[1..])).unwrap();
+        assert_eq!(token, "from-gh");
+        assert!(resolve_token_with(None, None, env_with(&[])).is_err());
+    }
+
+    #[tokio::test]
+    async fn batch_keeps_going_after_a_failed_entry() {
+        let server = httpmock::MockServer::start();
+        server.mock(|when, then| {
+            when.method(httpmock::Method::GET).path("/repos/org/tmpl");
+            then.status(200).json_body(serde_json::json!({
+                "default_branch": "main",
+                "is_template": true,
+                "private": false,
+                "archived": false
+            }));
+        });
+        for name in ["svc-a", "svc-c"] {
+            server.mock(|when, then| {
+                when.method(httpmock::Method::POST)
+                    .path("/repos/org/tmpl/generate")
+                    .json_body_includes(format!(r#"{{ "name": "{}" }}"#, name));
+                then.status(201).json_body(serde_json::json!({
+                    "full_name": format!("me/{}", name),
+                    "html_url": format!("https://github.com/me/{}", name),
+                    "default_branch": "main"
+                }));
+            });
+            server.mock(|when, then| {
+                when.method(httpmock::Method::GET)
+                    .path(format!("/repos/me/{}", name));
+                then.status(200).json_body(serde_json::json!({
+                    "full_name": format!("me/{}", name),
+                    "html_url": format!("https://github.com/me/{}", name),
+                    "default_branch": "main"
+                }));
+            });
+            server.mock(|when, then| {
+                when.method(httpmock::Method::GET)
+                    .path(format!("/repos/me/{}/git/ref/heads/main", name));
+                then.status(200)
+                    .json_body(serde_json::json!({ "object": { "sha": "abc" } }));
+            });
+            server.mock(|when, then| {
+                when.method(httpmock::Method::GET)
+                    .path(format!("/repos/me/{}/git/trees/main", name));
+                then.status(200)
+                    .json_body(serde_json::json!({ "tree": [{ "path": "README.md" }] }));
+            });
+            server.mock(|when, then| {
+                when.method(httpmock::Method::PATCH)
+                    .path(format!("/repos/me/{}", name));
+                then.status(200).json_body(serde_json::json!({}));
+            });
+            server.mock(|when, then| {
+                when.method(httpmock::Method::GET)
+                    .path(format!("/repos/me/{}/branches/main", name));
+                then.status(200);
+            });
+            server.mock(|when, then| {
+                when.method(httpmock::Method::PUT)
+                    .path(format!("/repos/me/{}/branches/main/protection", name));
+                then.status(200);
+            });
+        }
+        let rejected = server.mock(|when, then| {
+            when.method(httpmock::Method::POST)
+                .path("/repos/org/tmpl/generate")
+                .json_body_includes(r#"{ "name": "svc-b" }"#);
+            then.status(422).json_body(serde_json::json!({
+                "message": "Repository creation failed.",
+                "errors": [{ "resource": "Repository", "code": "custom", "field": "name",
+                             "message": "name is reserved" }]
+            }));
+        });
+
+        let manifest = env::temp_dir().join(format!("batch-{}.yaml", std::process::id()));
+        fs::write(
+            &manifest,
+            "- name: svc-a\n  visibility: public\n- name: svc-b\n  visibility: public\n- name: svc-c\n  visibility: private\n",
+        )
+        .unwrap();
+        let api_base = server.base_url();
+        let cli = Cli::parse_with_default([
+            "github-client",
+            "--api-base",
+            api_base.as_str(),
+            "create",
+            "--manifest",
+            manifest.to_str().unwrap(),
+            "--template-name",
+            "org/tmpl",
+            "--adopt-existing",
+        ])
+        .unwrap();
+        let _ = fs::remove_file(&manifest);
+        let Some(Command::Create(opts)) = &cli.command else {
+            panic!("expected create, got {:?}", cli.command);
+        };
+
+        let report = provision_batch(
+            &cli.global,
+            opts,
+            opts.manifest.as_ref().unwrap(),
+            &build_client(&cli.global, "testtoken").unwrap(),
+        )
+        .await
+        .unwrap();
+        rejected.assert();
+        let names: Vec<&str> = report.repos.iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(names, ["svc-a", "svc-b", "svc-c"]);
+        assert_eq!(report.failed(), ["svc-b"]);
+        assert!(report.repos[1]
+            .error
+            .as_deref()
+            .unwrap()
+            .contains("check repo_name and inputs"));
+        assert_eq!(
+            report.repos[2]
+                .summary
+                .as_ref()
+                .map(|s| s.full_name.as_str()),
+            Some("me/svc-c")
+        );
+    }
+
+    #[tokio::test]
+    async fn protection_flags_reach_the_protection_request() {
+        let server = httpmock::MockServer::start();
+        let _branch = server.mock(|when, then| {
+            when.method(httpmock::Method::GET)
+                .path("/repos/me/svc/branches/main");
+            then.status(200);
+        });
+        let put = server.mock(|when, then| {
+            when.method(httpmock::Method::PUT)
+                .path("/repos/me/svc/branches/main/protection")
+                .json_body_includes(
+                    r#"{
+                        "enforce_admins": false,
+                        "required_pull_request_reviews": { "required_approving_review_count": 2 },
+                        "required_status_checks": { "strict": true, "contexts": ["ci/build"] }
+                    }"#,
+                );
+            then.status(200);
+        });
+
+        let api_base = server.base_url();
+        let cli = Cli::parse_with_default([
+            "github-client",
+            "--api-base",
+            api_base.as_str(),
+            "protect",
+            "me/svc",
+            "--contexts",
+            "ci/build",
+            "--required-approvals",
+            "2",
+            "--no-enforce-admins",
+        ])
+        .unwrap();
+        let Some(Command::Protect(args)) = &cli.command else {
+            panic!("expected protect, got {:?}", cli.command);
+        };
+        protect(
+            &cli.global,
+            args,
+            &build_client(&cli.global, "testtoken").unwrap(),
+        )
+        .await
+        .unwrap();
+        put.assert();
+    }
+}
@@ -0,0 +1,59 @@
+use std::future::Future;
+use std::time::Duration;
+
+use rand::Rng;
+use tokio::time::sleep;
+use tracing::warn;
+
+use crate::error::{GithubClientError, Result};
+
+/// Longest single wait between attempts, before jitter.
+const MAX_DELAY: Duration = Duration::from_secs(30);
+
+/// Runs `op` up to `max_attempts` times (at least once), waiting
+/// `base_delay * 2^n` plus random jitter between attempts.
+///
+/// Only transient failures are retried: connection errors, timeouts and 5xx
+/// responses. Anything else, e.g. 403, 404 or 422, is returned immediately.
+/// Rate limits are not retried here either; their reset is usually minutes
+/// away. `op` receives the attempt number, starting at 1.
+pub async fn retry<T, F, Fut>(max_attempts: u32, base_delay: Duration, mut op: F) -> Result<T>
+where
+    F: FnMut(u32) -> Fut,
+    Fut: Future<Output = Result<T>>,
+{
+    let max_attempts = max_attempts.max(1);
+    let mut attempt = 1;
+    loop {
+        match op(attempt).await {
+            Err(err) if attempt < max_attempts && is_transient(&err) => {
+                let delay = backoff(base_delay, attempt);
+                warn!(
+                    "Attempt {}/{} failed ({}); retrying in {:?}",
+                    attempt, max_attempts, err, delay
+                );
+                sleep(delay).await;
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+pub(crate) fn is_transient(err: &GithubClientError) -> bool {
+    match err {
+        GithubClientError::Transport(e) => !e.is_decode() && !e.is_builder(),
+        GithubClientError::Unexpected { status, .. } => *status >= 500,
+        _ => false,
+    }
+}
+
+/// Exponential delay for the wait after `attempt`, plus up to 50% jitter so
+/// concurrent runs do not retry in lockstep.
+fn backoff(base_delay: Duration, attempt: u32) -> Duration {
+    let exp = base_delay
+        .saturating_mul(2u32.saturating_pow(attempt.saturating_sub(1)))
+        .min(MAX_DELAY);
+    let jitter_ms = rand::rng().random_range(0..=exp.as_millis() as u64 / 2);
+    exp + Duration::from_millis(jitter_ms)
+}
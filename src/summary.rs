@@ -0,0 +1,190 @@
+use std::fmt;
+
+use serde::Serialize;
+
+use crate::{CopyReport, EnvironmentReport, ReconcileStatus, RepoResponse};
+
+/// One step of a `--reconcile` run and whether it had to change anything.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct ReconcileStep {
+    /// What was checked, e.g. `branch protection on main`.
+    pub step: String,
+    pub status: ReconcileStatus,
+}
+
+/// What a provisioning run did, printed by the CLI as JSON or text.
+#[derive(Serialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct ProvisionSummary {
+    pub full_name: String,
+    pub html_url: String,
+    pub default_branch: String,
+    /// `false` when an existing repository was adopted.
+    pub created: bool,
+    pub branches_created: Vec<String>,
+    pub environments: Vec<EnvironmentReport>,
+    pub files_seeded: Vec<String>,
+    pub files_skipped: Vec<String>,
+    pub files_failed: Vec<String>,
+    /// Left out for exceeding the large file threshold with `--large-files skip`.
+    pub files_too_large: Vec<String>,
+    /// One entry per protected branch, e.g. `main (ruleset)`.
+    pub protections: Vec<String>,
+    /// Collaborators who were sent an invitation, e.g. `bot (invitation 42)`.
+    pub invitations: Vec<String>,
+    /// URL of the pull request opened with `--open-release-pr`.
+    pub release_pr: Option<String>,
+    /// Per-step outcome of a `--reconcile` run; empty otherwise.
+    pub reconciled: Vec<ReconcileStep>,
+}
+
+impl ProvisionSummary {
+    pub fn new(repo: &RepoResponse, created: bool) -> Self {
+        Self {
+            full_name: repo.full_name.clone(),
+            html_url: repo.html_url.clone(),
+            default_branch: repo.default_branch.clone(),
+            created,
+            ..Default::default()
+        }
+    }
+
+    pub fn add_reconcile_step(&mut self, step: impl Into<String>, status: ReconcileStatus) {
+        self.reconciled.push(ReconcileStep {
+            step: step.into(),
+            status,
+        });
+    }
+
+    /// Whether every reconciled step was already compliant.
+    pub fn is_compliant(&self) -> bool {
+        self.reconciled
+            .iter()
+            .all(|s| s.status == ReconcileStatus::Compliant)
+    }
+
+    pub fn add_copy_report(&mut self, report: CopyReport) {
+        self.files_seeded.extend(report.seeded);
+        self.files_skipped.extend(report.skipped);
+        self.files_failed.extend(report.failed);
+        self.files_too_large.extend(report.too_large);
+    }
+}
+
+impl fmt::Display for ProvisionSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let verb = if self.created {
+            "Created"
+        } else if !self.reconciled.is_empty() {
+            "Reconciled"
+        } else {
+            "Adopted"
+        };
+        writeln!(f, "{} repository {}", verb, self.full_name)?;
+        writeln!(f, "  URL:            {}", self.html_url)?;
+        writeln!(f, "  Default branch: {}", self.default_branch)?;
+        if !self.branches_created.is_empty() {
+            writeln!(f, "  Branches:       {}", self.branches_created.join(", "))?;
+        }
+        for env in &self.environments {
+            writeln!(
+                f,
+                "  Environment:    {} ({} branch policies added, {} existing)",
+                env.name,
+                env.branch_policies_added.len(),
+                env.branch_policies_existing.len()
+            )?;
+        }
+        if !self.files_seeded.is_empty() || !self.files_skipped.is_empty() {
+            writeln!(
+                f,
+                "  Files seeded:   {} ({} skipped, {} failed, {} too large)",
+                self.files_seeded.len(),
+                self.files_skipped.len(),
+                self.files_failed.len(),
+                self.files_too_large.len()
+            )?;
+        }
+        if !self.protections.is_empty() {
+            writeln!(f, "  Protection:     {}", self.protections.join(", "))?;
+        }
+        if !self.invitations.is_empty() {
+            writeln!(f, "  Invited:        {}", self.invitations.join(", "))?;
+        }
+        if let Some(url) = &self.release_pr {
+            writeln!(f, "  Release PR:     {}", url)?;
+        }
+        for step in &self.reconciled {
+            let status = match step.status {
+                ReconcileStatus::Compliant => "compliant",
+                ReconcileStatus::Changed => "changed",
+            };
+            writeln!(f, "  Reconciled:     {} ({})", step.step, status)?;
+        }
+        Ok(())
+    }
+}
+
+/// Outcome of one repository in a `--manifest` run: its summary when it was
+/// provisioned, the error otherwise.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct BatchEntry {
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub summary: Option<ProvisionSummary>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// What a `--manifest` run did, one entry per repository in manifest order.
+#[derive(Serialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct BatchReport {
+    pub repos: Vec<BatchEntry>,
+}
+
+impl BatchReport {
+    pub fn add_success(&mut self, name: impl Into<String>, summary: ProvisionSummary) {
+        self.repos.push(BatchEntry {
+            name: name.into(),
+            summary: Some(summary),
+            error: None,
+        });
+    }
+
+    pub fn add_failure(&mut self, name: impl Into<String>, error: impl Into<String>) {
+        self.repos.push(BatchEntry {
+            name: name.into(),
+            summary: None,
+            error: Some(error.into()),
+        });
+    }
+
+    /// Names of the repositories that failed.
+    pub fn failed(&self) -> Vec<&str> {
+        self.repos
+            .iter()
+            .filter(|r| r.error.is_some())
+            .map(|r| r.name.as_str())
+            .collect()
+    }
+}
+
+impl fmt::Display for BatchReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Provisioned {} of {} repositories",
+            self.repos.len() - self.failed().len(),
+            self.repos.len()
+        )?;
+        for entry in &self.repos {
+            match (&entry.summary, &entry.error) {
+                (_, Some(error)) => writeln!(f, "  FAILED {}: {}", entry.name, error)?,
+                (Some(summary), None) => {
+                    writeln!(f, "  ok     {} ({})", entry.name, summary.html_url)?
+                }
+                (None, None) => writeln!(f, "  ok     {}", entry.name)?,
+            }
+        }
+        Ok(())
+    }
+}
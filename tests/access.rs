@@ -0,0 +1,144 @@
+use github_client::{
+    add_collaborator, add_team_permission, add_team_to_repo, GithubClientError, Permission,
+};
+use httpmock::prelude::*;
+
+#[tokio::test]
+async fn grants_team_permission() {
+    let server = MockServer::start();
+    let m = server.mock(|when, then| {
+        when.method(PUT)
+            .path("/orgs/acme/teams/platform/repos/acme/new-repo")
+            .header("authorization", "Bearer testtoken")
+            .json_body(serde_json::json!({ "permission": "maintain" }));
+        then.status(204);
+    });
+
+    add_team_permission(
+        &server.base_url(),
+        "testtoken",
+        "acme",
+        "platform",
+        "acme/new-repo",
+        Permission::Maintain,
+    )
+    .await
+    .unwrap();
+    m.assert();
+}
+
+#[tokio::test]
+async fn add_team_to_repo_accepts_created() {
+    let server = MockServer::start();
+    let m = server.mock(|when, then| {
+        when.method(PUT)
+            .path("/orgs/acme/teams/reviewers/repos/acme/new-repo")
+            .json_body(serde_json::json!({ "permission": "push" }));
+        then.status(201);
+    });
+
+    add_team_to_repo(
+        &server.base_url(),
+        "testtoken",
+        "acme",
+        "reviewers",
+        "acme/new-repo",
+        Permission::Push,
+    )
+    .await
+    .unwrap();
+    m.assert();
+}
+
+#[tokio::test]
+async fn team_permission_reports_unknown_team() {
+    let server = MockServer::start();
+    let _m = server.mock(|when, then| {
+        when.method(PUT)
+            .path("/orgs/acme/teams/missing/repos/acme/new-repo");
+        then.status(404).body("{\"message\":\"Not Found\"}");
+    });
+
+    let res = add_team_permission(
+        &server.base_url(),
+        "testtoken",
+        "acme",
+        "missing",
+        "acme/new-repo",
+        Permission::Push,
+    )
+    .await;
+    assert!(matches!(res, Err(GithubClientError::NotFound { .. })));
+    assert_eq!(
+        res.unwrap_err().to_string(),
+        "Not found: team 'acme/missing' (check the slug, and that the token can see the team and 'acme/new-repo')"
+    );
+}
+
+#[tokio::test]
+async fn invites_collaborator_and_returns_invitation_id() {
+    let server = MockServer::start();
+    let m = server.mock(|when, then| {
+        when.method(PUT)
+            .path("/repos/acme/new-repo/collaborators/deploy-bot")
+            .json_body(serde_json::json!({ "permission": "push" }));
+        then.status(201)
+            .header("content-type", "application/json")
+            .json_body(serde_json::json!({ "id": 42, "permissions": "write" }));
+    });
+
+    let invitation = add_collaborator(
+        &server.base_url(),
+        "testtoken",
+        "acme/new-repo",
+        "deploy-bot",
+        Permission::Push,
+    )
+    .await
+    .unwrap();
+    assert_eq!(invitation, Some(42));
+    m.assert();
+}
+
+#[tokio::test]
+async fn existing_collaborator_is_success_without_body() {
+    let server = MockServer::start();
+    let m = server.mock(|when, then| {
+        when.method(PUT)
+            .path("/repos/acme/new-repo/collaborators/alice");
+        then.status(204);
+    });
+
+    let invitation = add_collaborator(
+        &server.base_url(),
+        "testtoken",
+        "acme/new-repo",
+        "alice",
+        Permission::Admin,
+    )
+    .await
+    .unwrap();
+    assert_eq!(invitation, None);
+    m.assert();
+}
+
+#[tokio::test]
+async fn collaborator_name_stays_in_its_path_segment() {
+    let server = MockServer::start();
+    let m = server.mock(|when, then| {
+        when.method(PUT)
+            .path("/repos/acme/new-repo/collaborators/..%2F..%2Fuser");
+        then.status(204);
+    });
+
+    add_collaborator(
+        &server.base_url(),
+        "testtoken",
+        "acme/new-repo",
+        "../../user",
+        Permission::Pull,
+    )
+    .await
+    .unwrap();
+    m.assert();
+}
@@ -1,4 +1,4 @@
-use github_client::protect_branch;
+use github_client::{protect_branch, GithubClientError};
 use httpmock::prelude::*;
 
 #[tokio::test]
@@ -123,6 +123,11 @@ async fn protect_branch_returns_error_on_forbidden() {
     let branch = "main";
     let token = "testtoken";
 
+    let _branch = server.mock(|when, then| {
+        when.method(GET)
+            .path(format!("/repos/{}/{}/branches/{}", owner, repo, branch));
+        then.status(200);
+    });
     let _m = server.mock(|when, then| {
         when.method(PUT).path(format!(
             "/repos/{}/{}/branches/{}/protection",
@@ -133,5 +138,5 @@ async fn protect_branch_returns_error_on_forbidden() {
 
     let api_base = server.base_url();
     let res = protect_branch(&api_base, token, &format!("{}/{}", owner, repo), branch).await;
-    assert!(res.is_err());
+    assert!(matches!(res, Err(GithubClientError::Forbidden { .. })));
 }
@@ -0,0 +1,31 @@
+use std::time::Duration;
+
+use github_client::{protect_branch_with_client, GithubClient, GithubClientError};
+use httpmock::prelude::*;
+
+#[tokio::test]
+async fn protect_branch_waits_for_configured_duration() {
+    let server = MockServer::start();
+    let client = GithubClient::builder(&server.base_url(), "testtoken")
+        .branch_wait(Duration::from_millis(500))
+        .build()
+        .unwrap();
+    let branch = server.mock(|when, then| {
+        when.method(GET).path("/repos/me/new-repo/branches/main");
+        then.status(404).body("{\"message\":\"Branch not found\"}");
+    });
+    let protection = server.mock(|when, then| {
+        when.method(PUT)
+            .path("/repos/me/new-repo/branches/main/protection");
+        then.status(200);
+    });
+
+    let err = protect_branch_with_client(&client, "me/new-repo", "main")
+        .await
+        .unwrap_err();
+
+    assert!(matches!(err, GithubClientError::Timeout { .. }));
+    assert!(err.to_string().contains("500ms"), "{}", err);
+    assert!(branch.calls() >= 2);
+    protection.assert_calls(0);
+}
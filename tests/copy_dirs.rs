@@ -0,0 +1,1031 @@
+use std::collections::HashMap;
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use github_client::{
+    copy_dirs_from_repo, copy_dirs_from_repo_with_progress, CopyAction, CopyOptions, CopyProgress,
+    CopyReport, CopySelector, GithubClientError, LargeFilePolicy,
+};
+use httpmock::prelude::*;
+
+#[tokio::test]
+async fn seeds_files_with_custom_author_and_message() {
+    let server = MockServer::start();
+    let token = "testtoken";
+
+    let _src_repo = server.mock(|when, then| {
+        when.method(GET).path("/repos/org/service-template");
+        then.status(200)
+            .json_body_obj(&serde_json::json!({ "default_branch": "main" }));
+    });
+    let _src_tree = server.mock(|when, then| {
+        when.method(GET)
+            .path("/repos/org/service-template/git/trees/main")
+            .query_param("recursive", "1");
+        then.status(200).json_body_obj(&serde_json::json!({
+            "tree": [{ "path": "terraform/main.tf", "type": "blob", "sha": "s1" }]
+        }));
+    });
+    let _blob = server.mock(|when, then| {
+        when.method(GET)
+            .path("/repos/org/service-template/git/blobs/s1");
+        then.status(200)
+            .json_body_obj(&serde_json::json!({ "content": "dGY=\n", "encoding": "base64" }));
+    });
+    let _exists = server.mock(|when, then| {
+        when.method(GET)
+            .path("/repos/org/new-svc/contents/terraform/main.tf");
+        then.status(404);
+    });
+    let put = server.mock(|when, then| {
+        when.method(PUT)
+            .path("/repos/org/new-svc/contents/terraform/main.tf")
+            .json_body_obj(&serde_json::json!({
+                "message": "chore(seed): terraform/main.tf",
+                "content": "dGY=",
+                "branch": "main",
+                "author": { "name": "Provisioning Bot", "email": "bot@example.com" },
+                "committer": { "name": "Provisioning Bot", "email": "bot@example.com" }
+            }));
+        then.status(201);
+    });
+
+    let options = CopyOptions {
+        author_name: Some("Provisioning Bot".to_string()),
+        author_email: Some("bot@example.com".to_string()),
+        commit_message_template: Some("chore(seed): {path}".to_string()),
+        ..Default::default()
+    };
+    let api_base = server.base_url();
+    copy_dirs_from_repo(
+        &api_base,
+        token,
+        "org/service-template",
+        "org/new-svc",
+        "main",
+        &CopySelector::prefixes(&["terraform/"]),
+        &options,
+    )
+    .await
+    .unwrap();
+    put.assert();
+}
+
+#[tokio::test]
+async fn rejects_author_name_without_email() {
+    let options = CopyOptions {
+        author_name: Some("Provisioning Bot".to_string()),
+        ..Default::default()
+    };
+    let res = copy_dirs_from_repo(
+        "http://127.0.0.1:9",
+        "testtoken",
+        "org/service-template",
+        "org/new-svc",
+        "main",
+        &CopySelector::prefixes(&["terraform/"]),
+        &options,
+    )
+    .await;
+    assert!(matches!(res, Err(GithubClientError::InvalidInput(_))));
+}
+
+/// Mocks a source repo with one blob at `path` and an empty target; returns
+/// the mock for the PUT that uploads `expected` (base64).
+fn mock_single_file<'a>(
+    server: &'a MockServer,
+    path: &str,
+    source_b64: &str,
+    expected_b64: &str,
+) -> httpmock::Mock<'a> {
+    server.mock(|when, then| {
+        when.method(GET).path("/repos/org/service-template");
+        then.status(200)
+            .json_body_obj(&serde_json::json!({ "default_branch": "main" }));
+    });
+    server.mock(|when, then| {
+        when.method(GET)
+            .path("/repos/org/service-template/git/trees/main")
+            .query_param("recursive", "1");
+        then.status(200).json_body_obj(&serde_json::json!({
+            "tree": [{ "path": path, "type": "blob", "sha": "s1" }]
+        }));
+    });
+    server.mock(|when, then| {
+        when.method(GET)
+            .path("/repos/org/service-template/git/blobs/s1");
+        then.status(200)
+            .json_body_obj(&serde_json::json!({ "content": source_b64, "encoding": "base64" }));
+    });
+    server.mock(|when, then| {
+        when.method(GET)
+            .path(format!("/repos/org/new-svc/contents/{}", path));
+        then.status(404);
+    });
+    server.mock(|when, then| {
+        when.method(PUT)
+            .path(format!("/repos/org/new-svc/contents/{}", path))
+            .json_body_includes(serde_json::json!({ "content": expected_b64 }).to_string());
+        then.status(201);
+    })
+}
+
+fn template_options(strict: bool) -> CopyOptions {
+    CopyOptions {
+        template_vars: HashMap::from([
+            ("SERVICE_NAME".to_string(), "billing".to_string()),
+            ("TEAM".to_string(), "payments".to_string()),
+        ]),
+        strict_template_vars: strict,
+        ..Default::default()
+    }
+}
+
+async fn copy_helm(
+    server: &MockServer,
+    options: &CopyOptions,
+) -> Result<CopyReport, GithubClientError> {
+    copy_dirs_from_repo(
+        &server.base_url(),
+        "testtoken",
+        "org/service-template",
+        "org/new-svc",
+        "main",
+        &CopySelector::prefixes(&["helm/"]),
+        options,
+    )
+    .await
+}
+
+#[tokio::test]
+async fn substitutes_placeholders_in_text_files() {
+    let server = MockServer::start();
+    let source = "name: {{SERVICE_NAME}}\nteam: {{TEAM}}\nimage: {{ .Values.image }}\n";
+    let expected = "name: billing\nteam: payments\nimage: {{ .Values.image }}\n";
+    let put = mock_single_file(
+        &server,
+        "helm/values.yaml",
+        &BASE64.encode(source),
+        &BASE64.encode(expected),
+    );
+
+    let report = copy_helm(&server, &template_options(false)).await.unwrap();
+    assert_eq!(report.seeded, vec!["helm/values.yaml".to_string()]);
+    put.assert();
+}
+
+#[tokio::test]
+async fn strict_failure_writes_nothing_and_existing_files_are_not_rendered() {
+    let server = MockServer::start();
+    server.mock(|when, then| {
+        when.method(GET).path("/repos/org/service-template");
+        then.status(200)
+            .json_body_obj(&serde_json::json!({ "default_branch": "main" }));
+    });
+    server.mock(|when, then| {
+        when.method(GET)
+            .path("/repos/org/service-template/git/trees/main")
+            .query_param("recursive", "1");
+        then.status(200).json_body_obj(&serde_json::json!({
+            "tree": [
+                { "path": "helm/Chart.yaml", "type": "blob", "sha": "s1" },
+                { "path": "helm/values.yaml", "type": "blob", "sha": "s2" },
+                { "path": "helm/owners.yaml", "type": "blob", "sha": "s3" }
+            ]
+        }));
+    });
+    for (sha, content) in [
+        ("s1", "name: {{SERVICE_NAME}}\n"),
+        ("s2", "owner: {{OWNER}}\n"),
+    ] {
+        server.mock(|when, then| {
+            when.method(GET)
+                .path(format!("/repos/org/service-template/git/blobs/{}", sha));
+            then.status(200).json_body_obj(
+                &serde_json::json!({ "content": BASE64.encode(content), "encoding": "base64" }),
+            );
+        });
+    }
+    // Already seeded by an earlier run; it also lacks OWNER but is skipped
+    let existing_blob = server.mock(|when, then| {
+        when.method(GET)
+            .path("/repos/org/service-template/git/blobs/s3");
+        then.status(200).json_body_obj(
+            &serde_json::json!({ "content": BASE64.encode("{{OWNER}}"), "encoding": "base64" }),
+        );
+    });
+    server.mock(|when, then| {
+        when.method(GET)
+            .path("/repos/org/new-svc/contents/helm/owners.yaml");
+        then.status(200)
+            .json_body_obj(&serde_json::json!({ "sha": "x" }));
+    });
+    server.mock(|when, then| {
+        when.method(GET)
+            .path_includes("/repos/org/new-svc/contents/");
+        then.status(404);
+    });
+    let put = server.mock(|when, then| {
+        when.method(PUT);
+        then.status(201);
+    });
+
+    let err = copy_helm(&server, &template_options(true))
+        .await
+        .unwrap_err();
+    assert!(
+        err.to_string()
+            .contains("'helm/values.yaml' uses template variables with no value: OWNER"),
+        "{}",
+        err
+    );
+    put.assert_calls(0);
+    existing_blob.assert_calls(0);
+}
+
+#[tokio::test]
+async fn copies_binary_files_unchanged() {
+    let server = MockServer::start();
+    // Invalid UTF-8 that happens to contain a placeholder
+    let mut binary = vec![0xff, 0xfe, 0x00];
+    binary.extend_from_slice(b"{{SERVICE_NAME}}");
+    let encoded = BASE64.encode(&binary);
+    let put = mock_single_file(&server, "helm/logo.png", &encoded, &encoded);
+
+    copy_helm(&server, &template_options(true)).await.unwrap();
+    put.assert();
+}
+
+#[tokio::test]
+async fn missing_template_var_is_kept_unless_strict() {
+    let source = BASE64.encode("owner: {{OWNER}}\n");
+
+    let server = MockServer::start();
+    let put = mock_single_file(&server, "helm/values.yaml", &source, &source);
+    copy_helm(&server, &template_options(false)).await.unwrap();
+    put.assert();
+
+    let server = MockServer::start();
+    let put = mock_single_file(&server, "helm/values.yaml", &source, &source);
+    let res = copy_helm(&server, &template_options(true)).await;
+    match res {
+        Err(GithubClientError::InvalidInput(msg)) => assert!(msg.contains("OWNER"), "{}", msg),
+        other => panic!("expected InvalidInput, got {:?}", other),
+    }
+    put.assert_calls(0);
+}
+
+#[tokio::test]
+async fn seeds_many_files_concurrently_with_one_put_each() {
+    let server = MockServer::start();
+    let paths: Vec<String> = (0..20)
+        .map(|i| format!("helm/templates/f{:02}.yaml", i))
+        .collect();
+
+    server.mock(|when, then| {
+        when.method(GET).path("/repos/org/service-template");
+        then.status(200)
+            .json_body_obj(&serde_json::json!({ "default_branch": "main" }));
+    });
+    let tree: Vec<_> = paths
+        .iter()
+        .enumerate()
+        .map(|(i, p)| serde_json::json!({ "path": p, "type": "blob", "sha": format!("s{}", i) }))
+        .collect();
+    server.mock(|when, then| {
+        when.method(GET)
+            .path("/repos/org/service-template/git/trees/main")
+            .query_param("recursive", "1");
+        then.status(200)
+            .json_body_obj(&serde_json::json!({ "tree": tree }));
+    });
+    server.mock(|when, then| {
+        when.method(GET)
+            .path_includes("/repos/org/service-template/git/blobs/");
+        then.status(200)
+            .json_body_obj(&serde_json::json!({ "content": "eDogMQo=", "encoding": "base64" }));
+    });
+    server.mock(|when, then| {
+        when.method(GET)
+            .path_includes("/repos/org/new-svc/contents/");
+        then.status(404);
+    });
+    let puts: Vec<_> = paths
+        .iter()
+        .map(|p| {
+            server.mock(|when, then| {
+                when.method(PUT)
+                    .path(format!("/repos/org/new-svc/contents/{}", p));
+                then.status(201);
+            })
+        })
+        .collect();
+
+    let options = CopyOptions {
+        concurrency: Some(4),
+        ..Default::default()
+    };
+    let report = copy_dirs_from_repo(
+        &server.base_url(),
+        "testtoken",
+        "org/service-template",
+        "org/new-svc",
+        "main",
+        &CopySelector::prefixes(&["helm/"]),
+        &options,
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(report.seeded, paths);
+    assert!(report.failed.is_empty());
+    for put in &puts {
+        put.assert_calls(1);
+    }
+}
+
+#[tokio::test]
+async fn downloads_large_blobs_raw_when_content_is_omitted() {
+    let server = MockServer::start();
+    // Non-UTF-8 bytes over 1MB, as GitHub omits inline content for those
+    let large: Vec<u8> = (0..1_100_000u32).map(|i| (i % 251) as u8 | 0x80).collect();
+
+    server.mock(|when, then| {
+        when.method(GET).path("/repos/org/service-template");
+        then.status(200)
+            .json_body_obj(&serde_json::json!({ "default_branch": "main" }));
+    });
+    server.mock(|when, then| {
+        when.method(GET)
+            .path("/repos/org/service-template/git/trees/main")
+            .query_param("recursive", "1");
+        then.status(200).json_body_obj(&serde_json::json!({
+            "tree": [{ "path": "helm/chart.tgz", "type": "blob", "sha": "big" }]
+        }));
+    });
+    let raw = server.mock(|when, then| {
+        when.method(GET)
+            .path("/repos/org/service-template/git/blobs/big")
+            .header("accept", "application/vnd.github.raw");
+        then.status(200).body(large.clone());
+    });
+    let json = server.mock(|when, then| {
+        when.method(GET)
+            .path("/repos/org/service-template/git/blobs/big")
+            .header("accept", "application/vnd.github+json");
+        then.status(200).json_body_obj(&serde_json::json!({
+            "content": "",
+            "encoding": "none",
+            "size": large.len()
+        }));
+    });
+    server.mock(|when, then| {
+        when.method(GET)
+            .path("/repos/org/new-svc/contents/helm/chart.tgz");
+        then.status(404);
+    });
+    let put = server.mock(|when, then| {
+        when.method(PUT)
+            .path("/repos/org/new-svc/contents/helm/chart.tgz")
+            .json_body(serde_json::json!({
+                "message": "chore: seed helm/chart.tgz from service-template",
+                "content": BASE64.encode(&large),
+                "branch": "main"
+            }));
+        then.status(201);
+    });
+
+    let report = copy_dirs_from_repo(
+        &server.base_url(),
+        "testtoken",
+        "org/service-template",
+        "org/new-svc",
+        "main",
+        &CopySelector::prefixes(&["helm/"]),
+        &CopyOptions::default(),
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(report.seeded, vec!["helm/chart.tgz"]);
+    json.assert_calls(1);
+    raw.assert_calls(1);
+    put.assert_calls(1);
+}
+
+#[tokio::test]
+async fn reports_progress_once_per_file() {
+    let server = MockServer::start();
+    server.mock(|when, then| {
+        when.method(GET).path("/repos/org/service-template");
+        then.status(200)
+            .json_body_obj(&serde_json::json!({ "default_branch": "main" }));
+    });
+    server.mock(|when, then| {
+        when.method(GET)
+            .path("/repos/org/service-template/git/trees/main")
+            .query_param("recursive", "1");
+        then.status(200).json_body_obj(&serde_json::json!({
+            "tree": [
+                { "path": "helm/a.yaml", "type": "blob", "sha": "s1" },
+                { "path": "helm/b.yaml", "type": "blob", "sha": "s2" },
+                { "path": "helm/c.yaml", "type": "blob", "sha": "s3" },
+                { "path": "README.md", "type": "blob", "sha": "s4" }
+            ]
+        }));
+    });
+    server.mock(|when, then| {
+        when.method(GET)
+            .path_includes("/repos/org/service-template/git/blobs/");
+        then.status(200)
+            .json_body_obj(&serde_json::json!({ "content": "eDogMQo=", "encoding": "base64" }));
+    });
+    server.mock(|when, then| {
+        when.method(GET)
+            .path("/repos/org/new-svc/contents/helm/a.yaml");
+        then.status(200)
+            .json_body_obj(&serde_json::json!({ "sha": "x" }));
+    });
+    server.mock(|when, then| {
+        when.method(GET)
+            .path_includes("/repos/org/new-svc/contents/");
+        then.status(404);
+    });
+    server.mock(|when, then| {
+        when.method(PUT)
+            .path("/repos/org/new-svc/contents/helm/b.yaml");
+        then.status(201);
+    });
+    server.mock(|when, then| {
+        when.method(PUT)
+            .path("/repos/org/new-svc/contents/helm/c.yaml");
+        then.status(422).body("{\"message\":\"Invalid request\"}");
+    });
+
+    let mut events: Vec<CopyProgress> = Vec::new();
+    let report = copy_dirs_from_repo_with_progress(
+        &server.base_url(),
+        "testtoken",
+        "org/service-template",
+        "org/new-svc",
+        "main",
+        &CopySelector::prefixes(&["helm/"]),
+        &CopyOptions::default(),
+        |p| events.push(p),
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(events.len(), 3);
+    assert!(events.iter().all(|e| e.total_files == 3));
+    let mut done: Vec<usize> = events.iter().map(|e| e.files_done).collect();
+    done.sort();
+    assert_eq!(done, vec![1, 2, 3]);
+    events.sort_by(|a, b| a.current_path.cmp(&b.current_path));
+    let actions: Vec<(&str, CopyAction)> = events
+        .iter()
+        .map(|e| (e.current_path.as_str(), e.action))
+        .collect();
+    assert_eq!(
+        actions,
+        vec![
+            ("helm/a.yaml", CopyAction::Skipped),
+            ("helm/b.yaml", CopyAction::Created),
+            ("helm/c.yaml", CopyAction::Failed)
+        ]
+    );
+    assert_eq!(report.seeded, vec!["helm/b.yaml"]);
+    assert_eq!(report.skipped, vec!["helm/a.yaml"]);
+    assert_eq!(report.failed, vec!["helm/c.yaml"]);
+}
+
+/// Mocks a source tree with a regular file, an executable script, a symlink
+/// and a submodule, and a target branch where none of them exist yet.
+fn mock_tree_with_modes(server: &MockServer) {
+    server.mock(|when, then| {
+        when.method(GET).path("/repos/org/service-template");
+        then.status(200)
+            .json_body_obj(&serde_json::json!({ "default_branch": "main" }));
+    });
+    server.mock(|when, then| {
+        when.method(GET)
+            .path("/repos/org/service-template/git/trees/main")
+            .query_param("recursive", "1");
+        then.status(200).json_body_obj(&serde_json::json!({
+            "tree": [
+                { "path": "helm/values.yaml", "mode": "100644", "type": "blob", "sha": "s1" },
+                { "path": "helm/deploy.sh", "mode": "100755", "type": "blob", "sha": "s2" },
+                { "path": "helm/charts/app", "mode": "120000", "type": "blob", "sha": "s3" },
+                { "path": "helm/vendor", "mode": "160000", "type": "commit", "sha": "s4" }
+            ]
+        }));
+    });
+    for (sha, content) in [("s1", "dmFscw=="), ("s2", "c2g="), ("s3", "Li4vYXBw")] {
+        server.mock(|when, then| {
+            when.method(GET)
+                .path(format!("/repos/org/service-template/git/blobs/{}", sha));
+            then.status(200)
+                .json_body_obj(&serde_json::json!({ "content": content, "encoding": "base64" }));
+        });
+    }
+    server.mock(|when, then| {
+        when.method(GET)
+            .path_includes("/repos/org/new-svc/contents/");
+        then.status(404);
+    });
+}
+
+#[tokio::test]
+async fn keeps_executable_and_symlink_modes() {
+    let server = MockServer::start();
+    mock_tree_with_modes(&server);
+    let put_regular = server.mock(|when, then| {
+        when.method(PUT)
+            .path("/repos/org/new-svc/contents/helm/values.yaml");
+        then.status(201);
+    });
+    let script_blob = server.mock(|when, then| {
+        when.method(POST)
+            .path("/repos/org/new-svc/git/blobs")
+            .json_body_obj(&serde_json::json!({ "content": "c2g=", "encoding": "base64" }));
+        then.status(201)
+            .json_body_obj(&serde_json::json!({ "sha": "nb-script" }));
+    });
+    let link_blob = server.mock(|when, then| {
+        when.method(POST)
+            .path("/repos/org/new-svc/git/blobs")
+            .json_body_obj(&serde_json::json!({ "content": "Li4vYXBw", "encoding": "base64" }));
+        then.status(201)
+            .json_body_obj(&serde_json::json!({ "sha": "nb-link" }));
+    });
+    server.mock(|when, then| {
+        when.method(GET)
+            .path("/repos/org/new-svc/git/ref/heads/main");
+        then.status(200)
+            .json_body_obj(&serde_json::json!({ "object": { "sha": "head1" } }));
+    });
+    server.mock(|when, then| {
+        when.method(GET)
+            .path("/repos/org/new-svc/git/commits/head1");
+        then.status(200)
+            .json_body_obj(&serde_json::json!({ "tree": { "sha": "basetree" } }));
+    });
+    let create_tree = server.mock(|when, then| {
+        when.method(POST)
+            .path("/repos/org/new-svc/git/trees")
+            .json_body_obj(&serde_json::json!({
+                "base_tree": "basetree",
+                "tree": [
+                    { "path": "helm/deploy.sh", "mode": "100755", "type": "blob", "sha": "nb-script" },
+                    { "path": "helm/charts/app", "mode": "120000", "type": "blob", "sha": "nb-link" }
+                ]
+            }));
+        then.status(201)
+            .json_body_obj(&serde_json::json!({ "sha": "newtree" }));
+    });
+    let create_commit = server.mock(|when, then| {
+        when.method(POST)
+            .path("/repos/org/new-svc/git/commits")
+            .json_body_includes(r#"{ "tree": "newtree", "parents": ["head1"] }"#);
+        then.status(201)
+            .json_body_obj(&serde_json::json!({ "sha": "c1" }));
+    });
+    let update_ref = server.mock(|when, then| {
+        when.method(PATCH)
+            .path("/repos/org/new-svc/git/refs/heads/main")
+            .json_body_obj(&serde_json::json!({ "sha": "c1", "force": false }));
+        then.status(200);
+    });
+
+    let report = copy_dirs_from_repo(
+        &server.base_url(),
+        "testtoken",
+        "org/service-template",
+        "org/new-svc",
+        "main",
+        &CopySelector::prefixes(&["helm/"]),
+        &CopyOptions::default(),
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(
+        report.seeded,
+        vec!["helm/charts/app", "helm/deploy.sh", "helm/values.yaml"]
+    );
+    assert_eq!(report.skipped, vec!["helm/vendor"]);
+    put_regular.assert();
+    script_blob.assert();
+    link_blob.assert();
+    create_tree.assert();
+    create_commit.assert();
+    update_ref.assert();
+}
+
+#[tokio::test]
+async fn skips_symlinks_when_asked() {
+    let server = MockServer::start();
+    mock_tree_with_modes(&server);
+    server.mock(|when, then| {
+        when.method(PUT)
+            .path("/repos/org/new-svc/contents/helm/values.yaml");
+        then.status(201);
+    });
+    let blobs = server.mock(|when, then| {
+        when.method(POST).path("/repos/org/new-svc/git/blobs");
+        then.status(201)
+            .json_body_obj(&serde_json::json!({ "sha": "nb-script" }));
+    });
+    server.mock(|when, then| {
+        when.method(GET)
+            .path("/repos/org/new-svc/git/ref/heads/main");
+        then.status(200)
+            .json_body_obj(&serde_json::json!({ "object": { "sha": "head1" } }));
+    });
+    server.mock(|when, then| {
+        when.method(GET)
+            .path("/repos/org/new-svc/git/commits/head1");
+        then.status(200)
+            .json_body_obj(&serde_json::json!({ "tree": { "sha": "basetree" } }));
+    });
+    let create_tree = server.mock(|when, then| {
+        when.method(POST)
+            .path("/repos/org/new-svc/git/trees")
+            .json_body_obj(&serde_json::json!({
+                "base_tree": "basetree",
+                "tree": [
+                    { "path": "helm/deploy.sh", "mode": "100755", "type": "blob", "sha": "nb-script" }
+                ]
+            }));
+        then.status(201)
+            .json_body_obj(&serde_json::json!({ "sha": "newtree" }));
+    });
+    server.mock(|when, then| {
+        when.method(POST).path("/repos/org/new-svc/git/commits");
+        then.status(201)
+            .json_body_obj(&serde_json::json!({ "sha": "c1" }));
+    });
+    server.mock(|when, then| {
+        when.method(PATCH)
+            .path("/repos/org/new-svc/git/refs/heads/main");
+        then.status(200);
+    });
+
+    let report = copy_dirs_from_repo(
+        &server.base_url(),
+        "testtoken",
+        "org/service-template",
+        "org/new-svc",
+        "main",
+        &CopySelector::prefixes(&["helm/"]),
+        &CopyOptions {
+            skip_symlinks: true,
+            ..Default::default()
+        },
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(report.seeded, vec!["helm/deploy.sh", "helm/values.yaml"]);
+    assert_eq!(report.skipped, vec!["helm/charts/app", "helm/vendor"]);
+    blobs.assert_calls(1);
+    create_tree.assert();
+}
+
+/// Mocks a source tree with a small file and a 20-byte fixture, which is
+/// large under a 10-byte threshold.
+fn mock_tree_with_large_file(server: &MockServer) {
+    server.mock(|when, then| {
+        when.method(GET).path("/repos/org/service-template");
+        then.status(200)
+            .json_body_obj(&serde_json::json!({ "default_branch": "main" }));
+    });
+    server.mock(|when, then| {
+        when.method(GET)
+            .path("/repos/org/service-template/git/trees/main")
+            .query_param("recursive", "1");
+        then.status(200).json_body_obj(&serde_json::json!({
+            "tree": [
+                { "path": "helm/values.yaml", "mode": "100644", "type": "blob", "sha": "s1", "size": 4 },
+                { "path": "helm/fixture.bin", "mode": "100644", "type": "blob", "sha": "s2", "size": 20 }
+            ]
+        }));
+    });
+    server.mock(|when, then| {
+        when.method(GET)
+            .path("/repos/org/service-template/git/blobs/s1");
+        then.status(200)
+            .json_body_obj(&serde_json::json!({ "content": "dmFscw==", "encoding": "base64" }));
+    });
+    server.mock(|when, then| {
+        when.method(GET)
+            .path_includes("/repos/org/new-svc/contents/");
+        then.status(404);
+    });
+    server.mock(|when, then| {
+        when.method(PUT)
+            .path("/repos/org/new-svc/contents/helm/values.yaml");
+        then.status(201);
+    });
+}
+
+#[tokio::test]
+async fn uploads_large_files_through_git_data_api() {
+    let server = MockServer::start();
+    mock_tree_with_large_file(&server);
+    let large_blob = server.mock(|when, then| {
+        when.method(GET)
+            .path("/repos/org/service-template/git/blobs/s2");
+        then.status(200).json_body_obj(&serde_json::json!({
+            "content": "MDEyMzQ1Njc4OTAxMjM0NTY3ODk=",
+            "encoding": "base64"
+        }));
+    });
+    let put_large = server.mock(|when, then| {
+        when.method(PUT)
+            .path("/repos/org/new-svc/contents/helm/fixture.bin");
+        then.status(201);
+    });
+    server.mock(|when, then| {
+        when.method(POST).path("/repos/org/new-svc/git/blobs");
+        then.status(201)
+            .json_body_obj(&serde_json::json!({ "sha": "nb-large" }));
+    });
+    server.mock(|when, then| {
+        when.method(GET)
+            .path("/repos/org/new-svc/git/ref/heads/main");
+        then.status(200)
+            .json_body_obj(&serde_json::json!({ "object": { "sha": "head1" } }));
+    });
+    server.mock(|when, then| {
+        when.method(GET)
+            .path("/repos/org/new-svc/git/commits/head1");
+        then.status(200)
+            .json_body_obj(&serde_json::json!({ "tree": { "sha": "basetree" } }));
+    });
+    let create_tree = server.mock(|when, then| {
+        when.method(POST)
+            .path("/repos/org/new-svc/git/trees")
+            .json_body_obj(&serde_json::json!({
+                "base_tree": "basetree",
+                "tree": [
+                    { "path": "helm/fixture.bin", "mode": "100644", "type": "blob", "sha": "nb-large" }
+                ]
+            }));
+        then.status(201)
+            .json_body_obj(&serde_json::json!({ "sha": "newtree" }));
+    });
+    server.mock(|when, then| {
+        when.method(POST).path("/repos/org/new-svc/git/commits");
+        then.status(201)
+            .json_body_obj(&serde_json::json!({ "sha": "c1" }));
+    });
+    server.mock(|when, then| {
+        when.method(PATCH)
+            .path("/repos/org/new-svc/git/refs/heads/main");
+        then.status(200);
+    });
+
+    let report = copy_dirs_from_repo(
+        &server.base_url(),
+        "testtoken",
+        "org/service-template",
+        "org/new-svc",
+        "main",
+        &CopySelector::prefixes(&["helm/"]),
+        &CopyOptions {
+            large_file_threshold: Some(10),
+            ..Default::default()
+        },
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(report.seeded, vec!["helm/fixture.bin", "helm/values.yaml"]);
+    assert!(report.too_large.is_empty());
+    large_blob.assert();
+    put_large.assert_calls(0);
+    create_tree.assert();
+}
+
+#[tokio::test]
+async fn skips_large_files_and_reports_them() {
+    let server = MockServer::start();
+    mock_tree_with_large_file(&server);
+    let large_blob = server.mock(|when, then| {
+        when.method(GET)
+            .path("/repos/org/service-template/git/blobs/s2");
+        then.status(200);
+    });
+    let git_data = server.mock(|when, then| {
+        when.method(POST).path_includes("/repos/org/new-svc/git/");
+        then.status(201);
+    });
+
+    let mut actions = Vec::new();
+    let report = copy_dirs_from_repo_with_progress(
+        &server.base_url(),
+        "testtoken",
+        "org/service-template",
+        "org/new-svc",
+        "main",
+        &CopySelector::prefixes(&["helm/"]),
+        &CopyOptions {
+            large_file_threshold: Some(10),
+            large_files: LargeFilePolicy::Skip,
+            ..Default::default()
+        },
+        |p| actions.push((p.current_path, p.action)),
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(
+        report,
+        CopyReport {
+            seeded: vec!["helm/values.yaml".to_string()],
+            too_large: vec!["helm/fixture.bin".to_string()],
+            ..Default::default()
+        }
+    );
+    assert!(actions.contains(&("helm/fixture.bin".to_string(), CopyAction::TooLarge)));
+    large_blob.assert_calls(0);
+    git_data.assert_calls(0);
+}
+
+#[tokio::test]
+async fn seeds_mapped_prefixes_at_destination_paths() {
+    let server = MockServer::start();
+    server.mock(|when, then| {
+        when.method(GET).path("/repos/org/service-template");
+        then.status(200)
+            .json_body(serde_json::json!({ "default_branch": "main" }));
+    });
+    server.mock(|when, then| {
+        when.method(GET)
+            .path("/repos/org/service-template/git/trees/main");
+        then.status(200).json_body(serde_json::json!({ "tree": [
+            { "path": "modules/bootstrap/terraform", "type": "tree", "sha": "t1" },
+            { "path": "modules/bootstrap/terraform/main.tf", "type": "blob", "sha": "s1" },
+            { "path": "modules/other/x.tf", "type": "blob", "sha": "s2" },
+            { "path": "helm/values.yaml", "type": "blob", "sha": "s3" }
+        ]}));
+    });
+    server.mock(|when, then| {
+        when.method(GET)
+            .path_matches(r"^/repos/org/service-template/git/blobs/s[13]$");
+        then.status(200)
+            .json_body(serde_json::json!({ "content": "eA==", "encoding": "base64" }));
+    });
+    let exists_mapped = server.mock(|when, then| {
+        when.method(GET)
+            .path("/repos/org/new-svc/contents/terraform/main.tf");
+        then.status(404);
+    });
+    let exists_source = server.mock(|when, then| {
+        when.method(GET)
+            .path_matches(r"^/repos/org/new-svc/contents/modules/");
+        then.status(404);
+    });
+    let exists_helm = server.mock(|when, then| {
+        when.method(GET)
+            .path("/repos/org/new-svc/contents/helm/values.yaml");
+        then.status(404);
+    });
+    let put_mapped = server.mock(|when, then| {
+        when.method(PUT)
+            .path("/repos/org/new-svc/contents/terraform/main.tf");
+        then.status(201);
+    });
+    let put_helm = server.mock(|when, then| {
+        when.method(PUT)
+            .path("/repos/org/new-svc/contents/helm/values.yaml");
+        then.status(201);
+    });
+
+    let report = copy_dirs_from_repo(
+        &server.base_url(),
+        "testtoken",
+        "org/service-template",
+        "org/new-svc",
+        "main",
+        &CopySelector::mapped(&[
+            ("modules/bootstrap/terraform/", "terraform/"),
+            ("helm/", "helm/"),
+        ]),
+        &CopyOptions::default(),
+    )
+    .await
+    .unwrap();
+
+    exists_mapped.assert();
+    exists_helm.assert();
+    exists_source.assert_calls(0);
+    put_mapped.assert();
+    put_helm.assert();
+    assert_eq!(report.seeded, vec!["helm/values.yaml", "terraform/main.tf"]);
+}
+
+#[tokio::test]
+async fn rejects_mappings_that_collide_before_writing() {
+    let server = MockServer::start();
+    server.mock(|when, then| {
+        when.method(GET).path("/repos/org/service-template");
+        then.status(200)
+            .json_body(serde_json::json!({ "default_branch": "main" }));
+    });
+    server.mock(|when, then| {
+        when.method(GET)
+            .path("/repos/org/service-template/git/trees/main");
+        then.status(200).json_body(serde_json::json!({ "tree": [
+            { "path": "modules/bootstrap/terraform/main.tf", "type": "blob", "sha": "s1" },
+            { "path": "terraform/main.tf", "type": "blob", "sha": "s2" }
+        ]}));
+    });
+    let writes = server.mock(|when, then| {
+        when.method(PUT).path_matches(r"^/repos/org/new-svc/");
+        then.status(201);
+    });
+
+    let err = copy_dirs_from_repo(
+        &server.base_url(),
+        "testtoken",
+        "org/service-template",
+        "org/new-svc",
+        "main",
+        &CopySelector::mapped(&[
+            ("modules/bootstrap/terraform/", "terraform/"),
+            ("terraform/", "terraform/"),
+        ]),
+        &CopyOptions::default(),
+    )
+    .await
+    .unwrap_err();
+
+    writes.assert_calls(0);
+    match err {
+        GithubClientError::InvalidInput(message) => {
+            assert!(
+                message.contains("'modules/bootstrap/terraform/main.tf'")
+                    && message.contains("'terraform/main.tf' would both be seeded"),
+                "{}",
+                message
+            );
+        }
+        other => panic!("expected InvalidInput, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn seeding_an_archived_repository_fails_clearly() {
+    let server = MockServer::start();
+
+    let _src_repo = server.mock(|when, then| {
+        when.method(GET).path("/repos/org/service-template");
+        then.status(200)
+            .json_body_obj(&serde_json::json!({ "default_branch": "main" }));
+    });
+    let _src_tree = server.mock(|when, then| {
+        when.method(GET)
+            .path("/repos/org/service-template/git/trees/main")
+            .query_param("recursive", "1");
+        then.status(200).json_body_obj(&serde_json::json!({
+            "tree": [{ "path": "terraform/main.tf", "type": "blob", "sha": "s1" }]
+        }));
+    });
+    let _blob = server.mock(|when, then| {
+        when.method(GET)
+            .path("/repos/org/service-template/git/blobs/s1");
+        then.status(200)
+            .json_body_obj(&serde_json::json!({ "content": "dGY=\n", "encoding": "base64" }));
+    });
+    let _exists = server.mock(|when, then| {
+        when.method(GET)
+            .path("/repos/org/old-svc/contents/terraform/main.tf");
+        then.status(404);
+    });
+    let _put = server.mock(|when, then| {
+        when.method(PUT)
+            .path("/repos/org/old-svc/contents/terraform/main.tf");
+        then.status(403)
+            .body(r#"{"message":"Repository was archived so is read-only."}"#);
+    });
+
+    let err = copy_dirs_from_repo(
+        &server.base_url(),
+        "testtoken",
+        "org/service-template",
+        "org/old-svc",
+        "main",
+        &CopySelector::prefixes(&["terraform/"]),
+        &CopyOptions::default(),
+    )
+    .await
+    .expect_err("an archived target must fail the copy");
+    assert!(err.is_archived(), "{:?}", err);
+    assert!(
+        err.to_string().contains("cannot write 'terraform/main.tf'"),
+        "{}",
+        err
+    );
+}
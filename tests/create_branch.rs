@@ -0,0 +1,133 @@
+use github_client::{create_branch_from_base, GithubClientError};
+use httpmock::prelude::*;
+
+/// Mocks a ready repository whose `main` branch points at `base-sha` and a
+/// POST /git/refs that GitHub rejects because `dev` already exists.
+fn mock_existing_branch<'a>(server: &'a MockServer, dev_sha: &str) -> httpmock::Mock<'a> {
+    server.mock(|when, then| {
+        when.method(GET)
+            .path("/repos/me/new-repo/git/ref/heads/main");
+        then.status(200)
+            .json_body_obj(&serde_json::json!({ "object": { "sha": "base-sha" } }));
+    });
+    server.mock(|when, then| {
+        when.method(GET).path("/repos/me/new-repo/git/trees/main");
+        then.status(200)
+            .json_body_obj(&serde_json::json!({ "tree": [{ "path": "README.md" }] }));
+    });
+    server.mock(|when, then| {
+        when.method(GET)
+            .path("/repos/me/new-repo/git/ref/heads/dev");
+        then.status(200)
+            .json_body_obj(&serde_json::json!({ "object": { "sha": dev_sha } }));
+    });
+    server.mock(|when, then| {
+        when.method(POST)
+            .path("/repos/me/new-repo/git/refs")
+            .json_body_obj(&serde_json::json!({ "ref": "refs/heads/dev", "sha": "base-sha" }));
+        then.status(422).json_body_obj(&serde_json::json!({
+            "message": "Reference already exists",
+            "documentation_url": "https://docs.github.com/rest/git/refs#create-a-reference"
+        }));
+    })
+}
+
+#[tokio::test]
+async fn existing_branch_at_the_base_commit_is_success() {
+    let server = MockServer::start();
+    let create = mock_existing_branch(&server, "base-sha");
+
+    let sha = create_branch_from_base(
+        &server.base_url(),
+        "testtoken",
+        "me/new-repo",
+        "main",
+        "dev",
+    )
+    .await
+    .unwrap();
+    create.assert();
+    assert_eq!(sha, "base-sha");
+}
+
+#[tokio::test]
+async fn returns_the_new_branch_sha() {
+    let server = MockServer::start();
+    server.mock(|when, then| {
+        when.method(GET)
+            .path("/repos/me/new-repo/git/ref/heads/main");
+        then.status(200)
+            .json_body_obj(&serde_json::json!({ "object": { "sha": "base-sha" } }));
+    });
+    server.mock(|when, then| {
+        when.method(GET).path("/repos/me/new-repo/git/trees/main");
+        then.status(200)
+            .json_body_obj(&serde_json::json!({ "tree": [{ "path": "README.md" }] }));
+    });
+    let create = server.mock(|when, then| {
+        when.method(POST)
+            .path("/repos/me/new-repo/git/refs")
+            .json_body_obj(&serde_json::json!({ "ref": "refs/heads/dev", "sha": "base-sha" }));
+        then.status(201).json_body_obj(&serde_json::json!({
+            "ref": "refs/heads/dev",
+            "object": { "sha": "base-sha", "type": "commit" }
+        }));
+    });
+
+    let sha = create_branch_from_base(
+        &server.base_url(),
+        "testtoken",
+        "me/new-repo",
+        "main",
+        "dev",
+    )
+    .await
+    .unwrap();
+    create.assert();
+    assert_eq!(sha, "base-sha");
+}
+
+#[tokio::test]
+async fn existing_branch_elsewhere_is_reported_clearly() {
+    let server = MockServer::start();
+    mock_existing_branch(&server, "other-sha");
+
+    let err = create_branch_from_base(
+        &server.base_url(),
+        "testtoken",
+        "me/new-repo",
+        "main",
+        "dev",
+    )
+    .await
+    .unwrap_err();
+
+    assert!(err.is_already_exists(), "{:?}", err);
+    assert!(matches!(err, GithubClientError::ValidationFailed { .. }));
+    assert_eq!(
+        err.to_string(),
+        "Validation failed: cannot create branch 'dev': it already exists at other-sha, not at 'main' (base-sha)"
+    );
+}
+
+#[tokio::test]
+async fn invalid_branch_names_are_rejected_before_any_request() {
+    let server = MockServer::start();
+    let any = server.mock(|when, then| {
+        when.any_request();
+        then.status(500);
+    });
+
+    for name in ["feature/new thing", "../escape", "refs/heads/dev"] {
+        let err = create_branch_from_base(&server.base_url(), "testtoken", "me/repo", "main", name)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, GithubClientError::InvalidInput(_)), "{err}");
+        assert!(
+            err.to_string()
+                .starts_with(&format!("invalid branch name '{}'", name)),
+            "{err}"
+        );
+    }
+    any.assert_calls(0);
+}
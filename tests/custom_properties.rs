@@ -0,0 +1,148 @@
+use github_client::{
+    get_custom_properties, set_custom_properties, CustomProperty, GithubClientError,
+};
+use httpmock::prelude::*;
+
+fn props(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+    pairs
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect()
+}
+
+#[tokio::test]
+async fn patches_property_values() {
+    let server = MockServer::start();
+    let m = server.mock(|when, then| {
+        when.method(PATCH)
+            .path("/repos/org/new-svc/properties/values")
+            .json_body(serde_json::json!({
+                "properties": [
+                    { "property_name": "service-tier", "value": "gold" },
+                    { "property_name": "cost-center", "value": "cc-42" }
+                ]
+            }));
+        then.status(204);
+    });
+
+    set_custom_properties(
+        &server.base_url(),
+        "testtoken",
+        "org/new-svc",
+        &props(&[("service-tier", "gold"), ("cost-center", "cc-42")]),
+    )
+    .await
+    .unwrap();
+    m.assert();
+}
+
+#[tokio::test]
+async fn reads_property_values() {
+    let server = MockServer::start();
+    server.mock(|when, then| {
+        when.method(GET)
+            .path("/repos/org/new-svc/properties/values");
+        then.status(200).json_body(serde_json::json!([
+            { "property_name": "service-tier", "value": "gold" },
+            { "property_name": "regions", "value": ["eu", "us"] },
+            { "property_name": "owner-team", "value": null }
+        ]));
+    });
+
+    let values = get_custom_properties(&server.base_url(), "testtoken", "org/new-svc")
+        .await
+        .unwrap();
+    assert_eq!(
+        values,
+        vec![
+            CustomProperty {
+                property_name: "service-tier".into(),
+                value: serde_json::json!("gold"),
+            },
+            CustomProperty {
+                property_name: "regions".into(),
+                value: serde_json::json!(["eu", "us"]),
+            },
+            CustomProperty {
+                property_name: "owner-team".into(),
+                value: serde_json::Value::Null,
+            },
+        ]
+    );
+}
+
+#[tokio::test]
+async fn passes_through_unknown_property_details() {
+    let server = MockServer::start();
+    server.mock(|when, then| {
+        when.method(PATCH)
+            .path("/repos/org/new-svc/properties/values");
+        then.status(422).json_body(serde_json::json!({
+            "message": "Validation Failed",
+            "errors": [{
+                "resource": "PropertyValue",
+                "field": "property_name",
+                "code": "invalid",
+                "message": "Property 'tier' is not defined for this organization"
+            }]
+        }));
+    });
+
+    let err = set_custom_properties(
+        &server.base_url(),
+        "testtoken",
+        "org/new-svc",
+        &props(&[("tier", "gold")]),
+    )
+    .await
+    .unwrap_err();
+    match err {
+        GithubClientError::ValidationFailed { errors, .. } => assert_eq!(
+            errors[0].message.as_deref(),
+            Some("Property 'tier' is not defined for this organization")
+        ),
+        other => panic!("expected ValidationFailed, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn explains_forbidden() {
+    let server = MockServer::start();
+    server.mock(|when, then| {
+        when.method(PATCH)
+            .path("/repos/org/new-svc/properties/values");
+        then.status(403).json_body(
+            serde_json::json!({ "message": "Resource not accessible by personal access token" }),
+        );
+    });
+
+    let err = set_custom_properties(
+        &server.base_url(),
+        "testtoken",
+        "org/new-svc",
+        &props(&[("service-tier", "gold")]),
+    )
+    .await
+    .unwrap_err();
+    assert!(matches!(err, GithubClientError::Forbidden { .. }));
+    assert!(
+        err.to_string()
+            .contains("'Custom properties: Read and write'"),
+        "{}",
+        err
+    );
+}
+
+#[tokio::test]
+async fn does_nothing_without_properties() {
+    let server = MockServer::start();
+    let m = server.mock(|when, then| {
+        when.any_request();
+        then.status(500);
+    });
+
+    set_custom_properties(&server.base_url(), "testtoken", "org/new-svc", &[])
+        .await
+        .unwrap();
+    m.assert_calls(0);
+}
@@ -0,0 +1,90 @@
+use std::time::Duration;
+
+use github_client::{
+    set_default_branch, set_default_branch_with_client, GithubClient, GithubClientError,
+};
+use httpmock::prelude::*;
+use serde_json::json;
+
+fn repo(default_branch: &str) -> serde_json::Value {
+    json!({
+        "full_name": "me/new-repo",
+        "html_url": "https://github.com/me/new-repo",
+        "default_branch": default_branch
+    })
+}
+
+#[tokio::test]
+async fn patches_default_branch_once_the_branch_exists() {
+    let server = MockServer::start();
+    let _repo = server.mock(|when, then| {
+        when.method(GET).path("/repos/me/new-repo");
+        then.status(200).json_body(repo("main"));
+    });
+    let branch = server.mock(|when, then| {
+        when.method(GET).path("/repos/me/new-repo/branches/dev");
+        then.status(200).json_body(json!({ "name": "dev" }));
+    });
+    let patch = server.mock(|when, then| {
+        when.method(PATCH)
+            .path("/repos/me/new-repo")
+            .json_body(json!({ "default_branch": "dev" }));
+        then.status(200).json_body(repo("dev"));
+    });
+
+    set_default_branch(&server.base_url(), "testtoken", "me/new-repo", "dev")
+        .await
+        .unwrap();
+
+    branch.assert();
+    patch.assert();
+}
+
+#[tokio::test]
+async fn skips_when_already_the_default() {
+    let server = MockServer::start();
+    let _repo = server.mock(|when, then| {
+        when.method(GET).path("/repos/me/new-repo");
+        then.status(200).json_body(repo("dev"));
+    });
+    let patch = server.mock(|when, then| {
+        when.method(PATCH).path("/repos/me/new-repo");
+        then.status(200).json_body(repo("dev"));
+    });
+
+    set_default_branch(&server.base_url(), "testtoken", "me/new-repo", "dev")
+        .await
+        .unwrap();
+
+    patch.assert_calls(0);
+}
+
+#[tokio::test]
+async fn does_not_patch_to_a_missing_branch() {
+    let server = MockServer::start();
+    let client = GithubClient::builder(&server.base_url(), "testtoken")
+        .branch_wait(Duration::from_millis(300))
+        .build()
+        .unwrap();
+    let _repo = server.mock(|when, then| {
+        when.method(GET).path("/repos/me/new-repo");
+        then.status(200).json_body(repo("main"));
+    });
+    let _branch = server.mock(|when, then| {
+        when.method(GET).path("/repos/me/new-repo/branches/develop");
+        then.status(404)
+            .json_body(json!({ "message": "Branch not found" }));
+    });
+    let patch = server.mock(|when, then| {
+        when.method(PATCH).path("/repos/me/new-repo");
+        then.status(422);
+    });
+
+    let err = set_default_branch_with_client(&client, "me/new-repo", "develop")
+        .await
+        .unwrap_err();
+
+    assert!(matches!(err, GithubClientError::Timeout { .. }));
+    assert!(err.to_string().contains("develop"), "{}", err);
+    patch.assert_calls(0);
+}
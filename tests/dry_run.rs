@@ -0,0 +1,178 @@
+use github_client::{
+    copy_dirs_from_repo_with_client, delete_repo_with_client, ensure_environment_with_client,
+    generate_from_template_with_client, protect_branch_with_checks_with_client,
+    set_repo_secret_with_client, CopyOptions, CopySelector, EnvironmentOptions, ExecutionMode,
+    GithubClient, Plan, PlannedCall,
+};
+use httpmock::prelude::*;
+
+#[tokio::test]
+async fn dry_run_sends_no_writes_and_records_plan() {
+    let server = MockServer::start();
+    let api_base = server.base_url();
+    let client = GithubClient::builder(&api_base, "testtoken")
+        .mode(ExecutionMode::DryRun)
+        .build()
+        .unwrap();
+
+    // Reads still go to GitHub
+    let _user = server.mock(|when, then| {
+        when.method(GET).path("/user");
+        then.status(200)
+            .json_body_obj(&serde_json::json!({ "login": "me" }));
+    });
+    let _template = server.mock(|when, then| {
+        when.method(GET).path("/repos/org/service-template");
+        then.status(200)
+            .json_body_obj(&serde_json::json!({ "default_branch": "main" }));
+    });
+    let _tree = server.mock(|when, then| {
+        when.method(GET)
+            .path("/repos/org/service-template/git/trees/main")
+            .query_param("recursive", "1");
+        then.status(200).json_body_obj(&serde_json::json!({
+            "tree": [
+                { "path": "terraform/main.tf", "type": "blob", "sha": "s1" },
+                { "path": "helm/values.yaml", "type": "blob", "sha": "s2" }
+            ]
+        }));
+    });
+    let _blobs = server.mock(|when, then| {
+        when.method(GET)
+            .path_prefix("/repos/org/service-template/git/blobs/");
+        then.status(200)
+            .json_body_obj(&serde_json::json!({ "content": "eA==", "encoding": "base64" }));
+    });
+    let _existing = server.mock(|when, then| {
+        when.method(GET)
+            .path("/repos/me/new-svc/contents/helm/values.yaml");
+        then.status(200).json_body_obj(&serde_json::json!({}));
+    });
+
+    let writes: Vec<_> = [POST, PUT, PATCH, DELETE]
+        .into_iter()
+        .map(|method| {
+            server.mock(|when, then| {
+                when.method(method);
+                then.status(500);
+            })
+        })
+        .collect();
+
+    let repo = generate_from_template_with_client(
+        &client,
+        "org/service-template",
+        None,
+        "new-svc",
+        "desc",
+        true,
+        false,
+    )
+    .await
+    .unwrap();
+    assert_eq!(repo.full_name, "me/new-svc");
+    assert_eq!(repo.default_branch, "main");
+
+    copy_dirs_from_repo_with_client(
+        &client,
+        "org/service-template",
+        &repo.full_name,
+        "main",
+        &CopySelector::prefixes(&["terraform/", "helm/"]),
+        &CopyOptions::default(),
+    )
+    .await
+    .unwrap();
+    protect_branch_with_checks_with_client(&client, &repo.full_name, "main", &["build"])
+        .await
+        .unwrap();
+    ensure_environment_with_client(
+        &client,
+        &repo.full_name,
+        "dev",
+        &EnvironmentOptions {
+            allowed_patterns: &["dev"],
+            ..Default::default()
+        },
+    )
+    .await
+    .unwrap();
+    set_repo_secret_with_client(&client, &repo.full_name, "DEPLOY_KEY", "hunter2")
+        .await
+        .unwrap();
+
+    for m in &writes {
+        m.assert_calls(0);
+    }
+
+    let plan = client.take_plan();
+    let calls: Vec<(&str, String)> = plan
+        .calls
+        .iter()
+        .map(|c| (c.method.as_str(), c.url.replace(&api_base, "")))
+        .collect();
+    assert_eq!(
+        calls,
+        vec![
+            ("POST", "/repos/org/service-template/generate".to_string()),
+            (
+                "PUT",
+                "/repos/me/new-svc/contents/terraform/main.tf".to_string()
+            ),
+            (
+                "PUT",
+                "/repos/me/new-svc/branches/main/protection".to_string()
+            ),
+            ("PUT", "/repos/me/new-svc/environments/dev".to_string()),
+            (
+                "POST",
+                "/repos/me/new-svc/environments/dev/deployment-branch-policies".to_string()
+            ),
+            (
+                "PUT",
+                "/repos/me/new-svc/actions/secrets/DEPLOY_KEY".to_string()
+            ),
+        ]
+    );
+    assert_eq!(plan.skipped, vec!["helm/values.yaml (already exists)"]);
+    assert!(!plan.to_string().contains("hunter2"));
+    assert!(client.take_plan().is_empty());
+}
+
+#[tokio::test]
+async fn each_client_keeps_its_own_plan() {
+    let dry_run = GithubClient::builder("http://127.0.0.1:1", "testtoken")
+        .mode(ExecutionMode::DryRun)
+        .build()
+        .unwrap();
+    let other = dry_run.clone();
+    let unrelated = GithubClient::builder("http://127.0.0.1:1", "testtoken")
+        .mode(ExecutionMode::DryRun)
+        .build()
+        .unwrap();
+
+    delete_repo_with_client(&dry_run, "me/old-svc")
+        .await
+        .unwrap();
+
+    assert!(unrelated.take_plan().is_empty());
+    // Clones share the plan
+    assert_eq!(other.take_plan().calls.len(), 1);
+    assert!(dry_run.take_plan().is_empty());
+}
+
+#[test]
+fn plan_renders_one_line_per_entry() {
+    let plan = Plan {
+        calls: vec![PlannedCall {
+            method: "PUT".to_string(),
+            url: "https://api.github.com/repos/me/r/contents/a.txt".to_string(),
+            summary: "would create a.txt on 'main'".to_string(),
+        }],
+        skipped: vec!["b.txt (already exists)".to_string()],
+    };
+    assert_eq!(
+        plan.to_string(),
+        "PUT https://api.github.com/repos/me/r/contents/a.txt: would create a.txt on 'main'\nskip: b.txt (already exists)\n"
+    );
+}
@@ -0,0 +1,121 @@
+use github_client::{set_environment_secret, set_environment_variable};
+use httpmock::prelude::*;
+
+#[tokio::test]
+async fn creates_environment_variable() {
+    let server = MockServer::start();
+    let owner = "me";
+    let repo = "new-repo";
+    let token = "testtoken";
+
+    let m = server.mock(|when, then| {
+        when.method(POST)
+            .path(format!(
+                "/repos/{}/{}/environments/dev/variables",
+                owner, repo
+            ))
+            .header("authorization", "Bearer testtoken")
+            .json_body_obj(&serde_json::json!({
+                "name": "AWS_REGION",
+                "value": "eu-west-1"
+            }));
+        then.status(201);
+    });
+
+    let api_base = server.base_url();
+    let res = set_environment_variable(
+        &api_base,
+        token,
+        &format!("{}/{}", owner, repo),
+        "dev",
+        "AWS_REGION",
+        "eu-west-1",
+    )
+    .await;
+    assert!(res.is_ok());
+    m.assert();
+}
+
+#[tokio::test]
+async fn updates_existing_environment_variable() {
+    let server = MockServer::start();
+    let owner = "me";
+    let repo = "new-repo";
+    let token = "testtoken";
+
+    let _create = server.mock(|when, then| {
+        when.method(POST).path(format!(
+            "/repos/{}/{}/environments/dev/variables",
+            owner, repo
+        ));
+        then.status(409)
+            .body("{\"message\":\"Variable already exists\"}");
+    });
+    let update = server.mock(|when, then| {
+        when.method(PATCH)
+            .path(format!(
+                "/repos/{}/{}/environments/dev/variables/AWS_REGION",
+                owner, repo
+            ))
+            .json_body_obj(&serde_json::json!({
+                "name": "AWS_REGION",
+                "value": "eu-west-1"
+            }));
+        then.status(204);
+    });
+
+    let api_base = server.base_url();
+    let res = set_environment_variable(
+        &api_base,
+        token,
+        &format!("{}/{}", owner, repo),
+        "dev",
+        "AWS_REGION",
+        "eu-west-1",
+    )
+    .await;
+    assert!(res.is_ok());
+    update.assert();
+}
+
+#[tokio::test]
+async fn sets_environment_secret_with_key_id() {
+    let server = MockServer::start();
+    let owner = "me";
+    let repo = "new-repo";
+    let token = "testtoken";
+
+    let key = server.mock(|when, then| {
+        when.method(GET).path(format!(
+            "/repos/{}/{}/environments/release/secrets/public-key",
+            owner, repo
+        ));
+        then.status(200).json_body_obj(&serde_json::json!({
+            "key_id": "012345678912345678",
+            "key": "CQkJCQkJCQkJCQkJCQkJCQkJCQkJCQkJCQkJCQkJCQk="
+        }));
+    });
+    let put = server.mock(|when, then| {
+        when.method(PUT)
+            .path(format!(
+                "/repos/{}/{}/environments/release/secrets/DEPLOY_KEY",
+                owner, repo
+            ))
+            .json_body_includes("{\"key_id\":\"012345678912345678\"}");
+        then.status(201);
+    });
+
+    let api_base = server.base_url();
+    let res = set_environment_secret(
+        &api_base,
+        token,
+        &format!("{}/{}", owner, repo),
+        "release",
+        "DEPLOY_KEY",
+        "hunter2",
+    )
+    .await;
+    assert!(res.is_ok());
+    key.assert();
+    put.assert();
+}
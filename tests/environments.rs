@@ -0,0 +1,348 @@
+use github_client::{
+    ensure_environment, ensure_environment_with_branches, get_team_id, get_user_id,
+    EnvironmentOptions, GithubClientError, Reviewer, ReviewerType,
+};
+use httpmock::prelude::*;
+
+#[tokio::test]
+async fn ensure_environment_sends_reviewers_and_wait_timer() {
+    let server = MockServer::start();
+    let owner = "me";
+    let repo = "new-repo";
+    let token = "testtoken";
+
+    let put = server.mock(|when, then| {
+        when.method(PUT)
+            .path(format!("/repos/{}/{}/environments/release", owner, repo))
+            .json_body_obj(&serde_json::json!({
+                "wait_timer": 30,
+                "prevent_self_review": true,
+                "reviewers": [
+                    { "type": "Team", "id": 42 },
+                    { "type": "User", "id": 7 }
+                ],
+                "deployment_branch_policy": {
+                    "protected_branches": false,
+                    "custom_branch_policies": true
+                }
+            }));
+        then.status(200);
+    });
+    let _existing = server.mock(|when, then| {
+        when.method(GET).path(format!(
+            "/repos/{}/{}/environments/release/deployment-branch-policies",
+            owner, repo
+        ));
+        then.status(200)
+            .json_body_obj(&serde_json::json!({ "total_count": 0, "branch_policies": [] }));
+    });
+    let policy = server.mock(|when, then| {
+        when.method(POST).path(format!(
+            "/repos/{}/{}/environments/release/deployment-branch-policies",
+            owner, repo
+        ));
+        then.status(200);
+    });
+
+    let reviewers = [
+        Reviewer {
+            reviewer_type: ReviewerType::Team,
+            id: 42,
+        },
+        Reviewer {
+            reviewer_type: ReviewerType::User,
+            id: 7,
+        },
+    ];
+    let options = EnvironmentOptions {
+        allowed_patterns: &["release/*", "main"],
+        wait_timer: Some(30),
+        reviewers: &reviewers,
+        prevent_self_review: Some(true),
+        prune: false,
+    };
+
+    let api_base = server.base_url();
+    let res = ensure_environment(
+        &api_base,
+        token,
+        &format!("{}/{}", owner, repo),
+        "release",
+        &options,
+    )
+    .await;
+    assert!(res.is_ok());
+    put.assert();
+    policy.assert_calls(2);
+}
+
+#[tokio::test]
+async fn ensure_environment_with_branches_omits_protection_rules() {
+    let server = MockServer::start();
+    let owner = "me";
+    let repo = "new-repo";
+    let token = "testtoken";
+
+    let put = server.mock(|when, then| {
+        when.method(PUT)
+            .path(format!("/repos/{}/{}/environments/dev", owner, repo))
+            .json_body_obj(&serde_json::json!({
+                "deployment_branch_policy": {
+                    "protected_branches": false,
+                    "custom_branch_policies": true
+                }
+            }));
+        then.status(200);
+    });
+    let _existing = server.mock(|when, then| {
+        when.method(GET).path(format!(
+            "/repos/{}/{}/environments/dev/deployment-branch-policies",
+            owner, repo
+        ));
+        then.status(200)
+            .json_body_obj(&serde_json::json!({ "total_count": 0, "branch_policies": [] }));
+    });
+    let _policy = server.mock(|when, then| {
+        when.method(POST).path(format!(
+            "/repos/{}/{}/environments/dev/deployment-branch-policies",
+            owner, repo
+        ));
+        then.status(200);
+    });
+
+    let api_base = server.base_url();
+    let res = ensure_environment_with_branches(
+        &api_base,
+        token,
+        &format!("{}/{}", owner, repo),
+        "dev",
+        &["dev"],
+    )
+    .await;
+    assert!(res.is_ok());
+    put.assert();
+}
+
+#[tokio::test]
+async fn ensure_environment_only_posts_missing_branch_policies() {
+    let server = MockServer::start();
+    let owner = "me";
+    let repo = "new-repo";
+    let token = "testtoken";
+    let policies_path = format!(
+        "/repos/{}/{}/environments/dev/deployment-branch-policies",
+        owner, repo
+    );
+
+    let _put = server.mock(|when, then| {
+        when.method(PUT)
+            .path(format!("/repos/{}/{}/environments/dev", owner, repo));
+        then.status(200);
+    });
+    // A full first page forces a second request, where 'dev' lives
+    let first_page: Vec<serde_json::Value> = (0..100)
+        .map(|i| serde_json::json!({ "id": i, "name": format!("release/{}", i) }))
+        .collect();
+    let page1 = server.mock(|when, then| {
+        when.method(GET)
+            .path(&policies_path)
+            .query_param("page", "1");
+        then.status(200).json_body_obj(&serde_json::json!({
+            "total_count": 101,
+            "branch_policies": first_page
+        }));
+    });
+    let page2 = server.mock(|when, then| {
+        when.method(GET)
+            .path(&policies_path)
+            .query_param("page", "2");
+        then.status(200).json_body_obj(&serde_json::json!({
+            "total_count": 101,
+            "branch_policies": [{ "id": 100, "name": "dev" }]
+        }));
+    });
+    let post_dev = server.mock(|when, then| {
+        when.method(POST)
+            .path(&policies_path)
+            .json_body_obj(&serde_json::json!({ "name": "dev" }));
+        then.status(422);
+    });
+    let post_feature = server.mock(|when, then| {
+        when.method(POST)
+            .path(&policies_path)
+            .json_body_obj(&serde_json::json!({ "name": "feature/*" }));
+        then.status(200);
+    });
+    let post_hotfix = server.mock(|when, then| {
+        when.method(POST)
+            .path(&policies_path)
+            .json_body_obj(&serde_json::json!({ "name": "hotfix/*" }));
+        then.status(200);
+    });
+
+    let api_base = server.base_url();
+    ensure_environment_with_branches(
+        &api_base,
+        token,
+        &format!("{}/{}", owner, repo),
+        "dev",
+        &["dev", "feature/*", "hotfix/*"],
+    )
+    .await
+    .unwrap();
+    page1.assert();
+    page2.assert();
+    post_dev.assert_calls(0);
+    post_feature.assert();
+    post_hotfix.assert();
+}
+
+#[tokio::test]
+async fn resolves_user_and_team_ids() {
+    let server = MockServer::start();
+
+    let _user = server.mock(|when, then| {
+        when.method(GET).path("/users/octocat");
+        then.status(200)
+            .json_body_obj(&serde_json::json!({ "login": "octocat", "id": 583231 }));
+    });
+    let _team = server.mock(|when, then| {
+        when.method(GET).path("/orgs/acme/teams/platform");
+        then.status(200)
+            .json_body_obj(&serde_json::json!({ "slug": "platform", "id": 1234 }));
+    });
+    let _missing = server.mock(|when, then| {
+        when.method(GET).path("/orgs/acme/teams/ghosts");
+        then.status(404);
+    });
+
+    let api_base = server.base_url();
+    assert_eq!(
+        get_user_id(&api_base, "testtoken", "octocat")
+            .await
+            .unwrap(),
+        583231
+    );
+    assert_eq!(
+        get_team_id(&api_base, "testtoken", "acme", "platform")
+            .await
+            .unwrap(),
+        1234
+    );
+    assert!(get_team_id(&api_base, "testtoken", "acme", "ghosts")
+        .await
+        .unwrap_err()
+        .is_not_found());
+}
+
+#[tokio::test]
+async fn prune_removes_undeclared_branch_policies() {
+    let server = MockServer::start();
+    let policies_path = "/repos/me/new-repo/environments/dev/deployment-branch-policies";
+
+    let _put = server.mock(|when, then| {
+        when.method(PUT).path("/repos/me/new-repo/environments/dev");
+        then.status(200);
+    });
+    let _existing = server.mock(|when, then| {
+        when.method(GET).path(policies_path);
+        then.status(200).json_body_obj(&serde_json::json!({
+            "total_count": 2,
+            "branch_policies": [
+                { "id": 11, "name": "dev" },
+                { "id": 12, "name": "experiment/*" }
+            ]
+        }));
+    });
+    let post = server.mock(|when, then| {
+        when.method(POST)
+            .path(policies_path)
+            .json_body_obj(&serde_json::json!({ "name": "feature/*" }));
+        then.status(200);
+    });
+    let delete_extra = server.mock(|when, then| {
+        when.method(DELETE).path(format!("{}/12", policies_path));
+        then.status(204);
+    });
+    let delete_dev = server.mock(|when, then| {
+        when.method(DELETE).path(format!("{}/11", policies_path));
+        then.status(204);
+    });
+
+    let report = ensure_environment(
+        &server.base_url(),
+        "testtoken",
+        "me/new-repo",
+        "dev",
+        &EnvironmentOptions {
+            allowed_patterns: &["dev", "feature/*"],
+            prune: true,
+            ..Default::default()
+        },
+    )
+    .await
+    .unwrap();
+
+    post.assert();
+    delete_extra.assert();
+    delete_dev.assert_calls(0);
+    assert_eq!(report.branch_policies_added, vec!["feature/*"]);
+    assert_eq!(report.branch_policies_existing, vec!["dev"]);
+    assert_eq!(report.branch_policies_removed, vec!["experiment/*"]);
+}
+
+#[tokio::test]
+async fn branch_policy_failures_are_errors_unless_the_pattern_is_invalid() {
+    let server = MockServer::start();
+    let policies_path = "/repos/me/new-repo/environments/dev/deployment-branch-policies";
+
+    let _put = server.mock(|when, then| {
+        when.method(PUT).path("/repos/me/new-repo/environments/dev");
+        then.status(200);
+    });
+    let _existing = server.mock(|when, then| {
+        when.method(GET).path(policies_path);
+        then.status(200)
+            .json_body_obj(&serde_json::json!({ "total_count": 0, "branch_policies": [] }));
+    });
+    let _invalid = server.mock(|when, then| {
+        when.method(POST)
+            .path(policies_path)
+            .json_body_obj(&serde_json::json!({ "name": "[" }));
+        then.status(422)
+            .json_body_obj(&serde_json::json!({ "message": "Invalid name" }));
+    });
+    let _forbidden = server.mock(|when, then| {
+        when.method(POST)
+            .path(policies_path)
+            .json_body_obj(&serde_json::json!({ "name": "dev" }));
+        then.status(403)
+            .json_body_obj(&serde_json::json!({ "message": "Resource not accessible" }));
+    });
+
+    let report = ensure_environment_with_branches(
+        &server.base_url(),
+        "testtoken",
+        "me/new-repo",
+        "dev",
+        &["["],
+    )
+    .await
+    .unwrap();
+    assert_eq!(report.branch_policies_failed, vec!["["]);
+
+    let err = ensure_environment_with_branches(
+        &server.base_url(),
+        "testtoken",
+        "me/new-repo",
+        "dev",
+        &["dev"],
+    )
+    .await
+    .unwrap_err();
+    assert!(
+        matches!(err, GithubClientError::Forbidden { .. }),
+        "{}",
+        err
+    );
+}
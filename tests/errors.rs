@@ -0,0 +1,157 @@
+use github_client::{
+    generate_from_template_with_client, get_repo, get_repo_with_client, protect_branch_with_client,
+    GithubClient, GithubClientError,
+};
+use httpmock::prelude::*;
+
+/// 5xx responses here stand for a failure, not an outage; skip the backoff.
+fn without_retries(api_base: &str, token: &str) -> GithubClient {
+    GithubClient::builder(api_base, token)
+        .max_retries(0)
+        .build()
+        .unwrap()
+}
+
+#[tokio::test]
+async fn maps_status_codes_to_variants() {
+    let server = MockServer::start();
+    let _unauthorized = server.mock(|when, then| {
+        when.method(GET).path("/repos/me/unauthorized");
+        then.status(401).body("{\"message\":\"Bad credentials\"}");
+    });
+    let _rate_limited = server.mock(|when, then| {
+        when.method(GET).path("/repos/me/limited");
+        then.status(403)
+            .header("x-ratelimit-remaining", "0")
+            .header("x-ratelimit-reset", "1700000000")
+            .body("{\"message\":\"API rate limit exceeded\"}");
+    });
+    let _forbidden = server.mock(|when, then| {
+        when.method(GET).path("/repos/me/forbidden");
+        then.status(403)
+            .body("{\"message\":\"Resource not accessible by integration\"}");
+    });
+
+    let client = without_retries(&server.base_url(), "testtoken");
+
+    let err = get_repo_with_client(&client, "me/unauthorized")
+        .await
+        .unwrap_err();
+    assert!(matches!(err, GithubClientError::Unauthorized));
+    assert!(!err.is_retryable());
+
+    let err = get_repo_with_client(&client, "me/limited")
+        .await
+        .unwrap_err();
+    assert!(matches!(
+        err,
+        GithubClientError::RateLimited {
+            reset_at: Some(1700000000)
+        }
+    ));
+    assert!(err.is_retryable());
+
+    let err = get_repo_with_client(&client, "me/forbidden")
+        .await
+        .unwrap_err();
+    match &err {
+        GithubClientError::Forbidden { message } => {
+            assert!(message.contains("Resource not accessible by integration"))
+        }
+        other => panic!("expected Forbidden, got {:?}", other),
+    }
+    assert!(!err.is_retryable());
+}
+
+#[tokio::test]
+async fn rejects_malformed_names_before_sending() {
+    let err = get_repo("http://127.0.0.1:1", "testtoken", "no-slash")
+        .await
+        .unwrap_err();
+    assert!(matches!(err, GithubClientError::InvalidInput(_)));
+}
+
+#[tokio::test]
+async fn error_bodies_never_contain_the_token() {
+    let server = MockServer::start();
+    let token = "ghp_supersecret";
+    let _echo = server.mock(|when, then| {
+        when.method(GET).path("/repos/me/echo");
+        then.status(500)
+            .body("upstream said: Authorization: Bearer ghp_supersecret");
+    });
+
+    let err = get_repo_with_client(&without_retries(&server.base_url(), token), "me/echo")
+        .await
+        .unwrap_err();
+    let shown = format!("{} {:?}", err, err);
+    assert!(!shown.contains(token), "{}", shown);
+    assert!(shown.contains("Bearer ***"), "{}", shown);
+}
+
+#[test]
+fn malformed_token_error_does_not_echo_it() {
+    let token = "bad\ntoken-value";
+    let err = match github_client::GithubClient::new("https://api.github.com", token) {
+        Ok(_) => panic!("a newline is not a valid header value"),
+        Err(err) => err,
+    };
+    assert!(matches!(err, GithubClientError::InvalidInput(_)));
+    assert!(!format!("{:?}", err).contains("token-value"));
+}
+
+#[tokio::test]
+async fn errors_carry_request_id_and_rate_limit_state() {
+    let server = MockServer::start();
+    let _forbidden = server.mock(|when, then| {
+        when.method(POST)
+            .path("/repos/me/service-template/generate");
+        then.status(403)
+            .header("x-github-request-id", "0C8E:1A2B:3C4D5E:6F7A8B:65A1B2C3")
+            .header("x-ratelimit-remaining", "4321")
+            .header("x-ratelimit-reset", "1700000000")
+            .body("{\"message\":\"Resource not accessible by integration\"}");
+    });
+    let _branch = server.mock(|when, then| {
+        when.method(GET).path("/repos/me/new-repo/branches/main");
+        then.status(200);
+    });
+    let _protection = server.mock(|when, then| {
+        when.method(PUT)
+            .path("/repos/me/new-repo/branches/main/protection");
+        then.status(404)
+            .header("x-github-request-id", "ABCD:0001")
+            .body("{\"message\":\"Not Found\"}");
+    });
+
+    let client = without_retries(&server.base_url(), "testtoken");
+    let err = generate_from_template_with_client(
+        &client,
+        "me/service-template",
+        None,
+        "new-repo",
+        "desc",
+        true,
+        false,
+    )
+    .await
+    .unwrap_err();
+    assert!(matches!(err, GithubClientError::Forbidden { .. }));
+    let message = err.to_string();
+    assert!(
+        message.contains("request id 0C8E:1A2B:3C4D5E:6F7A8B:65A1B2C3"),
+        "{}",
+        message
+    );
+    assert!(message.contains("rate limit 4321 remaining"), "{}", message);
+
+    let err = protect_branch_with_client(&client, "me/new-repo", "main")
+        .await
+        .unwrap_err();
+    assert!(err.is_not_found());
+    assert!(
+        err.to_string().ends_with(" [request id ABCD:0001]"),
+        "{}",
+        err
+    );
+}
@@ -0,0 +1,104 @@
+use github_client::{get_repo_default_branch_with_client, get_repo_with_client, GithubClient};
+use httpmock::prelude::*;
+use httpmock::Mock;
+use serde_json::json;
+
+fn mock_cached_repo<'a>(server: &'a MockServer, name: &str) -> (Mock<'a>, Mock<'a>) {
+    let first = server.mock(|when, then| {
+        when.method(GET)
+            .path(format!("/repos/me/{}", name))
+            .header_missing("if-none-match");
+        then.status(200)
+            .header("etag", format!("W/\"{}\"", name))
+            .json_body(json!({
+                "full_name": format!("me/{}", name),
+                "html_url": format!("https://github.com/me/{}", name),
+                "default_branch": "trunk"
+            }));
+    });
+    // GitHub sends 304 with an empty body
+    let revalidated = server.mock(|when, then| {
+        when.method(GET)
+            .path(format!("/repos/me/{}", name))
+            .header("if-none-match", format!("W/\"{}\"", name));
+        then.status(304);
+    });
+    (first, revalidated)
+}
+
+#[tokio::test]
+async fn not_modified_reuses_cached_body() {
+    let server = MockServer::start();
+    let (first, revalidated) = mock_cached_repo(&server, "cached");
+    let client = GithubClient::new(&server.base_url(), "testtoken").unwrap();
+
+    let repo = get_repo_with_client(&client, "me/cached").await.unwrap();
+    let again = get_repo_with_client(&client, "me/cached").await.unwrap();
+    let branch = get_repo_default_branch_with_client(&client, "me/cached")
+        .await
+        .unwrap();
+
+    assert_eq!(again.full_name, repo.full_name);
+    assert_eq!(branch, "trunk");
+    first.assert_calls(1);
+    revalidated.assert_calls(2);
+}
+
+#[tokio::test]
+async fn responses_without_etag_are_not_revalidated() {
+    let server = MockServer::start();
+    let plain = server.mock(|when, then| {
+        when.method(GET)
+            .path("/repos/me/uncached")
+            .header_missing("if-none-match");
+        then.status(200)
+            .json_body(json!({ "default_branch": "main" }));
+    });
+    let client = GithubClient::new(&server.base_url(), "testtoken").unwrap();
+
+    for _ in 0..2 {
+        let branch = get_repo_default_branch_with_client(&client, "me/uncached")
+            .await
+            .unwrap();
+        assert_eq!(branch, "main");
+    }
+    plain.assert_calls(2);
+}
+
+#[tokio::test]
+async fn clients_do_not_share_cached_bodies() {
+    let server = MockServer::start();
+    let (first, revalidated) = mock_cached_repo(&server, "cached");
+
+    for _ in 0..2 {
+        let client = GithubClient::new(&server.base_url(), "testtoken").unwrap();
+        get_repo_with_client(&client, "me/cached").await.unwrap();
+    }
+
+    first.assert_calls(2);
+    revalidated.assert_calls(0);
+}
+
+#[tokio::test]
+async fn least_recently_used_bodies_are_dropped_first() {
+    let server = MockServer::start();
+    let (a_first, a_revalidated) = mock_cached_repo(&server, "a");
+    let (b_first, b_revalidated) = mock_cached_repo(&server, "b");
+    let (c_first, _) = mock_cached_repo(&server, "c");
+    // Room for two of the ~90 byte bodies, not three
+    let client = GithubClient::builder(&server.base_url(), "testtoken")
+        .etag_cache_bytes(200)
+        .build()
+        .unwrap();
+
+    for name in ["me/a", "me/b", "me/a", "me/c", "me/a", "me/b"] {
+        get_repo_with_client(&client, name).await.unwrap();
+    }
+
+    // Adding c evicted b, the least recently used, but kept a
+    a_first.assert_calls(1);
+    a_revalidated.assert_calls(2);
+    b_first.assert_calls(2);
+    b_revalidated.assert_calls(0);
+    c_first.assert_calls(1);
+}
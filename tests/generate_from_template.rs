@@ -1,4 +1,7 @@
-use github_client::generate_from_template;
+use github_client::{
+    generate_from_template, generate_or_adopt, generate_with_selected_branches, GeneratedRepo,
+    GithubClientError,
+};
 use httpmock::prelude::*;
 
 #[tokio::test]
@@ -35,6 +38,7 @@ async fn creates_repo_successfully() {
         &api_base,
         token,
         &template_name,
+        None,
         repo_name,
         repo_desc,
         true,
@@ -67,6 +71,7 @@ async fn returns_error_on_api_failure() {
         &api_base,
         token,
         &template_name,
+        None,
         repo_name,
         repo_desc,
         false,
@@ -74,5 +79,313 @@ async fn returns_error_on_api_failure() {
     )
     .await;
 
-    assert!(res.is_err());
+    assert!(matches!(res, Err(GithubClientError::NotFound { .. })));
+}
+
+fn mock_already_exists(server: &MockServer, owner: &str, template: &str) {
+    server.mock(|when, then| {
+        when.method(POST)
+            .path(format!("/repos/{}/{}/generate", owner, template));
+        then.status(422)
+            .header("content-type", "application/json")
+            .json_body_obj(&serde_json::json!({
+                "message": "Repository creation failed.",
+                "errors": [{
+                    "resource": "Repository",
+                    "code": "custom",
+                    "field": "name",
+                    "message": "name already exists on this account"
+                }]
+            }));
+    });
+}
+
+#[tokio::test]
+async fn adopts_existing_repo_on_already_exists() {
+    let server = MockServer::start();
+    let owner = "owner";
+    let template = "template";
+    let token = "testtoken";
+    let repo_name = "new-repo";
+
+    mock_already_exists(&server, owner, template);
+    let _user = server.mock(|when, then| {
+        when.method(GET).path("/user");
+        then.status(200)
+            .json_body_obj(&serde_json::json!({ "login": "me" }));
+    });
+    let existing = server.mock(|when, then| {
+        when.method(GET).path(format!("/repos/me/{}", repo_name));
+        then.status(200).json_body_obj(&serde_json::json!({
+            "full_name": format!("me/{}", repo_name),
+            "html_url": format!("https://github.com/me/{}", repo_name),
+            "default_branch": "develop"
+        }));
+    });
+
+    let api_base = server.base_url();
+    let res = generate_or_adopt(
+        &api_base,
+        token,
+        &format!("{}/{}", owner, template),
+        None,
+        repo_name,
+        "desc",
+        true,
+        false,
+        true,
+    )
+    .await
+    .expect("existing repo should be adopted");
+
+    assert!(!res.was_created());
+    assert!(matches!(res, GeneratedRepo::Adopted(_)));
+    assert_eq!(res.repo().default_branch, "develop");
+    existing.assert();
+}
+
+#[tokio::test]
+async fn already_exists_fails_without_adopt() {
+    let server = MockServer::start();
+    let owner = "owner";
+    let template = "template";
+
+    mock_already_exists(&server, owner, template);
+
+    let api_base = server.base_url();
+    let err = generate_or_adopt(
+        &api_base,
+        "testtoken",
+        &format!("{}/{}", owner, template),
+        None,
+        "new-repo",
+        "desc",
+        true,
+        false,
+        false,
+    )
+    .await
+    .expect_err("should fail without adopt");
+
+    assert!(err.is_already_exists(), "{:?}", err);
+    assert!(matches!(err, GithubClientError::ValidationFailed { .. }));
+}
+
+#[tokio::test]
+async fn sends_owner_when_creating_in_org() {
+    let server = MockServer::start();
+    let m = server.mock(|when, then| {
+        when.method(POST)
+            .path("/repos/owner/template/generate")
+            .json_body_obj(&serde_json::json!({
+                "owner": "my-org",
+                "name": "new-repo",
+                "description": "desc",
+                "private": true,
+                "include_all_branches": false
+            }));
+        then.status(201).json_body_obj(&serde_json::json!({
+            "full_name": "my-org/new-repo",
+            "html_url": "https://github.com/my-org/new-repo",
+            "default_branch": "main"
+        }));
+    });
+
+    let api_base = server.base_url();
+    let res = generate_from_template(
+        &api_base,
+        "testtoken",
+        "owner/template",
+        Some("my-org"),
+        "new-repo",
+        "desc",
+        true,
+        false,
+    )
+    .await
+    .expect("should succeed");
+
+    assert_eq!(res.full_name, "my-org/new-repo");
+    m.assert();
+}
+
+#[tokio::test]
+async fn copies_only_the_selected_template_branches() {
+    let server = MockServer::start();
+    let token = "testtoken";
+
+    let _template = server.mock(|when, then| {
+        when.method(GET).path("/repos/org/tmpl");
+        then.status(200)
+            .json_body_obj(&serde_json::json!({ "default_branch": "main" }));
+    });
+    let _release_ref = server.mock(|when, then| {
+        when.method(GET)
+            .path("/repos/org/tmpl/git/ref/heads/release");
+        then.status(200)
+            .json_body_obj(&serde_json::json!({ "object": { "sha": "tr1" } }));
+    });
+    let _release_commit = server.mock(|when, then| {
+        when.method(GET).path("/repos/org/tmpl/git/commits/tr1");
+        then.status(200)
+            .json_body_obj(&serde_json::json!({ "tree": { "sha": "tt1" } }));
+    });
+    let generate = server.mock(|when, then| {
+        when.method(POST)
+            .path("/repos/org/tmpl/generate")
+            .json_body_obj(&serde_json::json!({
+                "name": "svc",
+                "description": "desc",
+                "private": true,
+                "include_all_branches": false
+            }));
+        then.status(201).json_body_obj(&serde_json::json!({
+            "full_name": "org/svc",
+            "html_url": "https://github.com/org/svc",
+            "default_branch": "main"
+        }));
+    });
+
+    // The new repository's default branch, which already has README.md
+    let _head = server.mock(|when, then| {
+        when.method(GET).path("/repos/org/svc/git/ref/heads/main");
+        then.status(200)
+            .json_body_obj(&serde_json::json!({ "object": { "sha": "head1" } }));
+    });
+    let _head_commit = server.mock(|when, then| {
+        when.method(GET).path("/repos/org/svc/git/commits/head1");
+        then.status(200)
+            .json_body_obj(&serde_json::json!({ "tree": { "sha": "tree1" } }));
+    });
+    let _ready = server.mock(|when, then| {
+        when.method(GET).path("/repos/org/svc/git/trees/main");
+        then.status(200).json_body_obj(&serde_json::json!({
+            "tree": [{ "path": "README.md", "type": "blob", "sha": "r1" }]
+        }));
+    });
+    let _head_tree = server.mock(|when, then| {
+        when.method(GET).path("/repos/org/svc/git/trees/tree1");
+        then.status(200).json_body_obj(&serde_json::json!({
+            "tree": [{ "path": "README.md", "type": "blob", "sha": "r1" }]
+        }));
+    });
+
+    let _release_tree = server.mock(|when, then| {
+        when.method(GET)
+            .path("/repos/org/tmpl/git/trees/release")
+            .query_param("recursive", "1");
+        then.status(200).json_body_obj(&serde_json::json!({
+            "tree": [
+                { "path": "README.md", "mode": "100644", "type": "blob", "sha": "r1" },
+                { "path": "deploy", "mode": "040000", "type": "tree", "sha": "d0" },
+                { "path": "deploy/run.sh", "mode": "100755", "type": "blob", "sha": "b2" }
+            ]
+        }));
+    });
+    let fetch_blob = server.mock(|when, then| {
+        when.method(GET).path("/repos/org/tmpl/git/blobs/b2");
+        then.status(200).json_body_obj(
+            &serde_json::json!({ "content": "cnVu", "encoding": "base64", "size": 3 }),
+        );
+    });
+    let create_blob = server.mock(|when, then| {
+        when.method(POST)
+            .path("/repos/org/svc/git/blobs")
+            .json_body_obj(&serde_json::json!({ "content": "cnVu", "encoding": "base64" }));
+        then.status(201)
+            .json_body_obj(&serde_json::json!({ "sha": "b2" }));
+    });
+    let create_tree = server.mock(|when, then| {
+        when.method(POST)
+            .path("/repos/org/svc/git/trees")
+            .json_body_obj(&serde_json::json!({
+                "tree": [
+                    { "path": "README.md", "mode": "100644", "type": "blob", "sha": "r1" },
+                    { "path": "deploy/run.sh", "mode": "100755", "type": "blob", "sha": "b2" }
+                ]
+            }));
+        then.status(201)
+            .json_body_obj(&serde_json::json!({ "sha": "newtree" }));
+    });
+    let create_commit = server.mock(|when, then| {
+        when.method(POST)
+            .path("/repos/org/svc/git/commits")
+            .json_body_obj(&serde_json::json!({
+                "message": "Copy branch 'release' from org/tmpl",
+                "tree": "newtree",
+                "parents": ["head1"]
+            }));
+        then.status(201)
+            .json_body_obj(&serde_json::json!({ "sha": "c1" }));
+    });
+    let create_ref = server.mock(|when, then| {
+        when.method(POST)
+            .path("/repos/org/svc/git/refs")
+            .json_body_obj(&serde_json::json!({ "ref": "refs/heads/release", "sha": "c1" }));
+        then.status(201)
+            .json_body_obj(&serde_json::json!({ "object": { "sha": "c1" } }));
+    });
+
+    let res = generate_with_selected_branches(
+        &server.base_url(),
+        token,
+        "org/tmpl",
+        None,
+        "svc",
+        "desc",
+        true,
+        &["main", "release"],
+    )
+    .await
+    .expect("should copy the release branch");
+
+    assert_eq!(res.full_name, "org/svc");
+    generate.assert();
+    fetch_blob.assert_calls(1);
+    create_blob.assert_calls(1);
+    create_tree.assert();
+    create_commit.assert();
+    create_ref.assert();
+}
+
+#[tokio::test]
+async fn unknown_template_branch_fails_before_generating() {
+    let server = MockServer::start();
+
+    let _template = server.mock(|when, then| {
+        when.method(GET).path("/repos/org/tmpl");
+        then.status(200)
+            .json_body_obj(&serde_json::json!({ "default_branch": "main" }));
+    });
+    let _missing = server.mock(|when, then| {
+        when.method(GET)
+            .path("/repos/org/tmpl/git/ref/heads/experimental");
+        then.status(404)
+            .json_body_obj(&serde_json::json!({ "message": "Not Found" }));
+    });
+    let generate = server.mock(|when, then| {
+        when.method(POST).path("/repos/org/tmpl/generate");
+        then.status(201);
+    });
+
+    let err = generate_with_selected_branches(
+        &server.base_url(),
+        "testtoken",
+        "org/tmpl",
+        None,
+        "svc",
+        "desc",
+        true,
+        &["experimental"],
+    )
+    .await
+    .expect_err("unknown branch must fail");
+
+    assert!(matches!(err, GithubClientError::NotFound { .. }), "{err}");
+    assert!(
+        err.to_string()
+            .contains("branch 'experimental' in template 'org/tmpl'"),
+        "{err}"
+    );
+    generate.assert_calls(0);
 }
@@ -0,0 +1,249 @@
+use github_client::{ensure_labels, sync_labels_from_repo, Label};
+use httpmock::prelude::*;
+use serde_json::json;
+
+fn label(name: &str, color: &str, description: &str) -> serde_json::Value {
+    json!({ "id": 1, "name": name, "color": color, "description": description, "default": false })
+}
+
+#[tokio::test]
+async fn creates_missing_and_updates_changed_labels() {
+    let server = MockServer::start();
+    let _source = server.mock(|when, then| {
+        when.method(GET).path("/repos/org/service-template/labels");
+        then.status(200).json_body(json!([
+            label("bug", "d73a4a", "Something isn't working"),
+            label("needs review ✓", "0e8a16", "Ready for review"),
+            label("docs", "0075ca", ""),
+        ]));
+    });
+    let _target = server.mock(|when, then| {
+        when.method(GET).path("/repos/me/new-repo/labels");
+        then.status(200).json_body(json!([
+            label("bug", "D73A4A", "Something isn't working"),
+            label("needs review ✓", "ededed", ""),
+        ]));
+    });
+    let create = server.mock(|when, then| {
+        when.method(POST)
+            .path("/repos/me/new-repo/labels")
+            .json_body(json!({ "name": "docs", "color": "0075ca", "description": "" }));
+        then.status(201).json_body(label("docs", "0075ca", ""));
+    });
+    let update = server.mock(|when, then| {
+        when.method(PATCH)
+            .path("/repos/me/new-repo/labels/needs%20review%20%E2%9C%93")
+            .json_body(json!({
+                "name": "needs review ✓",
+                "color": "0e8a16",
+                "description": "Ready for review"
+            }));
+        then.status(200)
+            .json_body(label("needs review ✓", "0e8a16", "Ready for review"));
+    });
+
+    let report = sync_labels_from_repo(
+        &server.base_url(),
+        "testtoken",
+        "org/service-template",
+        "me/new-repo",
+        false,
+    )
+    .await
+    .unwrap();
+
+    create.assert();
+    update.assert();
+    assert_eq!(report.created, vec!["docs"]);
+    assert_eq!(report.updated, vec!["needs review ✓"]);
+    assert!(report.deleted.is_empty());
+}
+
+#[tokio::test]
+async fn deletes_extra_labels_only_when_asked() {
+    let server = MockServer::start();
+    let _source = server.mock(|when, then| {
+        when.method(GET).path("/repos/org/service-template/labels");
+        then.status(200)
+            .json_body(json!([label("bug", "d73a4a", "")]));
+    });
+    let _target = server.mock(|when, then| {
+        when.method(GET).path("/repos/me/new-repo/labels");
+        then.status(200).json_body(json!([
+            label("bug", "d73a4a", ""),
+            label("good first issue", "7057ff", ""),
+        ]));
+    });
+    let delete = server.mock(|when, then| {
+        when.method(DELETE)
+            .path("/repos/me/new-repo/labels/good%20first%20issue");
+        then.status(204);
+    });
+
+    let report = sync_labels_from_repo(
+        &server.base_url(),
+        "testtoken",
+        "org/service-template",
+        "me/new-repo",
+        false,
+    )
+    .await
+    .unwrap();
+    assert!(report.deleted.is_empty());
+    delete.assert_calls(0);
+
+    let report = sync_labels_from_repo(
+        &server.base_url(),
+        "testtoken",
+        "org/service-template",
+        "me/new-repo",
+        true,
+    )
+    .await
+    .unwrap();
+    delete.assert();
+    assert_eq!(report.deleted, vec!["good first issue"]);
+    assert!(report.created.is_empty() && report.updated.is_empty());
+}
+
+#[tokio::test]
+async fn follows_source_pagination() {
+    let server = MockServer::start();
+    let first: Vec<_> = (0..100)
+        .map(|i| label(&format!("label-{}", i), "ededed", ""))
+        .collect();
+    let page1 = server.mock(|when, then| {
+        when.method(GET)
+            .path("/repos/org/service-template/labels")
+            .query_param("page", "1");
+        then.status(200).json_body(json!(first));
+    });
+    let page2 = server.mock(|when, then| {
+        when.method(GET)
+            .path("/repos/org/service-template/labels")
+            .query_param("page", "2");
+        then.status(200)
+            .json_body(json!([label("label-100", "ededed", "")]));
+    });
+    let _target = server.mock(|when, then| {
+        when.method(GET).path("/repos/me/new-repo/labels");
+        then.status(200).json_body(json!([]));
+    });
+    let create = server.mock(|when, then| {
+        when.method(POST).path("/repos/me/new-repo/labels");
+        then.status(201).json_body(label("x", "ededed", ""));
+    });
+
+    let report = sync_labels_from_repo(
+        &server.base_url(),
+        "testtoken",
+        "org/service-template",
+        "me/new-repo",
+        false,
+    )
+    .await
+    .unwrap();
+
+    page1.assert();
+    page2.assert();
+    create.assert_calls(101);
+    assert_eq!(report.created.len(), 101);
+    assert_eq!(report.created.last().map(String::as_str), Some("label-100"));
+}
+
+fn wanted(name: &str, color: &str, description: Option<&str>) -> Label {
+    Label {
+        name: name.into(),
+        color: color.into(),
+        description: description.map(Into::into),
+    }
+}
+
+#[tokio::test]
+async fn ensure_labels_creates_updates_and_skips_unchanged() {
+    let server = MockServer::start();
+    let _missing = server.mock(|when, then| {
+        when.method(GET).path("/repos/me/new-repo/labels/docs");
+        then.status(404)
+            .json_body(json!({ "message": "Not Found" }));
+    });
+    let _stale = server.mock(|when, then| {
+        when.method(GET)
+            .path("/repos/me/new-repo/labels/needs%20review");
+        then.status(200)
+            .json_body(label("needs review", "ededed", ""));
+    });
+    let _current = server.mock(|when, then| {
+        when.method(GET).path("/repos/me/new-repo/labels/bug");
+        then.status(200)
+            .json_body(label("bug", "d73a4a", "Something isn't working"));
+    });
+    let create = server.mock(|when, then| {
+        when.method(POST)
+            .path("/repos/me/new-repo/labels")
+            .json_body(json!({ "name": "docs", "color": "0075ca", "description": "" }));
+        then.status(201).json_body(label("docs", "0075ca", ""));
+    });
+    let update = server.mock(|when, then| {
+        when.method(PATCH)
+            .path("/repos/me/new-repo/labels/needs%20review")
+            .json_body(json!({
+                "name": "needs review",
+                "color": "0e8a16",
+                "description": "Ready for review"
+            }));
+        then.status(200)
+            .json_body(label("needs review", "0e8a16", "Ready for review"));
+    });
+
+    let report = ensure_labels(
+        &server.base_url(),
+        "testtoken",
+        "me/new-repo",
+        &[
+            wanted("docs", "#0075ca", None),
+            wanted("needs review", "0e8a16", Some("Ready for review")),
+            wanted("bug", "D73A4A", Some("Something isn't working")),
+        ],
+    )
+    .await
+    .unwrap();
+
+    create.assert();
+    update.assert();
+    assert_eq!(report.created, vec!["docs"]);
+    assert_eq!(report.updated, vec!["needs review"]);
+    assert!(report.deleted.is_empty());
+}
+
+#[tokio::test]
+async fn ensure_labels_is_a_no_op_when_in_sync() {
+    let server = MockServer::start();
+    let get = server.mock(|when, then| {
+        when.method(GET).path("/repos/me/new-repo/labels/bug");
+        then.status(200).json_body(label("bug", "d73a4a", ""));
+    });
+    let create = server.mock(|when, then| {
+        when.method(POST).path("/repos/me/new-repo/labels");
+        then.status(500);
+    });
+    let update = server.mock(|when, then| {
+        when.method(PATCH).path("/repos/me/new-repo/labels/bug");
+        then.status(500);
+    });
+
+    for _ in 0..2 {
+        let report = ensure_labels(
+            &server.base_url(),
+            "testtoken",
+            "me/new-repo",
+            &[wanted("bug", "d73a4a", None)],
+        )
+        .await
+        .unwrap();
+        assert_eq!(report, Default::default());
+    }
+    get.assert_calls(2);
+    create.assert_calls(0);
+    update.assert_calls(0);
+}
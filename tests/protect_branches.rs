@@ -0,0 +1,131 @@
+use github_client::{list_branches, protect_branches_matching, BranchProtectionOptions};
+use httpmock::prelude::*;
+use serde_json::json;
+
+fn branches(names: &[&str]) -> serde_json::Value {
+    names
+        .iter()
+        .map(|n| json!({ "name": n, "protected": false }))
+        .collect()
+}
+
+fn mock_two_pages(server: &MockServer) {
+    let next = format!(
+        "<{}/repos/me/new-repo/branches?per_page=100&page=2>; rel=\"next\", <{}/repos/me/new-repo/branches?per_page=100&page=2>; rel=\"last\"",
+        server.base_url(),
+        server.base_url()
+    );
+    server.mock(|when, then| {
+        when.method(GET)
+            .path("/repos/me/new-repo/branches")
+            .query_param("page", "2");
+        then.status(200)
+            .json_body(branches(&["release/1.1", "release/1.1/hotfix"]));
+    });
+    server.mock(|when, then| {
+        when.method(GET)
+            .path("/repos/me/new-repo/branches")
+            .query_param_missing("page");
+        then.status(200)
+            .header("link", next)
+            .json_body(branches(&["main", "dev", "release/1.0"]));
+    });
+}
+
+#[tokio::test]
+async fn lists_branches_across_pages() {
+    let server = MockServer::start();
+    mock_two_pages(&server);
+
+    let names = list_branches(&server.base_url(), "testtoken", "me/new-repo")
+        .await
+        .unwrap();
+    assert_eq!(
+        names,
+        vec![
+            "main",
+            "dev",
+            "release/1.0",
+            "release/1.1",
+            "release/1.1/hotfix"
+        ]
+    );
+}
+
+#[tokio::test]
+async fn protects_only_branches_matching_the_glob() {
+    let server = MockServer::start();
+    mock_two_pages(&server);
+    let protect = server.mock(|when, then| {
+        when.method(PUT)
+            .path_matches(r"^/repos/me/new-repo/branches/.+/protection$")
+            .json_body_includes(
+                r#"{ "required_status_checks": { "strict": true, "contexts": ["ci"] } }"#,
+            );
+        then.status(200);
+    });
+    let report = protect_branches_matching(
+        &server.base_url(),
+        "testtoken",
+        "me/new-repo",
+        &["release/*"],
+        &BranchProtectionOptions::with_checks(&["ci"]),
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(report.protected, vec!["release/1.0", "release/1.1"]);
+    assert!(report.failed.is_empty());
+    protect.assert_calls(2);
+}
+
+#[tokio::test]
+async fn reports_branches_github_refuses_to_protect() {
+    let server = MockServer::start();
+    mock_two_pages(&server);
+    let _main = server.mock(|when, then| {
+        when.method(PUT)
+            .path("/repos/me/new-repo/branches/main/protection");
+        then.status(200);
+    });
+    let _dev = server.mock(|when, then| {
+        when.method(PUT)
+            .path("/repos/me/new-repo/branches/dev/protection");
+        then.status(403)
+            .json_body(json!({ "message": "Upgrade to GitHub Pro" }));
+    });
+
+    let report = protect_branches_matching(
+        &server.base_url(),
+        "testtoken",
+        "me/new-repo",
+        &["main", "dev"],
+        &BranchProtectionOptions::default(),
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(report.protected, vec!["main"]);
+    assert_eq!(report.failed, vec!["dev"]);
+}
+
+#[tokio::test]
+async fn rejects_invalid_pattern_before_listing() {
+    let server = MockServer::start();
+    let list = server.mock(|when, then| {
+        when.method(GET).path("/repos/me/new-repo/branches");
+        then.status(200).json_body(json!([]));
+    });
+
+    let err = protect_branches_matching(
+        &server.base_url(),
+        "testtoken",
+        "me/new-repo",
+        &["release/[oops"],
+        &BranchProtectionOptions::default(),
+    )
+    .await
+    .unwrap_err();
+    assert!(err.to_string().contains("release/[oops"));
+    list.assert_calls(0);
+}
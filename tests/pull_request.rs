@@ -0,0 +1,107 @@
+use github_client::{create_pull_request, GithubClientError, PullRequest, PullRequestOutcome};
+use httpmock::prelude::*;
+use serde_json::json;
+
+#[tokio::test]
+async fn opens_draft_pull_request() {
+    let server = MockServer::start();
+    let m = server.mock(|when, then| {
+        when.method(POST)
+            .path("/repos/me/new-svc/pulls")
+            .json_body(json!({
+                "title": "Release: dev → main",
+                "head": "dev",
+                "base": "main",
+                "body": "Running release pull request.",
+                "draft": true
+            }));
+        then.status(201).json_body(json!({
+            "number": 1,
+            "html_url": "https://github.com/me/new-svc/pull/1",
+            "draft": true,
+            "state": "open"
+        }));
+    });
+
+    let pr = create_pull_request(
+        &server.base_url(),
+        "testtoken",
+        "me/new-svc",
+        "dev",
+        "main",
+        "Release: dev → main",
+        "Running release pull request.",
+        true,
+    )
+    .await
+    .unwrap();
+
+    m.assert();
+    assert_eq!(
+        pr,
+        PullRequestOutcome::Opened(PullRequest {
+            number: 1,
+            html_url: "https://github.com/me/new-svc/pull/1".into(),
+            draft: true,
+        })
+    );
+}
+
+#[tokio::test]
+async fn nothing_to_compare_is_not_an_error() {
+    let server = MockServer::start();
+    server.mock(|when, then| {
+        when.method(POST).path("/repos/me/new-svc/pulls");
+        then.status(422).json_body(json!({
+            "message": "Validation Failed",
+            "errors": [{
+                "resource": "PullRequest",
+                "code": "custom",
+                "message": "No commits between main and dev"
+            }]
+        }));
+    });
+
+    let pr = create_pull_request(
+        &server.base_url(),
+        "testtoken",
+        "me/new-svc",
+        "dev",
+        "main",
+        "Release",
+        "",
+        true,
+    )
+    .await
+    .unwrap();
+    assert_eq!(pr, PullRequestOutcome::NothingToCompare);
+}
+
+#[tokio::test]
+async fn missing_head_branch_is_not_found() {
+    let server = MockServer::start();
+    server.mock(|when, then| {
+        when.method(POST).path("/repos/me/new-svc/pulls");
+        then.status(404)
+            .json_body(json!({ "message": "Not Found" }));
+    });
+
+    let err = create_pull_request(
+        &server.base_url(),
+        "testtoken",
+        "me/new-svc",
+        "dev",
+        "main",
+        "Release",
+        "",
+        true,
+    )
+    .await
+    .unwrap_err();
+    match err {
+        GithubClientError::NotFound { resource } => {
+            assert!(resource.contains("branch 'dev'"), "{}", resource)
+        }
+        other => panic!("expected NotFound, got {:?}", other),
+    }
+}
@@ -0,0 +1,145 @@
+use github_client::{
+    ensure_branch, ensure_branch_protection, BranchProtectionOptions, ReconcileStatus,
+};
+use httpmock::prelude::*;
+
+const PROTECTION_PATH: &str = "/repos/me/new-repo/branches/main/protection";
+
+/// What GitHub returns for the policy `BranchProtectionOptions::with_checks(&["ci/build", "sonar"])` applies.
+fn current_protection(approvals: u8) -> serde_json::Value {
+    serde_json::json!({
+        "url": "https://api.github.com/repos/me/new-repo/branches/main/protection",
+        "required_status_checks": {
+            "strict": true,
+            "contexts": ["sonar", "ci/build"],
+            "checks": [{ "context": "sonar", "app_id": null }, { "context": "ci/build", "app_id": null }]
+        },
+        "required_pull_request_reviews": {
+            "dismiss_stale_reviews": true,
+            "require_code_owner_reviews": false,
+            "require_last_push_approval": true,
+            "required_approving_review_count": approvals
+        },
+        "required_signatures": { "enabled": false },
+        "enforce_admins": { "enabled": true },
+        "required_linear_history": { "enabled": true },
+        "allow_force_pushes": { "enabled": false },
+        "allow_deletions": { "enabled": false },
+        "block_creations": { "enabled": false },
+        "required_conversation_resolution": { "enabled": true },
+        "lock_branch": { "enabled": false },
+        "allow_fork_syncing": { "enabled": false }
+    })
+}
+
+#[tokio::test]
+async fn matching_protection_is_left_alone() {
+    let server = MockServer::start();
+    let _get = server.mock(|when, then| {
+        when.method(GET).path(PROTECTION_PATH);
+        then.status(200).json_body(current_protection(1));
+    });
+    let put = server.mock(|when, then| {
+        when.method(PUT).path(PROTECTION_PATH);
+        then.status(200);
+    });
+
+    let status = ensure_branch_protection(
+        &server.base_url(),
+        "testtoken",
+        "me/new-repo",
+        "main",
+        &BranchProtectionOptions::with_checks(&["ci/build", "sonar"]),
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(status, ReconcileStatus::Compliant);
+    put.assert_calls(0);
+}
+
+#[tokio::test]
+async fn drifted_protection_is_updated() {
+    let server = MockServer::start();
+    let _get = server.mock(|when, then| {
+        when.method(GET).path(PROTECTION_PATH);
+        then.status(200).json_body(current_protection(0));
+    });
+    let put = server.mock(|when, then| {
+        when.method(PUT).path(PROTECTION_PATH).json_body_includes(
+            r#"{ "required_pull_request_reviews": { "required_approving_review_count": 1 } }"#,
+        );
+        then.status(200);
+    });
+
+    let status = ensure_branch_protection(
+        &server.base_url(),
+        "testtoken",
+        "me/new-repo",
+        "main",
+        &BranchProtectionOptions::with_checks(&["ci/build", "sonar"]),
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(status, ReconcileStatus::Changed);
+    put.assert();
+}
+
+#[tokio::test]
+async fn unprotected_branch_gets_protected() {
+    let server = MockServer::start();
+    let _get = server.mock(|when, then| {
+        when.method(GET).path(PROTECTION_PATH);
+        then.status(404)
+            .json_body(serde_json::json!({ "message": "Branch not protected" }));
+    });
+    let _branch = server.mock(|when, then| {
+        when.method(GET).path("/repos/me/new-repo/branches/main");
+        then.status(200);
+    });
+    let put = server.mock(|when, then| {
+        when.method(PUT).path(PROTECTION_PATH);
+        then.status(200);
+    });
+
+    let status = ensure_branch_protection(
+        &server.base_url(),
+        "testtoken",
+        "me/new-repo",
+        "main",
+        &BranchProtectionOptions::default(),
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(status, ReconcileStatus::Changed);
+    put.assert();
+}
+
+#[tokio::test]
+async fn existing_branch_is_not_recreated() {
+    let server = MockServer::start();
+    let _dev = server.mock(|when, then| {
+        when.method(GET).path("/repos/me/new-repo/branches/dev");
+        then.status(200)
+            .json_body(serde_json::json!({ "name": "dev" }));
+    });
+    let create = server.mock(|when, then| {
+        when.method(POST).path("/repos/me/new-repo/git/refs");
+        then.status(201);
+    });
+
+    let status = ensure_branch(
+        &server.base_url(),
+        "testtoken",
+        "me/new-repo",
+        "main",
+        "dev",
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(status, ReconcileStatus::Compliant);
+    create.assert_calls(0);
+}
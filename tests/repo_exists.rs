@@ -0,0 +1,63 @@
+use github_client::{
+    get_authenticated_user, repo_exists_with_client, GithubClient, GithubClientError,
+};
+use httpmock::prelude::*;
+
+/// 5xx responses here stand for a failure, not an outage; skip the backoff.
+fn without_retries(api_base: &str, token: &str) -> GithubClient {
+    GithubClient::builder(api_base, token)
+        .max_retries(0)
+        .build()
+        .unwrap()
+}
+
+#[tokio::test]
+async fn repo_exists_maps_status_codes() {
+    let server = MockServer::start();
+
+    let _found = server.mock(|when, then| {
+        when.method(GET).path("/repos/me/taken");
+        then.status(200).json_body_obj(&serde_json::json!({
+            "full_name": "me/taken"
+        }));
+    });
+    let _missing = server.mock(|when, then| {
+        when.method(GET).path("/repos/me/free");
+        then.status(404).body("{\"message\":\"Not Found\"}");
+    });
+    let _broken = server.mock(|when, then| {
+        when.method(GET).path("/repos/me/broken");
+        then.status(500).body("oops");
+    });
+
+    let client = without_retries(&server.base_url(), "testtoken");
+    assert!(repo_exists_with_client(&client, "me/taken").await.unwrap());
+    assert!(!repo_exists_with_client(&client, "me/free").await.unwrap());
+    let err = repo_exists_with_client(&client, "me/broken")
+        .await
+        .unwrap_err();
+    assert!(matches!(
+        err,
+        GithubClientError::Unexpected { status: 500, .. }
+    ));
+    assert!(err.is_retryable());
+}
+
+#[tokio::test]
+async fn reads_authenticated_user_login() {
+    let server = MockServer::start();
+
+    let _user = server.mock(|when, then| {
+        when.method(GET)
+            .path("/user")
+            .header("authorization", "Bearer testtoken");
+        then.status(200)
+            .json_body_obj(&serde_json::json!({ "login": "octocat" }));
+    });
+
+    let api_base = server.base_url();
+    let login = get_authenticated_user(&api_base, "testtoken")
+        .await
+        .unwrap();
+    assert_eq!(login, "octocat");
+}
@@ -0,0 +1,80 @@
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use github_client::{set_repo_secret, GithubClientError};
+use httpmock::prelude::*;
+
+#[tokio::test]
+async fn sets_repo_secret_with_encrypted_value() {
+    let server = MockServer::start();
+    let owner = "me";
+    let repo = "new-repo";
+    let token = "testtoken";
+
+    let key = server.mock(|when, then| {
+        when.method(GET)
+            .path(format!(
+                "/repos/{}/{}/actions/secrets/public-key",
+                owner, repo
+            ))
+            .header("authorization", "Bearer testtoken");
+        then.status(200).json_body_obj(&serde_json::json!({
+            "key_id": "568250167242549743",
+            "key": "CQkJCQkJCQkJCQkJCQkJCQkJCQkJCQkJCQkJCQkJCQk="
+        }));
+    });
+    let put = server.mock(|when, then| {
+        when.method(PUT)
+            .path(format!(
+                "/repos/{}/{}/actions/secrets/SONAR_TOKEN",
+                owner, repo
+            ))
+            .json_body_includes("{\"key_id\":\"568250167242549743\"}")
+            .is_true(|req: &HttpMockRequest| {
+                let body: serde_json::Value =
+                    serde_json::from_slice(req.body().as_ref()).unwrap_or_default();
+                body["encrypted_value"]
+                    .as_str()
+                    .map(|v| !v.is_empty() && BASE64.decode(v).is_ok())
+                    .unwrap_or(false)
+            });
+        then.status(201);
+    });
+
+    let api_base = server.base_url();
+    let res = set_repo_secret(
+        &api_base,
+        token,
+        &format!("{}/{}", owner, repo),
+        "SONAR_TOKEN",
+        "squ_abc123",
+    )
+    .await;
+    assert!(res.is_ok());
+    key.assert();
+    put.assert();
+}
+
+#[tokio::test]
+async fn invalid_secret_name_fails_before_any_request() {
+    let server = MockServer::start();
+    let owner = "me";
+    let repo = "new-repo";
+    let token = "testtoken";
+
+    let any = server.mock(|when, then| {
+        when.any_request();
+        then.status(200);
+    });
+
+    let api_base = server.base_url();
+    let res = set_repo_secret(
+        &api_base,
+        token,
+        &format!("{}/{}", owner, repo),
+        "GITHUB_DEPLOY",
+        "value",
+    )
+    .await;
+    assert!(matches!(res, Err(GithubClientError::InvalidInput(_))));
+    any.assert_calls(0);
+}
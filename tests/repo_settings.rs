@@ -0,0 +1,186 @@
+use github_client::{
+    set_repo_archived, set_repo_features, update_repo_settings, GithubClientError, RepoFeatures,
+    RepoSettings,
+};
+use httpmock::prelude::*;
+
+#[tokio::test]
+async fn patches_only_set_fields() {
+    let server = MockServer::start();
+    let owner = "me";
+    let repo = "new-repo";
+    let token = "testtoken";
+
+    let m = server.mock(|when, then| {
+        when.method(PATCH)
+            .path(format!("/repos/{}/{}", owner, repo))
+            .header("authorization", "Bearer testtoken")
+            .json_body_obj(&serde_json::json!({
+                "allow_squash_merge": true,
+                "allow_merge_commit": false,
+                "allow_rebase_merge": false,
+                "delete_branch_on_merge": true
+            }));
+        then.status(200).json_body_obj(&serde_json::json!({}));
+    });
+
+    let settings = RepoSettings {
+        allow_squash_merge: Some(true),
+        allow_merge_commit: Some(false),
+        allow_rebase_merge: Some(false),
+        delete_branch_on_merge: Some(true),
+        ..Default::default()
+    };
+
+    let api_base = server.base_url();
+    let res =
+        update_repo_settings(&api_base, token, &format!("{}/{}", owner, repo), &settings).await;
+    assert!(res.is_ok());
+    m.assert();
+}
+
+#[tokio::test]
+async fn update_repo_settings_returns_error_on_failure() {
+    let server = MockServer::start();
+    let owner = "me";
+    let repo = "new-repo";
+    let token = "testtoken";
+
+    let _m = server.mock(|when, then| {
+        when.method(PATCH)
+            .path(format!("/repos/{}/{}", owner, repo));
+        then.status(403).body("{\"message\":\"forbidden\"}");
+    });
+
+    let settings = RepoSettings {
+        has_wiki: Some(false),
+        ..Default::default()
+    };
+
+    let api_base = server.base_url();
+    let res =
+        update_repo_settings(&api_base, token, &format!("{}/{}", owner, repo), &settings).await;
+    assert!(matches!(res, Err(GithubClientError::Forbidden { .. })));
+}
+
+#[tokio::test]
+async fn set_repo_features_sends_only_given_features() {
+    let server = MockServer::start();
+
+    let m = server.mock(|when, then| {
+        when.method(PATCH)
+            .path("/repos/me/new-repo")
+            .json_body_obj(&serde_json::json!({
+                "has_issues": true,
+                "has_wiki": false,
+                "has_projects": false
+            }));
+        then.status(200).json_body_obj(&serde_json::json!({}));
+    });
+
+    let features = RepoFeatures {
+        has_issues: Some(true),
+        has_wiki: Some(false),
+        has_projects: Some(false),
+        ..Default::default()
+    };
+    set_repo_features(&server.base_url(), "testtoken", "me/new-repo", &features)
+        .await
+        .unwrap();
+    m.assert();
+
+    // Nothing to change, nothing sent
+    set_repo_features(
+        &server.base_url(),
+        "testtoken",
+        "me/new-repo",
+        &RepoFeatures::default(),
+    )
+    .await
+    .unwrap();
+    m.assert_calls(1);
+}
+
+#[tokio::test]
+async fn merge_settings_and_features_go_out_in_one_patch() {
+    let server = MockServer::start();
+
+    let m = server.mock(|when, then| {
+        when.method(PATCH)
+            .path("/repos/me/new-repo")
+            .json_body_obj(&serde_json::json!({
+                "allow_squash_merge": true,
+                "has_wiki": false,
+                "has_discussions": true
+            }));
+        then.status(200).json_body_obj(&serde_json::json!({}));
+    });
+
+    let mut settings = RepoSettings {
+        allow_squash_merge: Some(true),
+        ..Default::default()
+    };
+    settings.apply_features(&RepoFeatures {
+        has_wiki: Some(false),
+        has_discussions: Some(true),
+        ..Default::default()
+    });
+    update_repo_settings(&server.base_url(), "testtoken", "me/new-repo", &settings)
+        .await
+        .unwrap();
+    m.assert();
+}
+
+#[tokio::test]
+async fn set_repo_archived_patches_the_archived_flag() {
+    let server = MockServer::start();
+
+    let archive = server.mock(|when, then| {
+        when.method(PATCH)
+            .path("/repos/me/old-svc")
+            .json_body_obj(&serde_json::json!({ "archived": true }));
+        then.status(200).json_body_obj(&serde_json::json!({}));
+    });
+    let unarchive = server.mock(|when, then| {
+        when.method(PATCH)
+            .path("/repos/me/old-svc")
+            .json_body_obj(&serde_json::json!({ "archived": false }));
+        then.status(200).json_body_obj(&serde_json::json!({}));
+    });
+
+    let api_base = server.base_url();
+    set_repo_archived(&api_base, "testtoken", "me/old-svc", true)
+        .await
+        .unwrap();
+    set_repo_archived(&api_base, "testtoken", "me/old-svc", false)
+        .await
+        .unwrap();
+    archive.assert();
+    unarchive.assert();
+}
+
+#[tokio::test]
+async fn writes_to_an_archived_repository_say_so() {
+    let server = MockServer::start();
+
+    let _m = server.mock(|when, then| {
+        when.method(PATCH).path("/repos/me/old-svc");
+        then.status(403)
+            .body(r#"{"message":"Repository was archived so is read-only."}"#);
+    });
+
+    let settings = RepoSettings {
+        has_wiki: Some(false),
+        ..Default::default()
+    };
+    let err = update_repo_settings(&server.base_url(), "testtoken", "me/old-svc", &settings)
+        .await
+        .unwrap_err();
+    assert!(err.is_archived(), "{:?}", err);
+    assert!(
+        err.to_string()
+            .starts_with("Repository is archived: cannot update repository settings for 'me/old-svc'; unarchive it first"),
+        "{}",
+        err
+    );
+}
@@ -0,0 +1,92 @@
+use github_client::{set_environment_variable, set_repo_variable, GithubClientError};
+use httpmock::prelude::*;
+
+#[tokio::test]
+async fn creates_repo_variable() {
+    let server = MockServer::start();
+    let m = server.mock(|when, then| {
+        when.method(POST)
+            .path("/repos/me/new-repo/actions/variables")
+            .header("authorization", "Bearer testtoken")
+            .json_body_obj(&serde_json::json!({
+                "name": "ENVIRONMENT_TIER",
+                "value": "gold"
+            }));
+        then.status(201);
+    });
+
+    let api_base = server.base_url();
+    set_repo_variable(
+        &api_base,
+        "testtoken",
+        "me/new-repo",
+        "ENVIRONMENT_TIER",
+        "gold",
+    )
+    .await
+    .unwrap();
+    m.assert();
+}
+
+#[tokio::test]
+async fn updates_existing_repo_variable_after_conflict() {
+    let server = MockServer::start();
+    let create = server.mock(|when, then| {
+        when.method(POST)
+            .path("/repos/me/new-repo/actions/variables");
+        then.status(409)
+            .body("{\"message\":\"Already exists - Variable already exists\"}");
+    });
+    let update = server.mock(|when, then| {
+        when.method(PATCH)
+            .path("/repos/me/new-repo/actions/variables/AWS_REGION")
+            .json_body_obj(&serde_json::json!({
+                "name": "AWS_REGION",
+                "value": "eu-west-1"
+            }));
+        then.status(204);
+    });
+
+    let api_base = server.base_url();
+    set_repo_variable(
+        &api_base,
+        "testtoken",
+        "me/new-repo",
+        "AWS_REGION",
+        "eu-west-1",
+    )
+    .await
+    .unwrap();
+    create.assert();
+    update.assert();
+}
+
+#[tokio::test]
+async fn rejects_invalid_variable_names_before_any_request() {
+    let server = MockServer::start();
+    let any = server.mock(|when, then| {
+        when.any_request();
+        then.status(201);
+    });
+
+    let api_base = server.base_url();
+    for name in ["aws_region", "GITHUB_REF", "MY-VAR"] {
+        let res = set_repo_variable(&api_base, "testtoken", "me/new-repo", name, "x").await;
+        assert!(
+            matches!(res, Err(GithubClientError::InvalidInput(_))),
+            "{}",
+            name
+        );
+    }
+    let res = set_environment_variable(
+        &api_base,
+        "testtoken",
+        "me/new-repo",
+        "dev",
+        "GITHUB_X",
+        "x",
+    )
+    .await;
+    assert!(matches!(res, Err(GithubClientError::InvalidInput(_))));
+    any.assert_calls(0);
+}
@@ -0,0 +1,126 @@
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::Duration;
+
+use github_client::{
+    generate_from_template_with_client, retry, update_repo_settings_with_client, GithubClient,
+    GithubClientError, RepoSettings,
+};
+use httpmock::prelude::*;
+
+fn fast_retries(server: &MockServer) -> GithubClient {
+    GithubClient::builder(&server.base_url(), "testtoken")
+        .max_retries(2)
+        .retry_base_delay(Duration::from_millis(1))
+        .build()
+        .unwrap()
+}
+
+#[tokio::test]
+async fn retries_transient_errors_until_success() {
+    let calls = AtomicU32::new(0);
+    let res = retry(5, Duration::from_millis(1), |attempt| {
+        calls.fetch_add(1, Ordering::SeqCst);
+        async move {
+            if attempt < 3 {
+                Err(GithubClientError::Unexpected {
+                    status: 503,
+                    body: "unavailable".to_string(),
+                })
+            } else {
+                Ok(attempt)
+            }
+        }
+    })
+    .await;
+    assert_eq!(res.unwrap(), 3);
+    assert_eq!(calls.load(Ordering::SeqCst), 3);
+}
+
+#[tokio::test]
+async fn does_not_retry_permanent_errors() {
+    let calls = AtomicU32::new(0);
+    let res: Result<(), _> = retry(5, Duration::from_millis(1), |_| {
+        calls.fetch_add(1, Ordering::SeqCst);
+        async {
+            Err(GithubClientError::NotFound {
+                resource: "/repos/me/nope".to_string(),
+            })
+        }
+    })
+    .await;
+    assert!(res.unwrap_err().is_not_found());
+    assert_eq!(calls.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn persistent_5xx_is_retried_then_reported() {
+    let server = MockServer::start();
+    let client = fast_retries(&server);
+
+    let m = server.mock(|when, then| {
+        when.method(PATCH).path("/repos/me/new-repo");
+        then.status(503)
+            .body("{\"message\":\"Service Unavailable\"}");
+    });
+
+    let settings = RepoSettings {
+        has_wiki: Some(false),
+        ..Default::default()
+    };
+    let res = update_repo_settings_with_client(&client, "me/new-repo", &settings).await;
+    assert!(matches!(
+        res,
+        Err(GithubClientError::Unexpected { status: 503, .. })
+    ));
+    m.assert_calls(3);
+}
+
+#[tokio::test]
+async fn validation_errors_are_not_retried() {
+    let server = MockServer::start();
+    let client = fast_retries(&server);
+
+    let m = server.mock(|when, then| {
+        when.method(PATCH).path("/repos/me/new-repo");
+        then.status(422).body("{\"message\":\"Validation Failed\"}");
+    });
+
+    let settings = RepoSettings {
+        has_wiki: Some(false),
+        ..Default::default()
+    };
+    let res = update_repo_settings_with_client(&client, "me/new-repo", &settings).await;
+    assert!(matches!(
+        res,
+        Err(GithubClientError::ValidationFailed { .. })
+    ));
+    m.assert_calls(1);
+}
+
+#[tokio::test]
+async fn failed_posts_are_not_resent() {
+    let server = MockServer::start();
+    let client = fast_retries(&server);
+
+    // GitHub may have created the repository before the gateway gave up
+    let m = server.mock(|when, then| {
+        when.method(POST).path("/repos/org/template/generate");
+        then.status(502).body("{\"message\":\"Bad Gateway\"}");
+    });
+
+    let res = generate_from_template_with_client(
+        &client,
+        "org/template",
+        None,
+        "new-repo",
+        "",
+        true,
+        false,
+    )
+    .await;
+    assert!(matches!(
+        res,
+        Err(GithubClientError::Unexpected { status: 502, .. })
+    ));
+    m.assert_calls(1);
+}
@@ -0,0 +1,192 @@
+use github_client::{
+    delete_repo, rollback_on_failure, rollback_on_failure_with_client,
+    seed_dirs_as_single_commit_with_client, CopyOptions, CopySelector, GithubClient,
+    GithubClientError,
+};
+use httpmock::prelude::*;
+
+/// 5xx responses here stand for a failure, not an outage; skip the backoff.
+fn without_retries(api_base: &str, token: &str) -> GithubClient {
+    GithubClient::builder(api_base, token)
+        .max_retries(0)
+        .build()
+        .unwrap()
+}
+
+fn mock_failing_seed_source(server: &MockServer) {
+    server.mock(|when, then| {
+        when.method(GET).path("/repos/org/service-template");
+        then.status(500).body("{\"message\":\"Server Error\"}");
+    });
+}
+
+#[tokio::test]
+async fn deletes_repo_when_seeding_fails() {
+    let server = MockServer::start();
+    mock_failing_seed_source(&server);
+    let delete = server.mock(|when, then| {
+        when.method(DELETE)
+            .path("/repos/org/new-svc")
+            .header("authorization", "Bearer testtoken");
+        then.status(204);
+    });
+
+    let client = without_retries(&server.base_url(), "testtoken");
+    let res = rollback_on_failure_with_client(
+        &client,
+        "org/new-svc",
+        true,
+        seed_dirs_as_single_commit_with_client(
+            &client,
+            "org/service-template",
+            "org/new-svc",
+            "main",
+            &CopySelector::prefixes(&["terraform/"]),
+            &CopyOptions::default(),
+        ),
+    )
+    .await;
+
+    match res.expect_err("seeding failure must still be returned") {
+        GithubClientError::RolledBack { full_name, source } => {
+            assert_eq!(full_name, "org/new-svc");
+            assert!(
+                matches!(*source, GithubClientError::Unexpected { status: 500, .. }),
+                "{:?}",
+                source
+            );
+        }
+        other => panic!("expected RolledBack, got {:?}", other),
+    }
+    delete.assert();
+}
+
+#[tokio::test]
+async fn keeps_repo_when_rollback_disabled() {
+    let server = MockServer::start();
+    mock_failing_seed_source(&server);
+    let delete = server.mock(|when, then| {
+        when.method(DELETE).path("/repos/org/new-svc");
+        then.status(204);
+    });
+
+    let client = without_retries(&server.base_url(), "testtoken");
+    let res = rollback_on_failure_with_client(
+        &client,
+        "org/new-svc",
+        false,
+        seed_dirs_as_single_commit_with_client(
+            &client,
+            "org/service-template",
+            "org/new-svc",
+            "main",
+            &CopySelector::prefixes(&["terraform/"]),
+            &CopyOptions::default(),
+        ),
+    )
+    .await;
+
+    assert!(res.is_err());
+    delete.assert_calls(0);
+}
+
+#[tokio::test]
+async fn reports_failed_delete_alongside_original_error() {
+    let server = MockServer::start();
+    mock_failing_seed_source(&server);
+    let delete = server.mock(|when, then| {
+        when.method(DELETE).path("/repos/org/new-svc");
+        then.status(403)
+            .body("{\"message\":\"Must have admin rights to Repository.\"}");
+    });
+
+    let client = without_retries(&server.base_url(), "testtoken");
+    let res = rollback_on_failure_with_client(
+        &client,
+        "org/new-svc",
+        true,
+        seed_dirs_as_single_commit_with_client(
+            &client,
+            "org/service-template",
+            "org/new-svc",
+            "main",
+            &CopySelector::prefixes(&["terraform/"]),
+            &CopyOptions::default(),
+        ),
+    )
+    .await;
+
+    match res.expect_err("should fail") {
+        GithubClientError::RollbackFailed {
+            delete_error,
+            source,
+            ..
+        } => {
+            assert!(
+                matches!(*delete_error, GithubClientError::Forbidden { .. }),
+                "{:?}",
+                delete_error
+            );
+            assert!(
+                matches!(*source, GithubClientError::Unexpected { status: 500, .. }),
+                "{:?}",
+                source
+            );
+        }
+        other => panic!("expected RollbackFailed, got {:?}", other),
+    }
+    delete.assert();
+}
+
+#[tokio::test]
+async fn leaves_successful_run_alone() {
+    let server = MockServer::start();
+    let delete = server.mock(|when, then| {
+        when.method(DELETE).path("/repos/org/new-svc");
+        then.status(204);
+    });
+
+    let res = rollback_on_failure(
+        &server.base_url(),
+        "testtoken",
+        "org/new-svc",
+        true,
+        async { Ok::<_, GithubClientError>(7) },
+    )
+    .await;
+
+    assert_eq!(res.unwrap(), 7);
+    delete.assert_calls(0);
+}
+
+#[tokio::test]
+async fn delete_repo_treats_missing_repo_as_deleted() {
+    let server = MockServer::start();
+    let delete = server.mock(|when, then| {
+        when.method(DELETE).path("/repos/org/gone");
+        then.status(404).body("{\"message\":\"Not Found\"}");
+    });
+
+    let res = delete_repo(&server.base_url(), "testtoken", "org/gone").await;
+    assert!(res.is_ok());
+    delete.assert();
+}
+
+#[tokio::test]
+async fn delete_repo_explains_missing_scope_on_403() {
+    let server = MockServer::start();
+    let _delete = server.mock(|when, then| {
+        when.method(DELETE).path("/repos/org/new-svc");
+        then.status(403)
+            .body("{\"message\":\"Must have admin rights to Repository.\"}");
+    });
+
+    let err = delete_repo(&server.base_url(), "testtoken", "org/new-svc")
+        .await
+        .expect_err("403 should fail");
+    assert!(
+        matches!(&err, GithubClientError::Forbidden { message } if message.contains("delete_repo scope")),
+        "{:?}",
+        err
+    );
+}
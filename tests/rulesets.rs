@@ -0,0 +1,153 @@
+use github_client::{create_ruleset, GithubClientError, Ruleset};
+use httpmock::prelude::*;
+
+#[test]
+fn default_branch_mapping_matches_legacy_protection() {
+    let body = serde_json::to_value(Ruleset::default_branch(&["branch-policy"])).unwrap();
+    assert_eq!(
+        body,
+        serde_json::json!({
+            "name": "default-branch-protection",
+            "target": "branch",
+            "enforcement": "active",
+            "conditions": {
+                "ref_name": { "include": ["~DEFAULT_BRANCH"], "exclude": [] }
+            },
+            "rules": [
+                { "type": "deletion" },
+                { "type": "non_fast_forward" },
+                { "type": "required_linear_history" },
+                {
+                    "type": "pull_request",
+                    "parameters": {
+                        "required_approving_review_count": 1,
+                        "dismiss_stale_reviews_on_push": true,
+                        "require_code_owner_review": false,
+                        "require_last_push_approval": true,
+                        "required_review_thread_resolution": true
+                    }
+                },
+                {
+                    "type": "required_status_checks",
+                    "parameters": {
+                        "strict_required_status_checks_policy": true,
+                        "required_status_checks": [{ "context": "branch-policy" }]
+                    }
+                }
+            ]
+        })
+    );
+}
+
+#[test]
+fn default_branch_mapping_omits_status_checks_without_contexts() {
+    let ruleset = Ruleset::default_branch(&[]);
+    let body = serde_json::to_value(&ruleset).unwrap();
+    let types: Vec<&str> = body["rules"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|r| r["type"].as_str().unwrap())
+        .collect();
+    assert!(!types.contains(&"required_status_checks"));
+}
+
+#[tokio::test]
+async fn creates_ruleset() {
+    let server = MockServer::start();
+    let owner = "me";
+    let repo = "new-repo";
+    let token = "testtoken";
+    let ruleset = Ruleset::default_branch(&[]);
+
+    let m = server.mock(|when, then| {
+        when.method(POST)
+            .path(format!("/repos/{}/{}/rulesets", owner, repo))
+            .header("authorization", "Bearer testtoken")
+            .json_body(serde_json::to_value(&ruleset).unwrap());
+        then.status(201).json_body_obj(&serde_json::json!({
+            "id": 42,
+            "name": "default-branch-protection"
+        }));
+    });
+
+    let api_base = server.base_url();
+    let id = create_ruleset(&api_base, token, &format!("{}/{}", owner, repo), &ruleset)
+        .await
+        .expect("ruleset should be created");
+    assert_eq!(id, 42);
+    m.assert();
+}
+
+#[tokio::test]
+async fn updates_existing_ruleset_with_same_name() {
+    let server = MockServer::start();
+    let owner = "me";
+    let repo = "new-repo";
+    let token = "testtoken";
+    let ruleset = Ruleset::default_branch(&["branch-policy"]);
+
+    let _create = server.mock(|when, then| {
+        when.method(POST)
+            .path(format!("/repos/{}/{}/rulesets", owner, repo));
+        then.status(422)
+            .body("{\"message\":\"Validation Failed\",\"errors\":[\"Name must be unique\"]}");
+    });
+    let list = server.mock(|when, then| {
+        when.method(GET)
+            .path(format!("/repos/{}/{}/rulesets", owner, repo));
+        then.status(200).json_body_obj(&serde_json::json!([
+            { "id": 7, "name": "tags" },
+            { "id": 9, "name": "default-branch-protection" }
+        ]));
+    });
+    let update = server.mock(|when, then| {
+        when.method(PUT)
+            .path(format!("/repos/{}/{}/rulesets/9", owner, repo))
+            .json_body(serde_json::to_value(&ruleset).unwrap());
+        then.status(200).json_body_obj(&serde_json::json!({
+            "id": 9,
+            "name": "default-branch-protection"
+        }));
+    });
+
+    let api_base = server.base_url();
+    let id = create_ruleset(&api_base, token, &format!("{}/{}", owner, repo), &ruleset)
+        .await
+        .expect("existing ruleset should be updated");
+    assert_eq!(id, 9);
+    list.assert();
+    update.assert();
+}
+
+#[tokio::test]
+async fn create_ruleset_fails_on_unrelated_422() {
+    let server = MockServer::start();
+    let owner = "me";
+    let repo = "new-repo";
+    let token = "testtoken";
+
+    let _create = server.mock(|when, then| {
+        when.method(POST)
+            .path(format!("/repos/{}/{}/rulesets", owner, repo));
+        then.status(422).body("{\"message\":\"Validation Failed\"}");
+    });
+    let _list = server.mock(|when, then| {
+        when.method(GET)
+            .path(format!("/repos/{}/{}/rulesets", owner, repo));
+        then.status(200).json_body_obj(&serde_json::json!([]));
+    });
+
+    let api_base = server.base_url();
+    let res = create_ruleset(
+        &api_base,
+        token,
+        &format!("{}/{}", owner, repo),
+        &Ruleset::default_branch(&[]),
+    )
+    .await;
+    assert!(matches!(
+        res,
+        Err(GithubClientError::ValidationFailed { .. })
+    ));
+}
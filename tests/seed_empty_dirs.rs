@@ -0,0 +1,80 @@
+use github_client::seed_empty_dirs;
+use httpmock::prelude::*;
+
+#[tokio::test]
+async fn writes_gitkeep_for_each_dir() {
+    let server = MockServer::start();
+    let owner = "me";
+    let repo = "new-repo";
+    let branch = "main";
+    let token = "testtoken";
+
+    // Nothing exists yet
+    let _missing = server.mock(|when, then| {
+        when.method(GET)
+            .path_prefix(format!("/repos/{}/{}/contents/", owner, repo))
+            .query_param("ref", branch);
+        then.status(404);
+    });
+
+    let logs = server.mock(|when, then| {
+        when.method(PUT)
+            .path(format!("/repos/{}/{}/contents/logs/.gitkeep", owner, repo))
+            .json_body_obj(&serde_json::json!({
+                "message": "chore: add logs/.gitkeep",
+                "content": "",
+                "branch": branch
+            }));
+        then.status(201);
+    });
+    let tmp = server.mock(|when, then| {
+        when.method(PUT)
+            .path(format!("/repos/{}/{}/contents/tmp/.gitkeep", owner, repo));
+        then.status(201);
+    });
+
+    let api_base = server.base_url();
+    let res = seed_empty_dirs(
+        &api_base,
+        token,
+        &format!("{}/{}", owner, repo),
+        branch,
+        &["logs/", "tmp/"],
+    )
+    .await;
+    assert!(res.is_ok());
+    logs.assert();
+    tmp.assert();
+}
+
+#[tokio::test]
+async fn skips_existing_gitkeep() {
+    let server = MockServer::start();
+    let owner = "me";
+    let repo = "new-repo";
+    let branch = "main";
+    let token = "testtoken";
+
+    let _exists = server.mock(|when, then| {
+        when.method(GET)
+            .path(format!("/repos/{}/{}/contents/logs/.gitkeep", owner, repo));
+        then.status(200)
+            .json_body_obj(&serde_json::json!({ "sha": "abc" }));
+    });
+    let put = server.mock(|when, then| {
+        when.method(PUT);
+        then.status(201);
+    });
+
+    let api_base = server.base_url();
+    let res = seed_empty_dirs(
+        &api_base,
+        token,
+        &format!("{}/{}", owner, repo),
+        branch,
+        &["logs"],
+    )
+    .await;
+    assert!(res.is_ok());
+    put.assert_calls(0);
+}
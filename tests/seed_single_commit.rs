@@ -0,0 +1,152 @@
+use github_client::{seed_dirs_as_single_commit, CopyOptions, CopySelector};
+use httpmock::prelude::*;
+
+#[tokio::test]
+async fn seeds_all_files_in_one_commit() {
+    let server = MockServer::start();
+    let token = "testtoken";
+
+    // Source repo and its tree
+    let _src_repo = server.mock(|when, then| {
+        when.method(GET).path("/repos/org/service-template");
+        then.status(200)
+            .json_body_obj(&serde_json::json!({ "default_branch": "main" }));
+    });
+    let _src_tree = server.mock(|when, then| {
+        when.method(GET)
+            .path("/repos/org/service-template/git/trees/main")
+            .query_param("recursive", "1");
+        then.status(200).json_body_obj(&serde_json::json!({
+            "tree": [
+                { "path": "terraform", "type": "tree", "sha": "t0" },
+                { "path": "terraform/main.tf", "type": "blob", "sha": "s1" },
+                { "path": "helm/values.yaml", "type": "blob", "sha": "s2" },
+                { "path": "README.md", "type": "blob", "sha": "s3" }
+            ]
+        }));
+    });
+    let _blob1 = server.mock(|when, then| {
+        when.method(GET)
+            .path("/repos/org/service-template/git/blobs/s1");
+        then.status(200)
+            .json_body_obj(&serde_json::json!({ "content": "dGY=\n", "encoding": "base64" }));
+    });
+    let _blob2 = server.mock(|when, then| {
+        when.method(GET)
+            .path("/repos/org/service-template/git/blobs/s2");
+        then.status(200)
+            .json_body_obj(&serde_json::json!({ "content": "aGVsbQ==", "encoding": "base64" }));
+    });
+
+    // Target branch head and current tree (helm/values.yaml already exists)
+    let _head = server.mock(|when, then| {
+        when.method(GET)
+            .path("/repos/org/new-svc/git/ref/heads/main");
+        then.status(200)
+            .json_body_obj(&serde_json::json!({ "object": { "sha": "head1" } }));
+    });
+    let _head_commit = server.mock(|when, then| {
+        when.method(GET)
+            .path("/repos/org/new-svc/git/commits/head1");
+        then.status(200)
+            .json_body_obj(&serde_json::json!({ "tree": { "sha": "basetree" } }));
+    });
+    let _dst_tree = server.mock(|when, then| {
+        when.method(GET)
+            .path("/repos/org/new-svc/git/trees/basetree")
+            .query_param("recursive", "1");
+        then.status(200).json_body_obj(&serde_json::json!({
+            "tree": [{ "path": "helm/values.yaml", "type": "blob", "sha": "x" }]
+        }));
+    });
+
+    let create_blob = server.mock(|when, then| {
+        when.method(POST)
+            .path("/repos/org/new-svc/git/blobs")
+            .json_body_obj(&serde_json::json!({ "content": "dGY=", "encoding": "base64" }));
+        then.status(201)
+            .json_body_obj(&serde_json::json!({ "sha": "nb1" }));
+    });
+    let create_tree = server.mock(|when, then| {
+        when.method(POST)
+            .path("/repos/org/new-svc/git/trees")
+            .json_body_obj(&serde_json::json!({
+                "base_tree": "basetree",
+                "tree": [
+                    { "path": "terraform/main.tf", "mode": "100644", "type": "blob", "sha": "nb1" }
+                ]
+            }));
+        then.status(201)
+            .json_body_obj(&serde_json::json!({ "sha": "newtree" }));
+    });
+    let create_commit = server.mock(|when, then| {
+        when.method(POST)
+            .path("/repos/org/new-svc/git/commits")
+            .json_body_obj(&serde_json::json!({
+                "message": "chore: seed scaffolding",
+                "tree": "newtree",
+                "parents": ["head1"]
+            }));
+        then.status(201)
+            .json_body_obj(&serde_json::json!({ "sha": "c1" }));
+    });
+    let update_ref = server.mock(|when, then| {
+        when.method(PATCH)
+            .path("/repos/org/new-svc/git/refs/heads/main")
+            .json_body_obj(&serde_json::json!({ "sha": "c1", "force": false }));
+        then.status(200);
+    });
+
+    let api_base = server.base_url();
+    let res = seed_dirs_as_single_commit(
+        &api_base,
+        token,
+        "org/service-template",
+        "org/new-svc",
+        "main",
+        &CopySelector::prefixes(&["terraform/", "helm/"]),
+        &CopyOptions::default(),
+    )
+    .await
+    .expect("should seed");
+
+    assert_eq!(res.commit.as_deref(), Some("c1"));
+    assert_eq!(res.seeded, vec!["terraform/main.tf".to_string()]);
+    assert_eq!(res.skipped, vec!["helm/values.yaml".to_string()]);
+    create_blob.assert_calls(1);
+    create_tree.assert();
+    create_commit.assert();
+    update_ref.assert();
+}
+
+#[tokio::test]
+async fn errors_clearly_when_target_branch_missing() {
+    let server = MockServer::start();
+    let token = "testtoken";
+
+    let _src_repo = server.mock(|when, then| {
+        when.method(GET).path("/repos/org/service-template");
+        then.status(200)
+            .json_body_obj(&serde_json::json!({ "default_branch": "main" }));
+    });
+    let _head = server.mock(|when, then| {
+        when.method(GET)
+            .path("/repos/org/new-svc/git/ref/heads/main");
+        then.status(404);
+    });
+
+    let api_base = server.base_url();
+    let err = seed_dirs_as_single_commit(
+        &api_base,
+        token,
+        "org/service-template",
+        "org/new-svc",
+        "main",
+        &CopySelector::prefixes(&["terraform/"]),
+        &CopyOptions::default(),
+    )
+    .await
+    .unwrap_err();
+
+    assert!(err.is_not_found(), "{:?}", err);
+}
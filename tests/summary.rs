@@ -0,0 +1,119 @@
+use github_client::{
+    BatchReport, CopyReport, EnvironmentReport, ProvisionSummary, ReconcileStatus, RepoResponse,
+};
+
+fn repo() -> RepoResponse {
+    RepoResponse {
+        full_name: "me/new-svc".to_string(),
+        html_url: "https://github.com/me/new-svc".to_string(),
+        default_branch: "main".to_string(),
+    }
+}
+
+#[test]
+fn serializes_full_summary() {
+    let mut summary = ProvisionSummary::new(&repo(), true);
+    summary.branches_created.push("dev".to_string());
+    summary.environments.push(EnvironmentReport {
+        name: "dev".to_string(),
+        branch_policies_added: vec!["feature/*".to_string()],
+        branch_policies_existing: vec!["dev".to_string()],
+        branch_policies_failed: Vec::new(),
+        branch_policies_removed: Vec::new(),
+    });
+    summary.add_copy_report(CopyReport {
+        seeded: vec!["terraform/main.tf".to_string()],
+        skipped: vec!["helm/values.yaml".to_string()],
+        ..Default::default()
+    });
+    summary.protections.push("main (ruleset)".to_string());
+    summary.add_reconcile_step("branch 'dev'", ReconcileStatus::Compliant);
+
+    let value = serde_json::to_value(&summary).unwrap();
+    assert_eq!(
+        value,
+        serde_json::json!({
+            "full_name": "me/new-svc",
+            "html_url": "https://github.com/me/new-svc",
+            "default_branch": "main",
+            "created": true,
+            "branches_created": ["dev"],
+            "environments": [{
+                "name": "dev",
+                "branch_policies_added": ["feature/*"],
+                "branch_policies_existing": ["dev"],
+                "branch_policies_failed": [],
+                "branch_policies_removed": []
+            }],
+            "files_seeded": ["terraform/main.tf"],
+            "files_skipped": ["helm/values.yaml"],
+            "files_failed": [],
+            "files_too_large": [],
+            "protections": ["main (ruleset)"],
+            "invitations": [],
+            "release_pr": null,
+            "reconciled": [{ "step": "branch 'dev'", "status": "compliant" }]
+        })
+    );
+}
+
+#[test]
+fn escapes_special_characters_in_json() {
+    let mut summary = ProvisionSummary::new(&repo(), false);
+    summary.html_url = "https://example.com/\"quoted\"\\path".to_string();
+    summary
+        .files_seeded
+        .push("docs/line\nbreak\t\u{1}.md".to_string());
+
+    let json = serde_json::to_string(&summary).unwrap();
+    assert!(json.contains(r#"\"quoted\"\\path"#), "{}", json);
+    assert!(!json.contains('\n'));
+
+    let parsed: serde_json::Value = serde_json::from_str(&json).unwrap();
+    assert_eq!(parsed["html_url"], "https://example.com/\"quoted\"\\path");
+    assert_eq!(parsed["files_seeded"][0], "docs/line\nbreak\t\u{1}.md");
+}
+
+#[test]
+fn text_output_lists_what_was_done() {
+    let mut summary = ProvisionSummary::new(&repo(), true);
+    summary.branches_created.push("dev".to_string());
+    summary
+        .protections
+        .push("main (branch protection)".to_string());
+
+    summary.release_pr = Some("https://github.com/me/new-svc/pull/1".to_string());
+
+    let text = summary.to_string();
+    assert!(text.starts_with("Created repository me/new-svc\n"));
+    assert!(text.contains("  Default branch: main\n"));
+    assert!(text.contains("  Branches:       dev\n"));
+    assert!(text.contains("  Protection:     main (branch protection)\n"));
+    assert!(text.contains("  Release PR:     https://github.com/me/new-svc/pull/1\n"));
+    assert!(!text.contains("Files seeded"));
+}
+
+#[test]
+fn batch_report_lists_each_repository() {
+    let mut report = BatchReport::default();
+    report.add_success("new-svc", ProvisionSummary::new(&repo(), true));
+    report.add_failure("other-svc", "Validation failed: name is taken");
+    assert_eq!(report.failed(), ["other-svc"]);
+
+    let text = report.to_string();
+    assert!(
+        text.starts_with("Provisioned 1 of 2 repositories\n"),
+        "{}",
+        text
+    );
+    assert!(text.contains("  ok     new-svc (https://github.com/me/new-svc)\n"));
+    assert!(text.contains("  FAILED other-svc: Validation failed: name is taken\n"));
+
+    let value = serde_json::to_value(&report).unwrap();
+    assert_eq!(value["repos"][0]["summary"]["full_name"], "me/new-svc");
+    assert!(value["repos"][0].get("error").is_none());
+    assert_eq!(
+        value["repos"][1],
+        serde_json::json!({ "name": "other-svc", "error": "Validation failed: name is taken" })
+    );
+}
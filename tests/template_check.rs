@@ -0,0 +1,89 @@
+use github_client::{verify_template, GithubClientError, RepoMeta};
+use httpmock::prelude::*;
+
+fn mock_template(server: &MockServer, body: serde_json::Value) {
+    server.mock(|when, then| {
+        when.method(GET).path("/repos/org/service-template");
+        then.status(200).json_body_obj(&body);
+    });
+}
+
+#[tokio::test]
+async fn accepts_a_template() {
+    let server = MockServer::start();
+    mock_template(
+        &server,
+        serde_json::json!({
+            "default_branch": "main",
+            "is_template": true,
+            "private": true,
+            "archived": false
+        }),
+    );
+
+    let meta = verify_template(&server.base_url(), "testtoken", "org/service-template")
+        .await
+        .unwrap();
+    assert_eq!(
+        meta,
+        RepoMeta {
+            default_branch: "main".to_string(),
+            is_template: true,
+            private: true,
+            archived: false,
+        }
+    );
+}
+
+#[tokio::test]
+async fn rejects_a_repository_not_marked_as_template() {
+    let server = MockServer::start();
+    mock_template(
+        &server,
+        serde_json::json!({ "default_branch": "main", "is_template": false }),
+    );
+
+    let err = verify_template(&server.base_url(), "testtoken", "org/service-template")
+        .await
+        .unwrap_err();
+    assert!(matches!(err, GithubClientError::InvalidInput(_)));
+    assert!(
+        err.to_string()
+            .contains("exists but is not marked as a template"),
+        "{}",
+        err
+    );
+}
+
+#[tokio::test]
+async fn rejects_an_archived_template() {
+    let server = MockServer::start();
+    mock_template(
+        &server,
+        serde_json::json!({ "default_branch": "main", "is_template": true, "archived": true }),
+    );
+
+    let err = verify_template(&server.base_url(), "testtoken", "org/service-template")
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("is archived"), "{}", err);
+}
+
+#[tokio::test]
+async fn explains_a_404() {
+    let server = MockServer::start();
+    server.mock(|when, then| {
+        when.method(GET).path("/repos/org/service-templat");
+        then.status(404)
+            .json_body_obj(&serde_json::json!({ "message": "Not Found" }));
+    });
+
+    let err = verify_template(&server.base_url(), "testtoken", "org/service-templat")
+        .await
+        .unwrap_err();
+    assert!(err.is_not_found());
+    assert_eq!(
+        err.to_string(),
+        "Not found: repository 'org/service-templat' not found or the token has no read access to it"
+    );
+}
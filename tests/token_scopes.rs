@@ -0,0 +1,90 @@
+use github_client::{check_token_scopes, GithubClientError};
+use httpmock::prelude::*;
+
+fn mock_rate_limit(server: &MockServer) {
+    server.mock(|when, then| {
+        when.method(GET).path("/rate_limit");
+        then.status(200).json_body(serde_json::json!({
+            "resources": { "core": { "limit": 5000, "remaining": 4999, "reset": 1700000000 } }
+        }));
+    });
+}
+
+#[tokio::test]
+async fn passes_when_scopes_are_present() {
+    let server = MockServer::start();
+    mock_rate_limit(&server);
+    let m = server.mock(|when, then| {
+        when.method(GET)
+            .path("/user")
+            .header("authorization", "Bearer testtoken");
+        then.status(200)
+            .header("x-oauth-scopes", "repo, delete_repo, read:org")
+            .json_body(serde_json::json!({ "login": "me" }));
+    });
+
+    check_token_scopes(
+        &server.base_url(),
+        "testtoken",
+        &["public_repo", "delete_repo"],
+    )
+    .await
+    .unwrap();
+    m.assert();
+}
+
+#[tokio::test]
+async fn reports_missing_scope() {
+    let server = MockServer::start();
+    mock_rate_limit(&server);
+    let _m = server.mock(|when, then| {
+        when.method(GET).path("/user");
+        then.status(200)
+            .header("x-oauth-scopes", "public_repo")
+            .json_body(serde_json::json!({ "login": "me" }));
+    });
+
+    let err = check_token_scopes(&server.base_url(), "testtoken", &["repo", "public_repo"])
+        .await
+        .unwrap_err();
+    match &err {
+        GithubClientError::MissingScopes { missing } => assert_eq!(missing, &["repo"]),
+        other => panic!("expected MissingScopes, got {:?}", other),
+    }
+    assert_eq!(err.to_string(), "Token is missing scope 'repo'");
+}
+
+#[tokio::test]
+async fn skips_check_without_scopes_header() {
+    let server = MockServer::start();
+    mock_rate_limit(&server);
+    let _m = server.mock(|when, then| {
+        when.method(GET).path("/user");
+        then.status(200)
+            .json_body(serde_json::json!({ "login": "me" }));
+    });
+
+    check_token_scopes(&server.base_url(), "testtoken", &["repo", "delete_repo"])
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn empty_scopes_header_means_no_scopes() {
+    let server = MockServer::start();
+    mock_rate_limit(&server);
+    let _m = server.mock(|when, then| {
+        when.method(GET).path("/user");
+        then.status(200)
+            .header("x-oauth-scopes", "")
+            .json_body(serde_json::json!({ "login": "me" }));
+    });
+
+    let err = check_token_scopes(&server.base_url(), "testtoken", &["repo", "delete_repo"])
+        .await
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Token is missing scopes 'repo', 'delete_repo'"
+    );
+}
@@ -0,0 +1,72 @@
+use github_client::{set_repo_topics, GithubClientError};
+use httpmock::prelude::*;
+
+#[tokio::test]
+async fn puts_normalized_topics() {
+    let server = MockServer::start();
+    let m = server.mock(|when, then| {
+        when.method(PUT)
+            .path("/repos/me/new-repo/topics")
+            .header("accept", "application/vnd.github+json")
+            .header("authorization", "Bearer testtoken")
+            .json_body(serde_json::json!({ "names": ["service", "rust", "data-platform"] }));
+        then.status(200)
+            .json_body_obj(&serde_json::json!({ "names": ["service", "rust", "data-platform"] }));
+    });
+
+    set_repo_topics(
+        &server.base_url(),
+        "testtoken",
+        "me/new-repo",
+        &["Service", "rust", "data_platform"],
+    )
+    .await
+    .unwrap();
+    m.assert();
+}
+
+#[tokio::test]
+async fn rejects_invalid_topic_before_calling_github() {
+    let server = MockServer::start();
+    let m = server.mock(|when, then| {
+        when.method(PUT).path("/repos/me/new-repo/topics");
+        then.status(200);
+    });
+
+    let res = set_repo_topics(&server.base_url(), "testtoken", "me/new-repo", &["c++"]).await;
+    match res {
+        Err(GithubClientError::InvalidInput(msg)) => assert!(msg.contains("'c++'"), "{}", msg),
+        other => panic!("expected InvalidInput, got {:?}", other),
+    }
+    m.assert_calls(0);
+}
+
+#[tokio::test]
+async fn reports_rejected_topics_as_validation_error() {
+    let server = MockServer::start();
+    let _m = server.mock(|when, then| {
+        when.method(PUT).path("/repos/me/new-repo/topics");
+        then.status(422).body(
+            "{\"message\":\"Validation Failed\",\"errors\":[{\"resource\":\"Repository\",\"field\":\"topics\",\"code\":\"invalid\",\"message\":\"reserved is a reserved topic\"}]}",
+        );
+    });
+
+    let res = set_repo_topics(
+        &server.base_url(),
+        "testtoken",
+        "me/new-repo",
+        &["reserved"],
+    )
+    .await;
+    match res {
+        Err(GithubClientError::ValidationFailed { message, errors }) => {
+            assert!(
+                message.contains("set topics on 'me/new-repo'"),
+                "{}",
+                message
+            );
+            assert_eq!(errors[0].field.as_deref(), Some("topics"));
+        }
+        other => panic!("expected ValidationFailed, got {:?}", other),
+    }
+}
@@ -0,0 +1,93 @@
+use github_client::{list_source_paths, verify_seed, CopySelector};
+use httpmock::prelude::*;
+
+#[tokio::test]
+async fn reports_missing_paths() {
+    let server = MockServer::start();
+    let owner = "me";
+    let repo = "new-repo";
+    let branch = "main";
+    let token = "testtoken";
+
+    let _present = server.mock(|when, then| {
+        when.method(GET)
+            .path(format!(
+                "/repos/{}/{}/contents/terraform/main.tf",
+                owner, repo
+            ))
+            .query_param("ref", branch);
+        then.status(200)
+            .json_body_obj(&serde_json::json!({ "sha": "a" }));
+    });
+    let _missing = server.mock(|when, then| {
+        when.method(GET)
+            .path(format!(
+                "/repos/{}/{}/contents/helm/values.yaml",
+                owner, repo
+            ))
+            .query_param("ref", branch);
+        then.status(404);
+    });
+
+    let api_base = server.base_url();
+    let missing = verify_seed(
+        &api_base,
+        token,
+        &format!("{}/{}", owner, repo),
+        branch,
+        &["terraform/main.tf", "helm/values.yaml"],
+    )
+    .await
+    .expect("verification should run");
+
+    assert_eq!(missing, vec!["helm/values.yaml".to_string()]);
+}
+
+#[tokio::test]
+async fn lists_source_paths_matching_globs() {
+    let server = MockServer::start();
+    let token = "testtoken";
+
+    let _src_repo = server.mock(|when, then| {
+        when.method(GET).path("/repos/org/service-template");
+        then.status(200)
+            .json_body_obj(&serde_json::json!({ "default_branch": "main" }));
+    });
+    let _src_tree = server.mock(|when, then| {
+        when.method(GET)
+            .path("/repos/org/service-template/git/trees/main")
+            .query_param("recursive", "1");
+        then.status(200).json_body_obj(&serde_json::json!({
+            "tree": [
+                { "path": "terraform", "type": "tree", "sha": "t0" },
+                { "path": "terraform/main.tf", "type": "blob", "sha": "s1" },
+                { "path": "terraform/secrets.tfvars", "type": "blob", "sha": "s2" },
+                { "path": "helm/values.yaml", "type": "blob", "sha": "s3" },
+                { "path": "helm/README.md", "type": "blob", "sha": "s4" },
+                { "path": ".github/workflows/ci.yml", "type": "blob", "sha": "s5" },
+                { "path": ".github/workflows/release.yml", "type": "blob", "sha": "s6" }
+            ]
+        }));
+    });
+
+    let selector = CopySelector::globs(&[
+        "terraform/**",
+        "helm/**/*.yaml",
+        ".github/workflows/ci.yml",
+        "!**/secrets.tfvars",
+    ])
+    .unwrap();
+    let api_base = server.base_url();
+    let paths = list_source_paths(&api_base, token, "org/service-template", &selector)
+        .await
+        .unwrap();
+
+    assert_eq!(
+        paths,
+        vec![
+            "terraform/main.tf".to_string(),
+            "helm/values.yaml".to_string(),
+            ".github/workflows/ci.yml".to_string(),
+        ]
+    );
+}
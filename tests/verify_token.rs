@@ -0,0 +1,115 @@
+use github_client::{verify_token, GithubClientError, TokenKind};
+use httpmock::prelude::*;
+use serde_json::json;
+
+fn mock_rate_limit(server: &MockServer, remaining: u64) {
+    server.mock(|when, then| {
+        when.method(GET).path("/rate_limit");
+        then.status(200).json_body(json!({
+            "resources": {
+                "core": { "limit": 5000, "remaining": remaining, "reset": 1700000000, "used": 5000 - remaining }
+            },
+            "rate": { "limit": 5000, "remaining": remaining, "reset": 1700000000, "used": 5000 - remaining }
+        }));
+    });
+}
+
+#[tokio::test]
+async fn parses_classic_scopes_and_rate_limit() {
+    let server = MockServer::start();
+    let _user = server.mock(|when, then| {
+        when.method(GET)
+            .path("/user")
+            .header("authorization", "Bearer ghp_classic");
+        then.status(200)
+            .header("x-oauth-scopes", "repo, read:org,  delete_repo")
+            .json_body(json!({ "login": "octocat" }));
+    });
+    mock_rate_limit(&server, 4990);
+
+    let info = verify_token(&server.base_url(), "ghp_classic")
+        .await
+        .unwrap();
+
+    assert_eq!(info.login.as_deref(), Some("octocat"));
+    assert_eq!(info.kind, TokenKind::Classic);
+    assert_eq!(
+        info.scopes,
+        Some(vec![
+            "repo".to_string(),
+            "read:org".to_string(),
+            "delete_repo".to_string()
+        ])
+    );
+    assert_eq!(info.rate_limit.remaining, 4990);
+    assert_eq!(info.rate_limit.reset, 1700000000);
+    info.require_scopes(&["public_repo", "delete_repo"])
+        .unwrap();
+    let err = info
+        .require_scopes(&["repo", "admin:repo_hook", "workflow"])
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Token is missing scopes 'admin:repo_hook', 'workflow'"
+    );
+}
+
+#[tokio::test]
+async fn expired_token_is_unauthorized() {
+    let server = MockServer::start();
+    let _user = server.mock(|when, then| {
+        when.method(GET).path("/user");
+        then.status(401)
+            .json_body(json!({ "message": "Bad credentials" }));
+    });
+    let rate_limit = server.mock(|when, then| {
+        when.method(GET).path("/rate_limit");
+        then.status(200);
+    });
+
+    let err = verify_token(&server.base_url(), "ghp_expired")
+        .await
+        .unwrap_err();
+
+    assert!(matches!(err, GithubClientError::Unauthorized), "{:?}", err);
+    rate_limit.assert_calls(0);
+}
+
+#[tokio::test]
+async fn detects_fine_grained_tokens_without_scopes() {
+    let server = MockServer::start();
+    let _user = server.mock(|when, then| {
+        when.method(GET).path("/user");
+        then.status(200).json_body(json!({ "login": "octocat" }));
+    });
+    mock_rate_limit(&server, 5000);
+
+    let info = verify_token(&server.base_url(), "github_pat_11ABCDEF")
+        .await
+        .unwrap();
+
+    assert_eq!(info.kind, TokenKind::FineGrained);
+    assert_eq!(info.scopes, None);
+    // Nothing to compare against, so the scope check passes
+    info.require_scopes(&["repo"]).unwrap();
+}
+
+#[tokio::test]
+async fn installation_tokens_skip_the_user_endpoint() {
+    let server = MockServer::start();
+    let user = server.mock(|when, then| {
+        when.method(GET).path("/user");
+        then.status(403)
+            .json_body(json!({ "message": "Resource not accessible by integration" }));
+    });
+    mock_rate_limit(&server, 0);
+
+    let info = verify_token(&server.base_url(), "ghs_installation")
+        .await
+        .unwrap();
+
+    user.assert_calls(0);
+    assert_eq!(info.kind, TokenKind::Installation);
+    assert_eq!(info.login, None);
+    assert_eq!(info.rate_limit.remaining, 0);
+}
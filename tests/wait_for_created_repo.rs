@@ -0,0 +1,80 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use github_client::{wait_for_created_repo, GithubClientError};
+use httpmock::prelude::*;
+use httpmock::HttpMockResponse;
+
+#[tokio::test]
+async fn tolerates_transient_404_after_create() {
+    let server = MockServer::start();
+    let owner = "me";
+    let repo = "new-repo";
+    let token = "testtoken";
+
+    // 404 twice (replication lag), then the repository becomes visible
+    let calls = Arc::new(AtomicUsize::new(0));
+    let seen = calls.clone();
+    let m = server.mock(|when, then| {
+        when.method(GET).path(format!("/repos/{}/{}", owner, repo));
+        then.respond_with(move |_req| {
+            if seen.fetch_add(1, Ordering::SeqCst) < 2 {
+                HttpMockResponse::builder()
+                    .status(404)
+                    .body("{\"message\":\"Not Found\"}")
+                    .build()
+            } else {
+                HttpMockResponse::builder()
+                    .status(200)
+                    .header("content-type", "application/json")
+                    .body(
+                        serde_json::json!({
+                            "full_name": format!("{}/{}", owner, repo),
+                            "html_url": format!("https://github.com/{}/{}", owner, repo),
+                            "default_branch": "main"
+                        })
+                        .to_string(),
+                    )
+                    .build()
+            }
+        });
+    });
+
+    let api_base = server.base_url();
+    let res = wait_for_created_repo(
+        &api_base,
+        token,
+        &format!("{}/{}", owner, repo),
+        Duration::from_secs(10),
+    )
+    .await
+    .expect("should succeed after transient 404s");
+
+    assert_eq!(res.default_branch, "main");
+    m.assert_calls(3);
+}
+
+#[tokio::test]
+async fn gives_up_after_max_wait() {
+    let server = MockServer::start();
+    let owner = "me";
+    let repo = "new-repo";
+    let token = "testtoken";
+
+    let _m = server.mock(|when, then| {
+        when.method(GET).path(format!("/repos/{}/{}", owner, repo));
+        then.status(404).body("{\"message\":\"Not Found\"}");
+    });
+
+    let api_base = server.base_url();
+    let res = wait_for_created_repo(
+        &api_base,
+        token,
+        &format!("{}/{}", owner, repo),
+        Duration::from_millis(500),
+    )
+    .await;
+
+    assert!(matches!(res, Err(GithubClientError::Timeout { .. })));
+}
@@ -0,0 +1,125 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use github_client::{wait_for_repo_ready, GithubClientError};
+use httpmock::prelude::*;
+use httpmock::HttpMockResponse;
+
+fn json(status: u16, body: serde_json::Value) -> HttpMockResponse {
+    HttpMockResponse::builder()
+        .status(status)
+        .header("content-type", "application/json")
+        .body(body.to_string())
+        .build()
+}
+
+#[tokio::test]
+async fn waits_for_ref_and_tree() {
+    let server = MockServer::start();
+
+    // The ref is missing, then the repository is empty, then the ref appears
+    let ref_calls = Arc::new(AtomicUsize::new(0));
+    let seen = ref_calls.clone();
+    let ref_mock = server.mock(|when, then| {
+        when.method(GET)
+            .path("/repos/me/new-repo/git/ref/heads/main");
+        then.respond_with(move |_req| match seen.fetch_add(1, Ordering::SeqCst) {
+            0 => json(404, serde_json::json!({ "message": "Not Found" })),
+            1 => json(
+                409,
+                serde_json::json!({ "message": "Git Repository is empty." }),
+            ),
+            _ => json(
+                200,
+                serde_json::json!({ "ref": "refs/heads/main", "object": { "sha": "abc" } }),
+            ),
+        });
+    });
+
+    // The tree is empty once before the template content lands
+    let tree_calls = Arc::new(AtomicUsize::new(0));
+    let seen = tree_calls.clone();
+    let tree_mock = server.mock(|when, then| {
+        when.method(GET).path("/repos/me/new-repo/git/trees/main");
+        then.respond_with(move |_req| {
+            if seen.fetch_add(1, Ordering::SeqCst) < 1 {
+                json(200, serde_json::json!({ "sha": "abc", "tree": [] }))
+            } else {
+                json(
+                    200,
+                    serde_json::json!({
+                        "sha": "abc",
+                        "tree": [{ "path": "README.md", "type": "blob", "sha": "def" }]
+                    }),
+                )
+            }
+        });
+    });
+
+    wait_for_repo_ready(
+        &server.base_url(),
+        "testtoken",
+        "me/new-repo",
+        "main",
+        Duration::from_secs(10),
+    )
+    .await
+    .expect("should succeed once content is there");
+
+    ref_mock.assert_calls(3);
+    tree_mock.assert_calls(2);
+}
+
+#[tokio::test]
+async fn timeout_names_what_is_missing() {
+    let server = MockServer::start();
+    let _ref = server.mock(|when, then| {
+        when.method(GET)
+            .path("/repos/me/new-repo/git/ref/heads/main");
+        then.status(200)
+            .header("content-type", "application/json")
+            .json_body(serde_json::json!({ "ref": "refs/heads/main", "object": { "sha": "abc" } }));
+    });
+    let _tree = server.mock(|when, then| {
+        when.method(GET).path("/repos/me/new-repo/git/trees/main");
+        then.status(409)
+            .body("{\"message\":\"Git Repository is empty.\"}");
+    });
+
+    let err = wait_for_repo_ready(
+        &server.base_url(),
+        "testtoken",
+        "me/new-repo",
+        "main",
+        Duration::from_millis(500),
+    )
+    .await
+    .unwrap_err();
+
+    assert!(matches!(err, GithubClientError::Timeout { .. }));
+    assert!(err.to_string().contains("tree of 'main'"), "{}", err);
+}
+
+#[tokio::test]
+async fn fails_fast_on_forbidden() {
+    let server = MockServer::start();
+    let m = server.mock(|when, then| {
+        when.method(GET)
+            .path("/repos/me/new-repo/git/ref/heads/main");
+        then.status(403)
+            .body("{\"message\":\"Resource not accessible\"}");
+    });
+
+    let res = wait_for_repo_ready(
+        &server.base_url(),
+        "testtoken",
+        "me/new-repo",
+        "main",
+        Duration::from_secs(10),
+    )
+    .await;
+
+    assert!(matches!(res, Err(GithubClientError::Forbidden { .. })));
+    m.assert_calls(1);
+}
@@ -0,0 +1,158 @@
+use github_client::{create_webhook, GithubClientError, WebhookConfig};
+use httpmock::prelude::*;
+
+fn config() -> WebhookConfig {
+    WebhookConfig {
+        secret: Some("s3cret".to_string()),
+        events: vec!["push".to_string(), "pull_request".to_string()],
+        ..WebhookConfig::new("https://ci.example.com/hook")
+    }
+}
+
+#[tokio::test]
+async fn creates_webhook_when_none_exists() {
+    let server = MockServer::start();
+    let owner = "me";
+    let repo = "new-repo";
+    let token = "testtoken";
+
+    let list = server.mock(|when, then| {
+        when.method(GET)
+            .path(format!("/repos/{}/{}/hooks", owner, repo));
+        then.status(200).json_body_obj(&serde_json::json!([
+            { "id": 7, "config": { "url": "https://other.example.com/hook" } }
+        ]));
+    });
+    let create = server.mock(|when, then| {
+        when.method(POST)
+            .path(format!("/repos/{}/{}/hooks", owner, repo))
+            .header("authorization", "Bearer testtoken")
+            .json_body_obj(&serde_json::json!({
+                "name": "web",
+                "active": true,
+                "events": ["push", "pull_request"],
+                "config": {
+                    "url": "https://ci.example.com/hook",
+                    "content_type": "json",
+                    "secret": "s3cret",
+                    "insecure_ssl": "0"
+                }
+            }));
+        then.status(201)
+            .json_body_obj(&serde_json::json!({ "id": 42, "config": {} }));
+    });
+
+    let api_base = server.base_url();
+    let id = create_webhook(&api_base, token, &format!("{}/{}", owner, repo), &config())
+        .await
+        .unwrap();
+    assert_eq!(id, 42);
+    list.assert();
+    create.assert();
+}
+
+#[tokio::test]
+async fn updates_existing_webhook_with_same_url() {
+    let server = MockServer::start();
+    let owner = "me";
+    let repo = "new-repo";
+    let token = "testtoken";
+
+    let _list = server.mock(|when, then| {
+        when.method(GET)
+            .path(format!("/repos/{}/{}/hooks", owner, repo));
+        then.status(200).json_body_obj(&serde_json::json!([
+            { "id": 7, "config": { "url": "https://ci.example.com/hook" } }
+        ]));
+    });
+    let create = server.mock(|when, then| {
+        when.method(POST)
+            .path(format!("/repos/{}/{}/hooks", owner, repo));
+        then.status(201);
+    });
+    let update = server.mock(|when, then| {
+        when.method(PATCH)
+            .path(format!("/repos/{}/{}/hooks/7", owner, repo))
+            .json_body_obj(&serde_json::json!({
+                "active": true,
+                "events": ["push", "pull_request"],
+                "config": {
+                    "url": "https://ci.example.com/hook",
+                    "content_type": "json",
+                    "secret": "s3cret",
+                    "insecure_ssl": "0"
+                }
+            }));
+        then.status(200)
+            .json_body_obj(&serde_json::json!({ "id": 7, "config": {} }));
+    });
+
+    let api_base = server.base_url();
+    let id = create_webhook(&api_base, token, &format!("{}/{}", owner, repo), &config())
+        .await
+        .unwrap();
+    assert_eq!(id, 7);
+    update.assert();
+    create.assert_calls(0);
+}
+
+#[tokio::test]
+async fn create_webhook_returns_validation_error_for_unreachable_url() {
+    let server = MockServer::start();
+    let owner = "me";
+    let repo = "new-repo";
+    let token = "testtoken";
+
+    let _list = server.mock(|when, then| {
+        when.method(GET)
+            .path(format!("/repos/{}/{}/hooks", owner, repo));
+        then.status(200).json_body_obj(&serde_json::json!([]));
+    });
+    let _create = server.mock(|when, then| {
+        when.method(POST)
+            .path(format!("/repos/{}/{}/hooks", owner, repo));
+        then.status(422).body(
+            "{\"message\":\"Validation Failed\",\"errors\":[{\"resource\":\"Hook\",\"code\":\"custom\",\"message\":\"Sorry, the URL host localhost is not supported because it isn't reachable over the public Internet\"}]}",
+        );
+    });
+
+    let api_base = server.base_url();
+    let res = create_webhook(&api_base, token, &format!("{}/{}", owner, repo), &config()).await;
+    match res {
+        Err(GithubClientError::ValidationFailed { errors, .. }) => {
+            assert_eq!(errors.len(), 1);
+            assert!(errors[0]
+                .message
+                .as_deref()
+                .unwrap_or_default()
+                .contains("isn't reachable"));
+        }
+        other => panic!("expected ValidationFailed, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn empty_event_list_falls_back_to_defaults() {
+    let server = MockServer::start();
+
+    let _list = server.mock(|when, then| {
+        when.method(GET).path("/repos/me/new-repo/hooks");
+        then.status(200).json_body_obj(&serde_json::json!([]));
+    });
+    let create = server.mock(|when, then| {
+        when.method(POST)
+            .path("/repos/me/new-repo/hooks")
+            .json_body_includes(r#"{ "events": ["push", "pull_request"] }"#);
+        then.status(201)
+            .json_body_obj(&serde_json::json!({ "id": 5, "config": {} }));
+    });
+
+    let config = WebhookConfig {
+        events: Vec::new(),
+        ..WebhookConfig::new("https://ci.example.com/hook")
+    };
+    create_webhook(&server.base_url(), "testtoken", "me/new-repo", &config)
+        .await
+        .unwrap();
+    create.assert();
+}